@@ -0,0 +1,21 @@
+use std::{fs, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use lib_rs::encoding::deflate::{compress, decompress, Level};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let data = fs::read("./assets/img.jpg").unwrap();
+    for level in [Level::FASTEST, Level::DEFAULT, Level::BEST] {
+        c.bench_function(&format!("deflate compress level {}", level.value()), |b| {
+            b.iter(|| compress(black_box(&data), level))
+        });
+    }
+    let compressed = compress(&data, Level::DEFAULT);
+    c.bench_function("deflate decompress", |b| {
+        b.iter(|| decompress(black_box(&compressed)))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
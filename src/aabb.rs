@@ -1,10 +1,14 @@
 use std::{f32::INFINITY, ops::Range};
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    linear_algebra::{vector::vec3, Vector3},
+    linear_algebra::{vector::vec3, Matrix4, Vector3},
     ray::Ray,
 };
 
+/// an axis-aligned bounding box, the default one is empty and contains nothing
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Aabb {
     pub min: Vector3,
     pub max: Vector3,
@@ -59,17 +63,232 @@ impl Aabb {
         self.max.z = p.z.max(self.max.z);
         self.min.z = p.z.min(self.min.z);
     }
-    /// ray intersects, only checks intersection rather than a full HitRecord
+    /// the smallest box containing both
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self::from_min_max(self.min.min(other.min), self.max.max(other.max))
+    }
+    /// the common part, empty if they don't overlap
+    pub fn intersection(&self, other: &Aabb) -> Aabb {
+        Self::from_min_max(self.min.max(other.min), self.max.min(other.max))
+    }
+    /// a flat box is not empty, it still contains points
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+    /// zero for an empty box
+    pub fn size(&self) -> Vector3 {
+        if self.is_empty() {
+            return Vector3::ZERO;
+        }
+        self.max - self.min
+    }
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+    pub fn surface_area(&self) -> f32 {
+        let s = self.size();
+        2.0 * (s.x * s.y + s.y * s.z + s.z * s.x)
+    }
+    pub fn volume(&self) -> f32 {
+        let s = self.size();
+        s.x * s.y * s.z
+    }
+    /// index of the largest dimension, x on ties
+    pub fn longest_axis(&self) -> usize {
+        let s = self.size();
+        if s.x >= s.y && s.x >= s.z {
+            0
+        } else if s.y >= s.z {
+            1
+        } else {
+            2
+        }
+    }
+    /// move every face outward by `d`, or inward for a negative one
+    pub fn expand_by_scalar(&mut self, d: f32) {
+        let d = vec3(d, d, d);
+        self.min = self.min - d;
+        self.max = self.max + d;
+    }
+    /// if `other` is fully inside, an empty box is inside any box
+    pub fn contains_aabb(&self, other: &Aabb) -> bool {
+        other.is_empty() || (self.contains_point(other.min) && self.contains_point(other.max))
+    }
+    /// the box containing this one under an affine transform, by Jim Arvo's method in
+    /// Graphics Gems, tighter than nothing but not than the transformed object
+    pub fn transform(&self, m: &Matrix4) -> Aabb {
+        if self.is_empty() {
+            return Self::empty();
+        }
+        let mut min = [0.0; 3];
+        let mut max = [0.0; 3];
+        for i in 0..3 {
+            min[i] = m.get(i, 3);
+            max[i] = m.get(i, 3);
+            for j in 0..3 {
+                let factor = m.get(i, j);
+                // skipped so unbounded boxes don't give 0 * inf = NaN
+                if factor == 0.0 {
+                    continue;
+                }
+                let (a, b) = (factor * self.min[j], factor * self.max[j]);
+                min[i] += a.min(b);
+                max[i] += a.max(b);
+            }
+        }
+        Self::from_min_max(vec3(min[0], min[1], min[2]), vec3(max[0], max[1], max[2]))
+    }
+    /// slab test, the `t` where the ray enters and exits the box clipped to the range, none if
+    /// the part of the ray within the range misses it. boundaries count as inside.
+    pub fn intersect_ray_interval(&self, ray: Ray, range: Range<f32>) -> Option<(f32, f32)> {
+        let (mut t_near, mut t_far) = (range.start, range.end);
+        for axis in 0..3 {
+            let (o, d) = (ray.origin[axis], ray.direction[axis]);
+            let (min, max) = (self.min[axis], self.max[axis]);
+            if d == 0.0 {
+                // parallel to the slab, 0/0 would give NaN for rays on its boundary
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            t_near = t_near.max(t0.min(t1));
+            t_far = t_far.min(t0.max(t1));
+        }
+        (t_near <= t_far).then_some((t_near, t_far))
+    }
+    /// if the part of the ray within the range crosses the box, including when the whole
+    /// part is inside it
     pub fn intersect_ray(&self, ray: Ray, range: Range<f32>) -> bool {
-        let p = ray.origin;
-        let d = ray.direction;
-        let dir_inv = vec3(1.0 / d.x, 1.0 / d.y, 1.0 / d.z);
-        let t_min = (self.min - p) * dir_inv;
-        let t_max = (self.max - p) * dir_inv;
-        let t1 = t_min.min(t_max);
-        let t2 = t_min.max(t_max);
-        let t_near = t1.x.max(t1.y).max(t1.z);
-        let t_far = t2.x.min(t2.y).min(t2.z);
-        range.contains(&t_near) || range.contains(&t_far)
+        self.intersect_ray_interval(ray, range).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn unit() -> Aabb {
+        Aabb::from_min_max(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn algebra() {
+        let a = unit();
+        let b = Aabb::from_min_max(vec3(0.0, 0.0, 0.0), vec3(3.0, 2.0, 1.0));
+        assert_eq!(a.union(&b), Aabb::from_min_max(a.min, vec3(3.0, 2.0, 1.0)));
+        assert_eq!(a.intersection(&b), Aabb::from_min_max(Vector3::ZERO, a.max));
+        assert_eq!(b.size(), vec3(3.0, 2.0, 1.0));
+        assert_eq!(b.center(), vec3(1.5, 1.0, 0.5));
+        assert_eq!(b.surface_area(), 22.0);
+        assert_eq!(b.volume(), 6.0);
+        assert_eq!(b.longest_axis(), 0);
+        assert_eq!(
+            Aabb::from_min_max(Vector3::ZERO, vec3(1.0, 1.0, 2.0)).longest_axis(),
+            2
+        );
+        assert!(a.contains_aabb(&a.intersection(&b)));
+        assert!(!a.contains_aabb(&b));
+        let mut grown = b;
+        grown.expand_by_scalar(1.0);
+        assert!(grown.contains_aabb(&a) && grown.contains_aabb(&b));
+        // empty boxes
+        let empty = Aabb::empty();
+        assert!(empty.is_empty() && !a.is_empty());
+        assert!(a
+            .intersection(&b.union(&empty).intersection(&empty))
+            .is_empty());
+        assert_eq!(a.union(&empty), a);
+        assert_eq!(empty.size(), Vector3::ZERO);
+        assert_eq!((empty.volume(), empty.surface_area()), (0.0, 0.0));
+        assert!(a.contains_aabb(&empty) && !empty.contains_aabb(&a));
+        let far = Aabb::from_min_max(vec3(5.0, 5.0, 5.0), vec3(6.0, 6.0, 6.0));
+        assert!(a.intersection(&far).is_empty());
+        // flat boxes aren't empty
+        assert!(!Aabb::from_min_max(Vector3::ZERO, vec3(1.0, 0.0, 1.0)).is_empty());
+    }
+    #[test]
+    fn transform() {
+        let b = Aabb::from_min_max(vec3(0.0, 0.0, 0.0), vec3(3.0, 2.0, 1.0));
+        let m = Matrix4::translation(vec3(1.0, 0.0, 0.0)) * Matrix4::rotation_z(FRAC_PI_2);
+        let t = b.transform(&m);
+        assert!((t.min - vec3(-1.0, 0.0, 0.0)).length() < 1e-5, "{t:?}");
+        assert!((t.max - vec3(1.0, 3.0, 1.0)).length() < 1e-5, "{t:?}");
+        // same as the box around the transformed corners
+        let m = Matrix4::rotation(vec3(1.0, 2.0, 3.0), 0.7) * Matrix4::scale(vec3(2.0, 1.0, 0.5));
+        let corners: Vec<Vector3> = (0..8)
+            .map(|i| {
+                let corner = vec3(
+                    if i & 1 == 0 { b.min.x } else { b.max.x },
+                    if i & 2 == 0 { b.min.y } else { b.max.y },
+                    if i & 4 == 0 { b.min.z } else { b.max.z },
+                );
+                m.transform_point(corner)
+            })
+            .collect();
+        let expected = Aabb::from_points(&corners);
+        let t = b.transform(&m);
+        assert!((t.min - expected.min).length() < 1e-5);
+        assert!((t.max - expected.max).length() < 1e-5);
+        assert!(Aabb::empty().transform(&m).is_empty());
+        // unbounded along x stays unbounded and finite elsewhere
+        let slab = Aabb::from_min_max(
+            vec3(-f32::INFINITY, 0.0, 0.0),
+            vec3(f32::INFINITY, 1.0, 1.0),
+        );
+        let t = slab.transform(&Matrix4::translation(vec3(0.0, 5.0, 0.0)));
+        assert_eq!(t.min, vec3(-f32::INFINITY, 5.0, 0.0));
+        assert_eq!(t.max, vec3(f32::INFINITY, 6.0, 1.0));
+    }
+    #[test]
+    fn ray_contract() {
+        let a = unit();
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X);
+        assert_eq!(
+            a.intersect_ray_interval(ray, 0.0..f32::INFINITY),
+            Some((4.0, 6.0))
+        );
+        // clipped to the range
+        assert_eq!(a.intersect_ray_interval(ray, 5.0..5.5), Some((5.0, 5.5)));
+        // a range enclosing the box, and one fully inside it
+        assert!(a.intersect_ray(ray, -f32::INFINITY..f32::INFINITY));
+        assert!(a.intersect_ray(ray, 4.5..5.5));
+        // the range stops before or starts after the box
+        assert!(!a.intersect_ray(ray, 0.0..3.0));
+        assert!(!a.intersect_ray(ray, 7.0..f32::INFINITY));
+        // behind the ray
+        assert!(!a.intersect_ray(
+            Ray::new(vec3(5.0, 0.0, 0.0), Vector3::UNIT_X),
+            0.0..f32::INFINITY
+        ));
+        // starting inside
+        let inside = Ray::new(Vector3::ZERO, vec3(0.0, -2.0, 0.0));
+        assert_eq!(
+            a.intersect_ray_interval(inside, 0.0..f32::INFINITY),
+            Some((0.0, 0.5))
+        );
+        // parallel to a slab, on its boundary and outside it
+        let on_face = Ray::new(vec3(-5.0, 1.0, 0.0), Vector3::UNIT_X);
+        assert_eq!(
+            a.intersect_ray_interval(on_face, 0.0..f32::INFINITY),
+            Some((4.0, 6.0))
+        );
+        assert!(!a.intersect_ray(
+            Ray::new(vec3(-5.0, 1.5, 0.0), Vector3::UNIT_X),
+            0.0..f32::INFINITY
+        ));
+        // grazing an edge and missing it
+        let diagonal = vec3(1.0, 1.0, 0.0);
+        assert!(a.intersect_ray(
+            Ray::new(vec3(-1.0, -3.0, 0.0), diagonal),
+            0.0..f32::INFINITY
+        ));
+        assert!(!a.intersect_ray(
+            Ray::new(vec3(-1.0, -3.5, 0.0), diagonal),
+            0.0..f32::INFINITY
+        ));
+        assert!(!Aabb::empty().intersect_ray(ray, -f32::INFINITY..f32::INFINITY));
     }
 }
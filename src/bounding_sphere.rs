@@ -0,0 +1,280 @@
+//! bounding spheres, fitted to points approximately by Ritter's method or exactly by Welzl's
+use std::ops::Range;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    linear_algebra::{
+        vector::{cross, dot, vec3},
+        Vector3,
+    },
+    query::Overlaps,
+    ray::Ray,
+};
+
+/// a sphere containing a set of points, for quick rejection tests
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct BoundingSphere {
+    pub center: Vector3,
+    pub radius: f32,
+}
+impl BoundingSphere {
+    pub fn new(center: Vector3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+    /// Jack Ritter's approximation in Graphics Gems, fast but up to about 5% larger than
+    /// the minimum
+    pub fn ritter(points: &[Vector3]) -> Self {
+        let Some(&first) = points.first() else {
+            return Self::new(Vector3::ZERO, 0.0);
+        };
+        let farthest = |from: Vector3| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| {
+                    (*a - from)
+                        .length_squared()
+                        .total_cmp(&(*b - from).length_squared())
+                })
+                .unwrap()
+        };
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut sphere = Self::new((a + b) * 0.5, (b - a).length() * 0.5);
+        for p in points {
+            let distance = (*p - sphere.center).length();
+            if distance > sphere.radius {
+                // grow just enough to reach p, keeping the opposite side in place
+                let radius = (sphere.radius + distance) * 0.5;
+                sphere.center =
+                    sphere.center + (*p - sphere.center) * ((radius - sphere.radius) / distance);
+                sphere.radius = radius;
+            }
+        }
+        sphere
+    }
+    /// the minimum enclosing sphere, by Emo Welzl's randomized algorithm in expected linear time.
+    ///
+    /// the points are shuffled with a fixed seed, so the same points always give the same sphere
+    pub fn welzl(points: &[Vector3]) -> Self {
+        if points.is_empty() {
+            return Self::new(Vector3::ZERO, 0.0);
+        }
+        let mut points = points.to_vec();
+        points.shuffle(&mut StdRng::seed_from_u64(0));
+        let end = points.len();
+        move_to_front(&mut points, end, &mut Vec::with_capacity(4))
+    }
+    pub fn contains_point(&self, p: Vector3) -> bool {
+        (p - self.center).length_squared() <= self.radius * self.radius
+    }
+    pub fn bounding_box(&self) -> Aabb {
+        let r = vec3(self.radius, self.radius, self.radius);
+        Aabb::from_min_max(self.center - r, self.center + r)
+    }
+    /// the `t` where the ray enters and exits the sphere clipped to the range, as
+    /// `Aabb::intersect_ray_interval`
+    pub fn intersect_ray_interval(&self, ray: Ray, range: Range<f32>) -> Option<(f32, f32)> {
+        let oc = ray.origin - self.center;
+        let a = dot(ray.direction, ray.direction);
+        let h = dot(ray.direction, oc);
+        let c = dot(oc, oc) - self.radius * self.radius;
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrtd = discriminant.sqrt();
+        let enter = ((-h - sqrtd) / a).max(range.start);
+        let exit = ((-h + sqrtd) / a).min(range.end);
+        (enter <= exit).then_some((enter, exit))
+    }
+    pub fn intersect_ray(&self, ray: Ray, range: Range<f32>) -> bool {
+        self.intersect_ray_interval(ray, range).is_some()
+    }
+}
+impl Overlaps<BoundingSphere> for BoundingSphere {
+    fn overlaps(&self, other: &BoundingSphere) -> bool {
+        let radius = self.radius + other.radius;
+        (self.center - other.center).length_squared() <= radius * radius
+    }
+}
+
+/// the move-to-front variant, recursing at most 4 levels deep: the minimum sphere of the
+/// first `end` points with the `support` points on its boundary
+fn move_to_front(points: &mut [Vector3], end: usize, support: &mut Vec<Vector3>) -> BoundingSphere {
+    let mut sphere = sphere_through(support);
+    if support.len() == 4 {
+        return sphere;
+    }
+    for i in 0..end {
+        let p = points[i];
+        // a little slack so rounding doesn't make points on the boundary fall outside
+        let distance = (p - sphere.center).length();
+        if distance <= sphere.radius * (1.0 + 1e-5) + 1e-6 {
+            continue;
+        }
+        support.push(p);
+        sphere = move_to_front(points, i, support);
+        support.pop();
+        points[..=i].rotate_right(1);
+    }
+    sphere
+}
+
+/// the smallest sphere with all the points on its boundary
+fn sphere_through(points: &[Vector3]) -> BoundingSphere {
+    match *points {
+        // contains nothing, so the first point is always outside
+        [] => BoundingSphere::new(Vector3::ZERO, -1.0),
+        [a] => BoundingSphere::new(a, 0.0),
+        [a, b] => BoundingSphere::new((a + b) * 0.5, (b - a).length() * 0.5),
+        [a, b, c] => {
+            let (ab, ac) = (b - a, c - a);
+            let n = cross(ab, ac);
+            let denom = 2.0 * dot(n, n);
+            if denom < 1e-12 {
+                // collinear, the two farthest apart decide
+                return [(a, b), (a, c), (b, c)]
+                    .map(|(p, q)| sphere_through(&[p, q]))
+                    .into_iter()
+                    .max_by(|s, t| s.radius.total_cmp(&t.radius))
+                    .unwrap();
+            }
+            let offset = (cross(n, ab) * dot(ac, ac) + cross(ac, n) * dot(ab, ab)) / denom;
+            BoundingSphere::new(a + offset, offset.length())
+        }
+        [a, b, c, d, ..] => {
+            let (ab, ac, ad) = (b - a, c - a, d - a);
+            let denom = 2.0 * dot(ab, cross(ac, ad));
+            if denom.abs() < 1e-12 {
+                // coplanar, the circle through three of them is enough
+                return [[a, b, c], [a, b, d], [a, c, d], [b, c, d]]
+                    .map(|t| sphere_through(&t))
+                    .into_iter()
+                    .max_by(|s, t| s.radius.total_cmp(&t.radius))
+                    .unwrap();
+            }
+            let offset = (cross(ac, ad) * dot(ab, ab)
+                + cross(ad, ab) * dot(ac, ac)
+                + cross(ab, ac) * dot(ad, ad))
+                / denom;
+            BoundingSphere::new(a + offset, offset.length())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{linear_algebra::Matrix4, model_3d::primitives};
+
+    fn assert_contains_all(sphere: &BoundingSphere, points: &[Vector3]) {
+        for p in points {
+            let distance = (*p - sphere.center).length();
+            assert!(
+                distance <= sphere.radius * (1.0 + 1e-4),
+                "{p:?} outside {sphere:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn through_points() {
+        let a = vec3(1.0, 0.0, 0.0);
+        let b = vec3(-1.0, 0.0, 0.0);
+        let c = vec3(0.0, 1.0, 0.0);
+        let d = vec3(0.0, 0.0, 1.0);
+        let sphere = sphere_through(&[a, b, c]);
+        assert!(sphere.center.length() < 1e-6 && (sphere.radius - 1.0).abs() < 1e-6);
+        let sphere = sphere_through(&[a, b, c, d]);
+        assert!(sphere.center.length() < 1e-6 && (sphere.radius - 1.0).abs() < 1e-6);
+        let offset = vec3(3.0, -2.0, 5.0);
+        let sphere = sphere_through(&[a + offset, c + offset, d + offset, -c + offset]);
+        assert!((sphere.center - offset).length() < 1e-5);
+    }
+    #[test]
+    fn minimum_sphere() {
+        // points inside don't change the sphere through the extremes
+        let mut points = vec![
+            vec3(2.0, 1.0, 0.0),
+            vec3(-2.0, 1.0, 0.0),
+            vec3(0.0, 1.0, 2.0),
+            vec3(0.0, 1.0, -2.0),
+            vec3(0.0, 3.0, 0.0),
+            vec3(0.0, -1.0, 0.0),
+        ];
+        for i in 0..100 {
+            let f = i as f32;
+            points.push(vec3(
+                (f * 0.7).sin(),
+                1.0 + (f * 1.3).cos(),
+                (f * 0.3).sin(),
+            ));
+        }
+        let sphere = BoundingSphere::welzl(&points);
+        assert!(
+            (sphere.center - vec3(0.0, 1.0, 0.0)).length() < 1e-4,
+            "{sphere:?}"
+        );
+        assert!((sphere.radius - 2.0).abs() < 1e-4);
+        let ritter = BoundingSphere::ritter(&points);
+        assert_contains_all(&ritter, &points);
+        assert!(ritter.radius >= sphere.radius - 1e-4);
+        // a triangle where the minimum sphere only touches the two ends of the long edge
+        let obtuse = [
+            vec3(-3.0, 0.0, 0.0),
+            vec3(3.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ];
+        let sphere = BoundingSphere::welzl(&obtuse);
+        assert!(sphere.center.length() < 1e-5 && (sphere.radius - 3.0).abs() < 1e-5);
+        assert_eq!(BoundingSphere::welzl(&[]).radius, 0.0);
+        assert_eq!(BoundingSphere::welzl(&[obtuse[0]]).radius, 0.0);
+    }
+    #[test]
+    fn from_mesh() {
+        let m = Matrix4::translation(vec3(1.0, 2.0, 3.0)) * Matrix4::scale(vec3(2.0, 2.0, 2.0));
+        let points: Vec<Vector3> = primitives::icosphere(1.0, 2)
+            .vertices()
+            .iter()
+            .map(|p| m.transform_point(*p))
+            .collect();
+        let welzl = BoundingSphere::welzl(&points);
+        let ritter = BoundingSphere::ritter(&points);
+        assert_contains_all(&welzl, &points);
+        assert_contains_all(&ritter, &points);
+        assert!((welzl.center - vec3(1.0, 2.0, 3.0)).length() < 1e-3);
+        assert!((welzl.radius - 2.0).abs() < 1e-3);
+        assert!(ritter.radius < welzl.radius * 1.05);
+        // the same points give the same sphere, to the bit
+        assert_eq!(BoundingSphere::welzl(&points), welzl);
+        // a cube's minimum sphere goes through its corners
+        let cube = primitives::cuboid(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let sphere = BoundingSphere::welzl(cube.vertices());
+        assert!((sphere.radius - 3.0_f32.sqrt()).abs() < 1e-4);
+    }
+    #[test]
+    fn ray_and_overlaps() {
+        let sphere = BoundingSphere::new(Vector3::ZERO, 2.0);
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X);
+        assert_eq!(
+            sphere.intersect_ray_interval(ray, 0.0..f32::INFINITY),
+            Some((3.0, 7.0))
+        );
+        assert_eq!(
+            sphere.intersect_ray_interval(ray, 4.0..5.0),
+            Some((4.0, 5.0))
+        );
+        assert!(!sphere.intersect_ray(ray, 0.0..2.0));
+        assert!(!sphere.intersect_ray(
+            Ray::new(vec3(-5.0, 2.5, 0.0), Vector3::UNIT_X),
+            0.0..f32::INFINITY
+        ));
+        assert!(sphere.overlaps(&BoundingSphere::new(vec3(3.0, 0.0, 0.0), 1.0)));
+        assert!(!sphere.overlaps(&BoundingSphere::new(vec3(3.0, 0.0, 0.0), 0.9)));
+        assert_eq!(sphere.bounding_box().max, vec3(2.0, 2.0, 2.0));
+    }
+}
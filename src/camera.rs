@@ -0,0 +1,350 @@
+//! cameras generating the primary rays of an image
+//!
+//! https://raytracing.github.io/books/RayTracingInOneWeekend.html#positionablecamera
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    linear_algebra::{
+        vector::{cross, vec3},
+        Onb, Vector3,
+    },
+    radian::{Degree, Radian},
+    ray::Ray,
+    sampling,
+};
+
+/// maps points of the image to rays
+pub trait Projection {
+    /// the ray through `(s, t)` in [0, 1]², from the bottom left corner of the image
+    fn ray(&self, s: f32, t: f32, rng: &mut impl Rng) -> Ray;
+    /// the ray through a random point of the pixel `(x, y)`, rows going down from the top
+    fn pixel_ray(&self, x: u32, y: u32, width: u32, height: u32, rng: &mut impl Rng) -> Ray {
+        let s = (x as f32 + rng.random::<f32>()) / width as f32;
+        let t = 1.0 - (y as f32 + rng.random::<f32>()) / height as f32;
+        self.ray(s, t, rng)
+    }
+}
+
+/// when the shutter is open, rays get a random time in between for motion blur
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shutter {
+    pub open: f32,
+    pub close: f32,
+}
+impl Shutter {
+    pub fn new(open: f32, close: f32) -> Self {
+        Self { open, close }
+    }
+    fn time(&self, rng: &mut impl Rng) -> f32 {
+        if self.open == self.close {
+            return self.open;
+        }
+        self.open + (self.close - self.open) * rng.random::<f32>()
+    }
+}
+
+/// where the camera is and where it looks at
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct View {
+    pub look_from: Vector3,
+    pub look_at: Vector3,
+    /// only needs to be in the plane of the up direction, it's made perpendicular
+    pub up: Vector3,
+}
+impl View {
+    pub fn new(look_from: Vector3, look_at: Vector3, up: Vector3) -> Self {
+        Self {
+            look_from,
+            look_at,
+            up,
+        }
+    }
+    /// `u` to the right, `v` up and `w` backward, the camera looks along -w
+    fn basis(&self) -> Onb {
+        let w = (self.look_from - self.look_at).normalize();
+        let u = cross(self.up, w).normalize();
+        Onb {
+            u,
+            v: cross(w, u),
+            w,
+        }
+    }
+}
+
+/// a pinhole camera, everything is in focus
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Perspective {
+    pub view: View,
+    /// vertical field of view
+    pub fov: Degree,
+    /// width / height
+    pub aspect_ratio: f32,
+    #[serde(default)]
+    pub shutter: Shutter,
+}
+impl Perspective {
+    pub fn new(view: View, fov: Degree, aspect_ratio: f32) -> Self {
+        Self {
+            view,
+            fov,
+            aspect_ratio,
+            shutter: Shutter::default(),
+        }
+    }
+    pub fn with_shutter(self, shutter: Shutter) -> Self {
+        Self { shutter, ..self }
+    }
+}
+impl Projection for Perspective {
+    fn ray(&self, s: f32, t: f32, rng: &mut impl Rng) -> Ray {
+        let target = viewport_point(&self.view.basis(), self.fov, self.aspect_ratio, s, t, 1.0);
+        Ray::new(self.view.look_from, target.normalize()).with_time(self.shutter.time(rng))
+    }
+}
+
+/// parallel rays, sizes don't change with the distance
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Orthographic {
+    pub view: View,
+    /// height of the visible area in world units
+    pub height: f32,
+    /// width / height
+    pub aspect_ratio: f32,
+    #[serde(default)]
+    pub shutter: Shutter,
+}
+impl Orthographic {
+    pub fn new(view: View, height: f32, aspect_ratio: f32) -> Self {
+        Self {
+            view,
+            height,
+            aspect_ratio,
+            shutter: Shutter::default(),
+        }
+    }
+    pub fn with_shutter(self, shutter: Shutter) -> Self {
+        Self { shutter, ..self }
+    }
+}
+impl Projection for Orthographic {
+    fn ray(&self, s: f32, t: f32, rng: &mut impl Rng) -> Ray {
+        let basis = self.view.basis();
+        let width = self.height * self.aspect_ratio;
+        let offset = basis.to_world(vec3((s - 0.5) * width, (t - 0.5) * self.height, 0.0));
+        Ray::new(self.view.look_from + offset, -basis.w).with_time(self.shutter.time(rng))
+    }
+}
+
+/// a camera with a lens, only things at the focus distance are sharp
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThinLens {
+    pub view: View,
+    /// vertical field of view
+    pub fov: Degree,
+    /// width / height
+    pub aspect_ratio: f32,
+    /// diameter of the lens, 0 for a pinhole
+    pub aperture: f32,
+    /// distance from `look_from` to the plane in focus
+    pub focus_distance: f32,
+    #[serde(default)]
+    pub shutter: Shutter,
+}
+impl ThinLens {
+    pub fn new(
+        view: View,
+        fov: Degree,
+        aspect_ratio: f32,
+        aperture: f32,
+        focus_distance: f32,
+    ) -> Self {
+        Self {
+            view,
+            fov,
+            aspect_ratio,
+            aperture,
+            focus_distance,
+            shutter: Shutter::default(),
+        }
+    }
+    pub fn with_shutter(self, shutter: Shutter) -> Self {
+        Self { shutter, ..self }
+    }
+}
+impl Projection for ThinLens {
+    fn ray(&self, s: f32, t: f32, rng: &mut impl Rng) -> Ray {
+        let basis = self.view.basis();
+        let target = viewport_point(
+            &basis,
+            self.fov,
+            self.aspect_ratio,
+            s,
+            t,
+            self.focus_distance,
+        );
+        // from a random point of the lens to where the pinhole ray meets the focus plane
+        let lens = basis.to_world(sampling::unit_disk(rng) * (self.aperture * 0.5));
+        Ray::new(self.view.look_from + lens, (target - lens).normalize())
+            .with_time(self.shutter.time(rng))
+    }
+}
+
+/// the point `(s, t)` of the viewport at `distance` in front of the camera, relative to it
+fn viewport_point(
+    basis: &Onb,
+    fov: Degree,
+    aspect_ratio: f32,
+    s: f32,
+    t: f32,
+    distance: f32,
+) -> Vector3 {
+    let fov: Radian = fov.into();
+    let height = 2.0 * (fov.value() as f32 * 0.5).tan() * distance;
+    let width = height * aspect_ratio;
+    basis.to_world(vec3((s - 0.5) * width, (t - 0.5) * height, -distance))
+}
+
+/// any camera, as found in scene files
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Camera {
+    Perspective(Perspective),
+    Orthographic(Orthographic),
+    ThinLens(ThinLens),
+}
+impl Projection for Camera {
+    fn ray(&self, s: f32, t: f32, rng: &mut impl Rng) -> Ray {
+        match self {
+            Camera::Perspective(camera) => camera.ray(s, t, rng),
+            Camera::Orthographic(camera) => camera.ray(s, t, rng),
+            Camera::ThinLens(camera) => camera.ray(s, t, rng),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linear_algebra::vector::dot;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
+    fn view() -> View {
+        View::new(vec3(0.0, 0.0, 5.0), Vector3::ZERO, Vector3::UNIT_Y)
+    }
+
+    #[test]
+    fn perspective() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let camera = Perspective::new(view(), Degree::new(90.0), 2.0);
+        let center = camera.ray(0.5, 0.5, &mut rng);
+        assert_eq!(center.origin, vec3(0.0, 0.0, 5.0));
+        assert_close(center.direction, -Vector3::UNIT_Z);
+        assert_eq!(center.time, 0.0);
+        // 45° up at the top, and twice as wide as high
+        assert_close(
+            camera.ray(0.5, 1.0, &mut rng).direction,
+            vec3(0.0, 1.0, -1.0).normalize(),
+        );
+        assert_close(
+            camera.ray(0.0, 0.5, &mut rng).direction,
+            vec3(-2.0, 0.0, -1.0).normalize(),
+        );
+        // the pixels cover the whole image once, the first row being the top one
+        let (width, height) = (8, 4);
+        for y in 0..height {
+            for x in 0..width {
+                let d = camera.pixel_ray(x, y, width, height, &mut rng).direction;
+                let (s, t) = (d.x / -d.z, d.y / -d.z);
+                let (s, t) = ((s / 2.0 + 1.0) / 2.0, (t + 1.0) / 2.0);
+                assert!((s * width as f32 - x as f32 - 0.5).abs() <= 0.5);
+                assert!(((1.0 - t) * height as f32 - y as f32 - 0.5).abs() <= 0.5);
+            }
+        }
+        // the up vector doesn't need to be perpendicular
+        let tilted = View::new(vec3(0.0, 5.0, 5.0), Vector3::ZERO, Vector3::UNIT_Y);
+        let camera = Perspective::new(tilted, Degree::new(40.0), 1.0);
+        let d = camera.ray(0.5, 1.0, &mut rng).direction;
+        assert!(d.x.abs() < 1e-6 && d.y > -(0.5_f32.sqrt()));
+    }
+    #[test]
+    fn orthographic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let camera = Orthographic::new(view(), 2.0, 1.5);
+        let ray = camera.ray(1.0, 0.0, &mut rng);
+        assert_close(ray.origin, vec3(1.5, -1.0, 5.0));
+        assert_close(ray.direction, -Vector3::UNIT_Z);
+        for _ in 0..10 {
+            assert_close(
+                camera.pixel_ray(3, 1, 10, 10, &mut rng).direction,
+                -Vector3::UNIT_Z,
+            );
+        }
+    }
+    #[test]
+    fn thin_lens() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let camera = ThinLens::new(view(), Degree::new(60.0), 1.0, 0.5, 4.0);
+        let pinhole = Perspective::new(view(), Degree::new(60.0), 1.0);
+        for (s, t) in [(0.5, 0.5), (0.2, 0.9), (1.0, 0.0)] {
+            // every ray through the same point meets at the focus plane
+            let sharp = pinhole.ray(s, t, &mut rng);
+            let focus = sharp.at(4.0 / dot(sharp.direction, -Vector3::UNIT_Z));
+            for _ in 0..20 {
+                let ray = camera.ray(s, t, &mut rng);
+                assert!((ray.origin - view().look_from).length() <= 0.25 + 1e-6);
+                assert_eq!(ray.origin.z, 5.0);
+                let at_focus = ray.at((ray.origin.z - focus.z) / -ray.direction.z);
+                assert!((at_focus - focus).length() < 1e-4);
+            }
+        }
+        // no aperture is a pinhole
+        let pinhole_lens = ThinLens::new(view(), Degree::new(60.0), 1.0, 0.0, 4.0);
+        let ray = pinhole_lens.ray(0.3, 0.6, &mut rng);
+        assert_eq!(ray.origin, view().look_from);
+        assert_close(ray.direction, pinhole.ray(0.3, 0.6, &mut rng).direction);
+    }
+    #[test]
+    fn shutter() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let camera =
+            Perspective::new(view(), Degree::new(60.0), 1.0).with_shutter(Shutter::new(0.5, 1.0));
+        for _ in 0..100 {
+            let time = camera.pixel_ray(0, 0, 1, 1, &mut rng).time;
+            assert!((0.5..=1.0).contains(&time));
+        }
+    }
+    #[test]
+    fn deserialize() {
+        let json = r#"{
+            "ThinLens": {
+                "view": {
+                    "look_from": { "x": 0.0, "y": 0.0, "z": 5.0 },
+                    "look_at": { "x": 0.0, "y": 0.0, "z": 0.0 },
+                    "up": { "x": 0.0, "y": 1.0, "z": 0.0 }
+                },
+                "fov": 60.0,
+                "aspect_ratio": 1.0,
+                "aperture": 0.5,
+                "focus_distance": 4.0
+            }
+        }"#;
+        let camera: Camera = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            camera,
+            Camera::ThinLens(ThinLens::new(view(), Degree::new(60.0), 1.0, 0.5, 4.0))
+        );
+        let json =
+            serde_json::to_string(&Camera::Orthographic(Orthographic::new(view(), 2.0, 1.0)))
+                .unwrap();
+        let camera: Camera = serde_json::from_str(&json).unwrap();
+        assert!(matches!(camera, Camera::Orthographic(o) if o.height == 2.0));
+    }
+}
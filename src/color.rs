@@ -16,6 +16,7 @@ pub fn mix<T: Mix>(a: T, b: T, t: f32) -> T {
 
 /// Color for GPU rendering and game development
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -0,0 +1,263 @@
+//! constructive solid geometry, combining closed objects with boolean operations
+use std::ops::Range;
+
+use crate::{
+    aabb::Aabb,
+    ray::{HitRecord, Hitable, Interval, Ray},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// inside either object
+    Union,
+    /// inside both objects
+    Intersection,
+    /// inside the first object but not the second
+    Difference,
+}
+impl Operation {
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Operation::Union => in_a || in_b,
+            Operation::Intersection => in_a && in_b,
+            Operation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// two objects combined with an `Operation`, nodes can be nested to build a tree
+pub struct Csg<A, B> {
+    pub a: A,
+    pub b: B,
+    pub operation: Operation,
+}
+impl<A: Hitable, B: Hitable> Csg<A, B> {
+    pub fn new(a: A, b: B, operation: Operation) -> Self {
+        Self { a, b, operation }
+    }
+    pub fn union(a: A, b: B) -> Self {
+        Self::new(a, b, Operation::Union)
+    }
+    pub fn intersection(a: A, b: B) -> Self {
+        Self::new(a, b, Operation::Intersection)
+    }
+    pub fn difference(a: A, b: B) -> Self {
+        Self::new(a, b, Operation::Difference)
+    }
+}
+
+impl<A: Hitable, B: Hitable> Hitable for Csg<A, B> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        self.intervals(ray)
+            .into_iter()
+            .flat_map(|interval| [interval.enter, interval.exit])
+            .find(|rec| range.contains(&rec.t))
+    }
+    fn bounding_box(&self) -> Aabb {
+        let (a, b) = (self.a.bounding_box(), self.b.bounding_box());
+        match self.operation {
+            Operation::Union => a.union(&b),
+            Operation::Intersection => a.intersection(&b),
+            // the hole can't make the box smaller in general
+            Operation::Difference => a,
+        }
+    }
+    fn intervals(&self, ray: Ray) -> Vec<Interval> {
+        // sweep along the ray over the boundaries of both objects,
+        // a boundary of the result is where the inside state changes
+        let mut events: Vec<(HitRecord, bool)> = vec![];
+        for interval in self.a.intervals(ray) {
+            events.extend([(interval.enter, true), (interval.exit, true)]);
+        }
+        for interval in self.b.intervals(ray) {
+            events.extend([(interval.enter, false), (interval.exit, false)]);
+        }
+        events.sort_by(|(a, _), (b, _)| a.t.total_cmp(&b.t));
+
+        let mut intervals = vec![];
+        let (mut in_a, mut in_b) = (false, false);
+        let mut enter: Option<HitRecord> = None;
+        for (mut rec, from_a) in events {
+            if from_a {
+                in_a = rec.front_face;
+            } else {
+                in_b = rec.front_face;
+                if self.operation == Operation::Difference {
+                    // the inside of b is the outside of the result, the normal already faces
+                    // the ray so only the side changes
+                    rec.front_face = !rec.front_face;
+                }
+            }
+            let inside = self.operation.inside(in_a, in_b);
+            match enter {
+                None if inside => enter = Some(rec),
+                Some(start) if !inside => {
+                    intervals.push(Interval {
+                        enter: start,
+                        exit: rec,
+                    });
+                    enter = None;
+                }
+                _ => {}
+            }
+        }
+        intervals
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::{Box, Cylinder, Geometry, Quad, Sphere, Torus},
+        linear_algebra::{vector::vec3, Vector3},
+    };
+
+    fn ray() -> Ray {
+        Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X)
+    }
+    fn spans(object: &impl Hitable) -> Vec<(f32, f32)> {
+        object
+            .intervals(ray())
+            .iter()
+            .map(|i| (i.enter.t, i.exit.t))
+            .collect()
+    }
+    fn assert_spans(object: &impl Hitable, expected: &[(f32, f32)]) {
+        let spans = spans(object);
+        assert_eq!(spans.len(), expected.len(), "{spans:?}");
+        for (span, expected) in spans.iter().zip(expected) {
+            assert!(
+                (span.0 - expected.0).abs() < 1e-4 && (span.1 - expected.1).abs() < 1e-4,
+                "{spans:?}"
+            );
+        }
+    }
+    // two spheres overlapping on the x axis, covering t in [3, 7] and [5, 9]
+    fn spheres() -> (Sphere, Sphere) {
+        (
+            Sphere::new(Vector3::ZERO, 2.0),
+            Sphere::new(vec3(2.0, 0.0, 0.0), 2.0),
+        )
+    }
+
+    #[test]
+    fn primitive_intervals() {
+        assert_spans(&spheres().0, &[(3.0, 7.0)]);
+        let b = Box::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        assert_spans(&b, &[(4.0, 6.0)]);
+        // the whole line is covered, behind the origin too
+        let inside = Ray::new(Vector3::ZERO, Vector3::UNIT_X);
+        let intervals = b.intervals(inside);
+        assert_eq!(intervals.len(), 1);
+        assert_eq!((intervals[0].enter.t, intervals[0].exit.t), (-1.0, 1.0));
+        // both sides of the tube
+        let torus = Torus::new(Vector3::ZERO, Vector3::UNIT_Z, 2.0, 0.5);
+        assert_spans(&torus, &[(2.5, 3.5), (6.5, 7.5)]);
+        // open surfaces have no inside
+        assert_spans(
+            &Quad::new(Vector3::ZERO, Vector3::UNIT_Y, Vector3::UNIT_Z),
+            &[],
+        );
+    }
+    #[test]
+    fn thin_shells() {
+        // hollow shapes with walls 1e-5 thick, far thinner than the steps between hits, around
+        // 3e-4 at t = 3
+        let spans = |object: &dyn Hitable| -> Vec<(f32, f32)> {
+            object
+                .intervals(ray())
+                .iter()
+                .map(|i| (i.enter.t, i.exit.t))
+                .collect()
+        };
+        let assert_walls = |spans: Vec<(f32, f32)>, near: f32, far: f32| {
+            assert_eq!(spans.len(), 2, "{spans:?}");
+            let expected = [(near, near + 1e-5), (far - 1e-5, far)];
+            for (span, expected) in spans.iter().zip(expected) {
+                assert!((span.0 - expected.0).abs() < 2e-6, "{spans:?}");
+                assert!((span.1 - expected.1).abs() < 2e-6, "{spans:?}");
+            }
+        };
+        let shell = Csg::difference(
+            Sphere::new(Vector3::ZERO, 2.0),
+            Sphere::new(Vector3::ZERO, 2.0 - 1e-5),
+        );
+        assert_walls(spans(&shell), 3.0, 7.0);
+        let (outer, inner) = (vec3(2.0, 2.0, 2.0), vec3(2.0 - 1e-5, 1.0, 1.0));
+        let shell = Csg::difference(Box::new(-outer, outer), Box::new(-inner, inner));
+        assert_walls(spans(&shell), 3.0, 7.0);
+        let tube = |radius| Cylinder::new(vec3(0.0, 0.0, -1.0), Vector3::UNIT_Z, radius, 2.0, true);
+        let shell = Csg::difference(tube(2.0), tube(2.0 - 1e-5));
+        assert_walls(spans(&shell), 3.0, 7.0);
+        // the tube of the torus is crossed twice
+        let ring = |minor| Torus::new(Vector3::ZERO, Vector3::UNIT_Z, 2.0, minor);
+        let shell = Csg::difference(ring(0.5), ring(0.5 - 1e-5));
+        let spans = spans(&shell);
+        assert_eq!(spans.len(), 4, "{spans:?}");
+        assert!((spans[0].0 - 2.5).abs() < 1e-5 && (spans[3].1 - 7.5).abs() < 1e-5);
+        // through Geometry too
+        let shell = Csg::difference(
+            Geometry::Sphere(Sphere::new(Vector3::ZERO, 2.0)),
+            Geometry::Sphere(Sphere::new(Vector3::ZERO, 2.0 - 1e-5)),
+        );
+        assert_eq!(shell.intervals(ray()).len(), 2);
+    }
+    #[test]
+    fn edges() {
+        // a capped cylinder entered exactly on its bottom rim and left on its top rim, the cap
+        // and the side are hit at the same t
+        let cylinder = Cylinder::new(Vector3::ZERO, Vector3::UNIT_Z, 1.0, 2.0, true);
+        let ray = Ray::new(vec3(-3.0, 0.0, -2.0), vec3(1.0, 0.0, 1.0));
+        let intervals = cylinder.intervals(ray);
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter.t - 2.0).abs() < 1e-5);
+        assert!((intervals[0].exit.t - 4.0).abs() < 1e-5);
+        // a box entered and left exactly through edges
+        let b = Box::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let ray = Ray::new(vec3(-3.0, -3.0, 0.0), vec3(1.0, 1.0, 0.0));
+        let intervals = b.intervals(ray);
+        assert_eq!(intervals.len(), 1);
+        assert_eq!((intervals[0].enter.t, intervals[0].exit.t), (2.0, 4.0));
+    }
+    #[test]
+    fn operations() {
+        let (a, b) = spheres();
+        assert_spans(&Csg::union(a, b), &[(3.0, 9.0)]);
+        assert_spans(&Csg::intersection(a, b), &[(5.0, 7.0)]);
+        assert_spans(&Csg::difference(a, b), &[(3.0, 5.0)]);
+        assert_spans(&Csg::difference(b, a), &[(7.0, 9.0)]);
+        // disjoint
+        let far = Sphere::new(vec3(10.0, 0.0, 0.0), 1.0);
+        assert_spans(&Csg::union(a, far), &[(3.0, 7.0), (14.0, 16.0)]);
+        assert_spans(&Csg::intersection(a, far), &[]);
+    }
+    #[test]
+    fn box_with_spherical_hole() {
+        let b = Box::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let object = Csg::difference(b, Sphere::new(Vector3::ZERO, 0.5));
+        assert_spans(&object, &[(4.0, 4.5), (5.5, 6.0)]);
+        // hitting the inside of the hole
+        let rec = object.hit(ray(), 4.2..f32::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-4);
+        assert!(!rec.front_face);
+        let rec = object.hit(ray(), 5.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 5.5).abs() < 1e-4);
+        assert!(rec.front_face);
+        // the normal points into the hole, against the ray
+        assert!((rec.normal - -Vector3::UNIT_X).length() < 1e-4);
+        assert_eq!(object.bounding_box().max, vec3(1.0, 1.0, 1.0));
+    }
+    #[test]
+    fn nested() {
+        let (a, b) = spheres();
+        let lens = Csg::intersection(a, b);
+        let object = Csg::union(lens, Sphere::new(vec3(10.0, 0.0, 0.0), 1.0));
+        assert_spans(&object, &[(5.0, 7.0), (14.0, 16.0)]);
+        let rec = object.hit(ray(), 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-4);
+        assert!(rec.front_face);
+        let aabb = object.bounding_box();
+        assert_eq!((aabb.min.x, aabb.max.x), (0.0, 11.0));
+    }
+}
@@ -27,4 +27,18 @@ impl<T> Node<T> {
     pub fn clear_right(&mut self) {
         self.right_child = None
     }
+    /// a node with both children already built
+    pub fn with_children(value: T, left: Node<T>, right: Node<T>) -> Self {
+        Node {
+            left_child: Some(Box::new(left)),
+            right_child: Some(Box::new(right)),
+            value,
+        }
+    }
+    pub fn left(&self) -> Option<&Node<T>> {
+        self.left_child.as_deref()
+    }
+    pub fn right(&self) -> Option<&Node<T>> {
+        self.right_child.as_deref()
+    }
 }
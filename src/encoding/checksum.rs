@@ -0,0 +1,128 @@
+//! the checksums of zlib, gzip and png
+
+/// the reflected polynomial of CRC-32 (ISO 3309)
+const POLYNOMIAL: u32 = 0xedb8_8320;
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                POLYNOMIAL ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 computed over several updates
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Crc32 {
+    pub fn new() -> Self {
+        Self(0xffff_ffff)
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 = CRC_TABLE[((self.0 ^ *byte as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+    /// the checksum of everything so far
+    pub fn finish(&self) -> u32 {
+        self.0 ^ 0xffff_ffff
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// the largest prime below 2^16
+const MOD_ADLER: u32 = 65521;
+/// the most bytes summed before the sums could overflow
+const ADLER_BLOCK: usize = 5552;
+
+/// Adler-32 (RFC 1950) computed over several updates
+#[derive(Clone, Copy, Debug)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Adler32 {
+    pub fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(ADLER_BLOCK) {
+            for byte in block {
+                self.a += *byte as u32;
+                self.b += self.a;
+            }
+            self.a %= MOD_ADLER;
+            self.b %= MOD_ADLER;
+        }
+    }
+    /// the checksum of everything so far
+    pub fn finish(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(data);
+    adler.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+    }
+    #[test]
+    fn adler() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        // long enough for the sums to wrap many times
+        let data = vec![255; 100_000];
+        let mut adler = Adler32::new();
+        for chunk in data.chunks(777) {
+            adler.update(chunk);
+        }
+        assert_eq!(adler.finish(), adler32(&data));
+        assert_eq!(adler32(&data), 0x149a_302c);
+    }
+}
@@ -0,0 +1,1006 @@
+//! DEFLATE compressed data, LZ77 matches written with huffman codes
+//!
+//! RFC 1951 https://www.rfc-editor.org/rfc/rfc1951
+
+/// why compressed data couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// the data ends in the middle of a block or a trailer
+    UnexpectedEnd,
+    /// the reserved block type 3
+    InvalidBlockType,
+    /// a stored block whose length doesn't match its complement
+    InvalidStoredLength,
+    /// code lengths not making a huffman code, or a code that has no meaning
+    InvalidCode,
+    /// a match reaching before the start of the data
+    InvalidDistance,
+    /// a zlib or gzip header that can't be read
+    InvalidHeader,
+    /// the data doesn't match the checksum of the trailer
+    ChecksumMismatch,
+    /// the data decompresses to more bytes than allowed
+    TooLong,
+}
+
+const MAX_BITS: usize = 15;
+const END_OF_BLOCK: usize = 256;
+/// how far back matches can reach
+pub(super) const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// the most bytes in a stored block
+const MAX_STORED: usize = 65535;
+/// the most tokens written in a block, each block has its own codes
+const BLOCK_TOKENS: usize = 1 << 14;
+
+/// the first length of the symbols from 257, and how many extra bits follow them
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// the order code length code lengths are stored in dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// the code lengths of the literal/length and distance codes of fixed blocks
+fn fixed_lengths() -> ([u8; 288], [u8; 30]) {
+    let mut literals = [8; 288];
+    literals[144..256].fill(9);
+    literals[256..280].fill(7);
+    (literals, [5; 30])
+}
+
+/// how hard the compressor looks for matches, from 0 storing the data as it is to 9
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Level(u8);
+impl Level {
+    pub const NONE: Level = Level(0);
+    pub const FASTEST: Level = Level(1);
+    pub const DEFAULT: Level = Level(6);
+    pub const BEST: Level = Level(9);
+
+    /// levels over 9 are 9
+    pub fn new(level: u8) -> Self {
+        Self(level.min(9))
+    }
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+    /// how many earlier positions are tried, the match length that is good enough to stop
+    /// looking, and whether a match is delayed when the next byte starts a longer one
+    fn parameters(&self) -> (usize, usize, bool) {
+        match self.0 {
+            0 | 1 => (4, 8, false),
+            2 => (8, 16, false),
+            3 => (32, 32, false),
+            4 => (16, 16, true),
+            5 => (32, 32, true),
+            6 => (128, 128, true),
+            7 => (256, 128, true),
+            8 => (1024, MAX_MATCH, true),
+            _ => (4096, MAX_MATCH, true),
+        }
+    }
+}
+impl Default for Level {
+    fn default() -> Self {
+        Level::DEFAULT
+    }
+}
+
+/// decompress raw deflate data
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    inflate(data, usize::MAX).map(|(output, _)| output)
+}
+
+/// the decompressed data and how many bytes of `data` it took, what follows is a trailer,
+/// fails once the output would be longer than `limit`
+pub(crate) fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), Error> {
+    let mut reader = BitReader::new(data, 0);
+    let mut output = Vec::new();
+    while !inflate_block(&mut reader, &mut output, limit)? {}
+    Ok((output, reader.consumed()))
+}
+
+/// decodes the next block at the end of `output`, true if it's the last one,
+/// fails once `output` would be longer than `limit`
+pub(super) fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> Result<bool, Error> {
+    let last = reader.bits(1)? == 1;
+    match reader.bits(2)? {
+        0 => {
+            reader.align();
+            let header = reader.bytes(4)?;
+            let length = u16::from_le_bytes([header[0], header[1]]);
+            if length != !u16::from_le_bytes([header[2], header[3]]) {
+                return Err(Error::InvalidStoredLength);
+            }
+            if output.len() + length as usize > limit {
+                return Err(Error::TooLong);
+            }
+            output.extend_from_slice(reader.bytes(length as usize)?);
+        }
+        1 => {
+            let (literals, distances) = fixed_lengths();
+            let literals = Huffman::new(&literals)?;
+            let distances = Huffman::new(&distances)?;
+            inflate_codes(reader, output, limit, &literals, &distances)?;
+        }
+        2 => {
+            let (literals, distances) = dynamic_codes(reader)?;
+            inflate_codes(reader, output, limit, &literals, &distances)?;
+        }
+        _ => return Err(Error::InvalidBlockType),
+    }
+    Ok(last)
+}
+
+fn inflate_codes(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    limit: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < END_OF_BLOCK {
+            if output.len() == limit {
+                return Err(Error::TooLong);
+            }
+            output.push(symbol as u8);
+            continue;
+        } else if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+        let i = symbol - END_OF_BLOCK - 1;
+        if i >= LENGTH_BASE.len() {
+            return Err(Error::InvalidCode);
+        }
+        let length = LENGTH_BASE[i] as usize + reader.bits(LENGTH_EXTRA[i] as u32)? as usize;
+        let d = distances.decode(reader)? as usize;
+        if d >= DISTANCE_BASE.len() {
+            return Err(Error::InvalidCode);
+        }
+        let distance = DISTANCE_BASE[d] as usize + reader.bits(DISTANCE_EXTRA[d] as u32)? as usize;
+        if distance > output.len() {
+            return Err(Error::InvalidDistance);
+        }
+        if output.len() + length > limit {
+            return Err(Error::TooLong);
+        }
+        // byte by byte, a match can overlap what it copies
+        let start = output.len() - distance;
+        for k in start..start + length {
+            output.push(output[k]);
+        }
+    }
+}
+
+/// the codes of a dynamic block, from the code lengths in its header
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), Error> {
+    let literals = reader.bits(5)? as usize + 257;
+    let distances = reader.bits(5)? as usize + 1;
+    let code_lengths = reader.bits(4)? as usize + 4;
+    let mut lengths = [0; 19];
+    for i in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[*i] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths)?;
+    let count = literals + distances;
+    let mut lengths = Vec::with_capacity(count);
+    while lengths.len() < count {
+        let (length, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or(Error::InvalidCode)?,
+                3 + reader.bits(2)?,
+            ),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(Error::InvalidCode),
+        };
+        let end = lengths.len() + repeat as usize;
+        if end > count {
+            return Err(Error::InvalidCode);
+        }
+        lengths.resize(end, length);
+    }
+    // a block always ends
+    if lengths[END_OF_BLOCK] == 0 {
+        return Err(Error::InvalidCode);
+    }
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..])?,
+    ))
+}
+
+/// reads bits from the least significant bit of each byte
+pub(super) struct BitReader<'a> {
+    data: &'a [u8],
+    /// the next byte to buffer
+    position: usize,
+    buffer: u64,
+    count: u32,
+}
+impl<'a> BitReader<'a> {
+    /// reading from the `bit`th bit of the data
+    pub(super) fn new(data: &'a [u8], bit: usize) -> Self {
+        let mut reader = Self {
+            data,
+            position: bit / 8,
+            buffer: 0,
+            count: 0,
+        };
+        // the byte is there if a previous reader stopped in it
+        let _ = reader.bits((bit % 8) as u32);
+        reader
+    }
+    fn bits(&mut self, n: u32) -> Result<u32, Error> {
+        while self.count < n {
+            let byte = *self.data.get(self.position).ok_or(Error::UnexpectedEnd)?;
+            self.buffer |= (byte as u64) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value as u32)
+    }
+    /// skips to the next byte
+    fn align(&mut self) {
+        let n = self.count % 8;
+        self.buffer >>= n;
+        self.count -= n;
+    }
+    /// the next `n` bytes, after `align`
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        // whole bytes still in the buffer are read again from the data
+        self.position -= (self.count / 8) as usize;
+        self.buffer = 0;
+        self.count = 0;
+        let bytes = self
+            .data
+            .get(self.position..self.position + n)
+            .ok_or(Error::UnexpectedEnd)?;
+        self.position += n;
+        Ok(bytes)
+    }
+    /// how many bits were read
+    pub(super) fn bit_position(&self) -> usize {
+        self.position * 8 - self.count as usize
+    }
+    /// how many bytes were read, counting a partly read one
+    pub(super) fn consumed(&self) -> usize {
+        self.bit_position().div_ceil(8)
+    }
+}
+
+/// a canonical huffman code read a bit at a time
+struct Huffman {
+    /// how many codes have each length
+    counts: [u16; MAX_BITS + 1],
+    /// the symbols ordered by code
+    symbols: Vec<u16>,
+}
+impl Huffman {
+    /// `lengths[symbol]` is the code length of the symbol, 0 when it's unused. incomplete
+    /// codes are allowed, codes with too many symbols are not
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(Error::InvalidCode);
+            }
+        }
+        let mut offsets = [0; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.iter().filter(|l| **l != 0).count()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                let offset = &mut offsets[*length as usize];
+                symbols[*offset as usize] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, Error> {
+        // the first code of each length follows the last code of the previous length
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(Error::InvalidCode)
+    }
+}
+
+/// compress to raw deflate data
+pub fn compress(data: &[u8], level: Level) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    write_blocks(&mut writer, data, 0, level, true);
+    writer.finish()
+}
+
+/// compresses the data from `start` on, the bytes before it are what matches can reach back to
+pub(super) fn write_blocks(
+    writer: &mut BitWriter,
+    data: &[u8],
+    start: usize,
+    level: Level,
+    last: bool,
+) {
+    if level == Level::NONE {
+        write_stored(writer, &data[start..], last);
+        return;
+    }
+    let tokens = lz77(data, start, level);
+    let mut blocks: Vec<&[Token]> = tokens.chunks(BLOCK_TOKENS).collect();
+    if blocks.is_empty() {
+        // no data still makes a block
+        blocks.push(&[]);
+    }
+    let mut start = start;
+    for (i, block) in blocks.iter().enumerate() {
+        let size: usize = block.iter().map(Token::size).sum();
+        write_block(
+            writer,
+            block,
+            &data[start..start + size],
+            last && i + 1 == blocks.len(),
+        );
+        start += size;
+    }
+}
+
+/// the smallest of a stored, a fixed and a dynamic block
+fn write_block(writer: &mut BitWriter, tokens: &[Token], data: &[u8], last: bool) {
+    let (fixed_literals, fixed_distances) = fixed_lengths();
+    let fixed = 3 + cost(tokens, &fixed_literals, &fixed_distances);
+    let dynamic = DynamicHeader::new(tokens);
+    let dynamic_cost = 3 + dynamic.cost() + cost(tokens, &dynamic.literals, &dynamic.distances);
+    let stored = (data.len() + data.len().div_ceil(MAX_STORED).max(1) * 5) * 8;
+    if stored < fixed.min(dynamic_cost) {
+        write_stored(writer, data, last);
+    } else if fixed <= dynamic_cost {
+        writer.bits(last as u32, 1);
+        writer.bits(1, 2);
+        write_tokens(writer, tokens, &fixed_literals, &fixed_distances);
+    } else {
+        writer.bits(last as u32, 1);
+        writer.bits(2, 2);
+        dynamic.write(writer);
+        write_tokens(writer, tokens, &dynamic.literals, &dynamic.distances);
+    }
+}
+
+fn write_stored(writer: &mut BitWriter, data: &[u8], last: bool) {
+    let mut chunks: Vec<&[u8]> = data.chunks(MAX_STORED).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    for (i, chunk) in chunks.iter().enumerate() {
+        writer.bits((last && i + 1 == chunks.len()) as u32, 1);
+        writer.bits(0, 2);
+        writer.align();
+        writer.bits(chunk.len() as u32, 16);
+        writer.bits(!chunk.len() as u32 & 0xffff, 16);
+        writer.output.extend_from_slice(chunk);
+    }
+}
+
+/// bits taken by the tokens and the end of the block with these code lengths
+fn cost(tokens: &[Token], literals: &[u8], distances: &[u8]) -> usize {
+    let mut bits = literals[END_OF_BLOCK] as usize;
+    for token in tokens {
+        bits += match *token {
+            Token::Literal(byte) => literals[byte as usize] as usize,
+            Token::Match { length, distance } => {
+                let (l, d) = (length_index(length), distance_index(distance));
+                (literals[END_OF_BLOCK + 1 + l]
+                    + LENGTH_EXTRA[l]
+                    + distances[d]
+                    + DISTANCE_EXTRA[d]) as usize
+            }
+        };
+    }
+    bits
+}
+
+fn write_tokens(writer: &mut BitWriter, tokens: &[Token], literals: &[u8], distances: &[u8]) {
+    let (literal_codes, distance_codes) = (codes(literals), codes(distances));
+    let symbol =
+        |writer: &mut BitWriter, s: usize| writer.bits(literal_codes[s] as u32, literals[s] as u32);
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => symbol(writer, byte as usize),
+            Token::Match { length, distance } => {
+                let l = length_index(length);
+                symbol(writer, END_OF_BLOCK + 1 + l);
+                writer.bits((length - LENGTH_BASE[l]) as u32, LENGTH_EXTRA[l] as u32);
+                let d = distance_index(distance);
+                writer.bits(distance_codes[d] as u32, distances[d] as u32);
+                writer.bits(
+                    (distance - DISTANCE_BASE[d]) as u32,
+                    DISTANCE_EXTRA[d] as u32,
+                );
+            }
+        }
+    }
+    symbol(writer, END_OF_BLOCK);
+}
+
+/// the code lengths of a dynamic block, built for its tokens
+struct DynamicHeader {
+    literals: Vec<u8>,
+    distances: Vec<u8>,
+    /// the code lengths of both codes, run length encoded as (symbol, extra bits value)
+    runs: Vec<(u8, u8)>,
+    code_lengths: Vec<u8>,
+}
+impl DynamicHeader {
+    fn new(tokens: &[Token]) -> Self {
+        let mut literal_counts = [0u32; 286];
+        let mut distance_counts = [0u32; 30];
+        literal_counts[END_OF_BLOCK] = 1;
+        for token in tokens {
+            match *token {
+                Token::Literal(byte) => literal_counts[byte as usize] += 1,
+                Token::Match { length, distance } => {
+                    literal_counts[END_OF_BLOCK + 1 + length_index(length)] += 1;
+                    distance_counts[distance_index(distance)] += 1;
+                }
+            }
+        }
+        let mut literals = huffman_lengths(&literal_counts, MAX_BITS as u8);
+        let mut distances = huffman_lengths(&distance_counts, MAX_BITS as u8);
+        // trailing unused codes aren't written
+        let used = |lengths: &[u8]| lengths.iter().rposition(|l| *l != 0).map_or(0, |i| i + 1);
+        literals.truncate(used(&literals).max(257));
+        distances.truncate(used(&distances).max(1));
+
+        let runs = run_lengths(&[literals.as_slice(), distances.as_slice()].concat());
+        let mut counts = [0u32; 19];
+        for (symbol, _) in &runs {
+            counts[*symbol as usize] += 1;
+        }
+        let code_lengths = huffman_lengths(&counts, 7);
+        Self {
+            literals,
+            distances,
+            runs,
+            code_lengths,
+        }
+    }
+    /// how many code length code lengths are written, in their odd order
+    fn code_length_count(&self) -> usize {
+        let used = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|i| self.code_lengths[*i] != 0)
+            .map_or(0, |i| i + 1);
+        used.max(4)
+    }
+    /// the bits of the header, without the block type
+    fn cost(&self) -> usize {
+        let runs: usize = self
+            .runs
+            .iter()
+            .map(|(symbol, _)| {
+                (self.code_lengths[*symbol as usize] + repeat_bits(*symbol)) as usize
+            })
+            .sum();
+        5 + 5 + 4 + 3 * self.code_length_count() + runs
+    }
+    fn write(&self, writer: &mut BitWriter) {
+        writer.bits(self.literals.len() as u32 - 257, 5);
+        writer.bits(self.distances.len() as u32 - 1, 5);
+        let count = self.code_length_count();
+        writer.bits(count as u32 - 4, 4);
+        for i in &CODE_LENGTH_ORDER[..count] {
+            writer.bits(self.code_lengths[*i] as u32, 3);
+        }
+        let codes = codes(&self.code_lengths);
+        for (symbol, extra) in &self.runs {
+            let s = *symbol as usize;
+            writer.bits(codes[s] as u32, self.code_lengths[s] as u32);
+            writer.bits(*extra as u32, repeat_bits(*symbol) as u32);
+        }
+    }
+}
+
+/// the extra bits after a code length symbol
+fn repeat_bits(symbol: u8) -> u8 {
+    match symbol {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// code lengths as symbols 0 to 15, 16 repeating the previous length 3 to 6 times, 17 and 18
+/// repeating zero 3 to 10 and 11 to 138 times
+fn run_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        let run = lengths[i..].iter().take_while(|l| **l == length).count();
+        if length == 0 && run >= 11 {
+            let run = run.min(138);
+            runs.push((18, (run - 11) as u8));
+            i += run;
+        } else if length == 0 && run >= 3 {
+            runs.push((17, (run - 3) as u8));
+            i += run;
+        } else if run >= 4 {
+            let run = (run - 1).min(6);
+            runs.push((length, 0));
+            runs.push((16, (run - 3) as u8));
+            i += 1 + run;
+        } else {
+            runs.push((length, 0));
+            i += 1;
+        }
+    }
+    runs
+}
+
+/// huffman code lengths for the symbol counts, no longer than `max_bits`. at least 2 symbols
+/// get a code so that the code is complete
+fn huffman_lengths(counts: &[u32], max_bits: u8) -> Vec<u8> {
+    let mut counts = counts.to_vec();
+    let used = counts.iter().filter(|c| **c > 0).count();
+    if used < 2 {
+        for count in counts.iter_mut().filter(|c| **c == 0).take(2 - used) {
+            *count = 1;
+        }
+    }
+    loop {
+        let lengths = unlimited_huffman_lengths(&counts);
+        if lengths.iter().all(|l| *l <= max_bits) {
+            return lengths;
+        }
+        // flatter counts make a shallower tree, in the end all the same
+        for count in counts.iter_mut().filter(|c| **c > 0) {
+            *count = (*count / 2).max(1);
+        }
+    }
+}
+
+fn unlimited_huffman_lengths(counts: &[u32]) -> Vec<u8> {
+    use std::{cmp::Reverse, collections::BinaryHeap};
+
+    // leaves are the symbols, every node but the root has a parent
+    let mut parents = vec![usize::MAX; counts.len()];
+    let mut heap: BinaryHeap<_> = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c > 0)
+        .map(|(symbol, c)| Reverse((*c as u64, symbol)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((a, left)) = heap.pop().unwrap();
+        let Reverse((b, right)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = node;
+        parents[right] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    // parents are created after their children
+    let mut depths = vec![0u8; parents.len()];
+    for node in (0..parents.len()).rev() {
+        if parents[node] != usize::MAX {
+            depths[node] = depths[parents[node]] + 1;
+        }
+    }
+    depths.truncate(counts.len());
+    depths
+}
+
+fn length_index(length: u16) -> usize {
+    LENGTH_BASE.partition_point(|base| *base <= length) - 1
+}
+fn distance_index(distance: u16) -> usize {
+    DISTANCE_BASE.partition_point(|base| *base <= distance) - 1
+}
+
+/// the canonical codes of the lengths, bit reversed since huffman codes are written from
+/// their most significant bit
+fn codes(lengths: &[u8]) -> Vec<u16> {
+    let mut counts = [0u16; MAX_BITS + 1];
+    for length in lengths {
+        counts[*length as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0u16; MAX_BITS + 1];
+    for bits in 1..=MAX_BITS {
+        next[bits] = (next[bits - 1] + counts[bits - 1]) << 1;
+    }
+    lengths
+        .iter()
+        .map(|length| {
+            let length = *length as usize;
+            if length == 0 {
+                return 0;
+            }
+            let code = next[length];
+            next[length] += 1;
+            code.reverse_bits() >> (16 - length)
+        })
+        .collect()
+}
+
+/// a byte or a copy of earlier bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+impl Token {
+    /// how many bytes of the data it stands for
+    fn size(&self) -> usize {
+        match self {
+            Token::Literal(_) => 1,
+            Token::Match { length, .. } => *length as usize,
+        }
+    }
+}
+
+const HASH_BITS: u32 = 15;
+const NONE: usize = usize::MAX;
+
+/// earlier positions starting with the same 3 bytes, most recent first
+struct HashChains<'a> {
+    data: &'a [u8],
+    /// the last position of each hash
+    head: Vec<usize>,
+    /// the position before, for each position of the window
+    previous: Vec<usize>,
+}
+impl<'a> HashChains<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![NONE; 1 << HASH_BITS],
+            previous: vec![NONE; WINDOW_SIZE],
+        }
+    }
+    fn hash(&self, i: usize) -> usize {
+        let d = &self.data[i..];
+        let v = (d[0] as u32) << 16 | (d[1] as u32) << 8 | d[2] as u32;
+        (v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let h = self.hash(i);
+            self.previous[i % WINDOW_SIZE] = self.head[h];
+            self.head[h] = i;
+        }
+    }
+    /// the longest match for position `i` among the `max_chain` latest candidates, as
+    /// (length, distance)
+    fn longest_match(&self, i: usize, max_chain: usize, nice: usize) -> Option<(usize, usize)> {
+        if i + MIN_MATCH > self.data.len() {
+            return None;
+        }
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(i)];
+        for _ in 0..max_chain {
+            if candidate == NONE || i - candidate > WINDOW_SIZE {
+                break;
+            }
+            let length = self.data[candidate..]
+                .iter()
+                .zip(&self.data[i..])
+                .take(MAX_MATCH)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, i - candidate);
+                if length >= nice {
+                    break;
+                }
+            }
+            let next = self.previous[candidate % WINDOW_SIZE];
+            // the slot was reused by a later position
+            if next == NONE || next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        (best.0 >= MIN_MATCH).then_some(best)
+    }
+}
+
+/// the tokens for the data from `start`, matches searched in hash chains. with lazy matching a
+/// match is only taken when the next position doesn't start a longer one
+fn lz77(data: &[u8], start: usize, level: Level) -> Vec<Token> {
+    let (max_chain, nice, lazy) = level.parameters();
+    let mut chains = HashChains::new(data);
+    for i in start.saturating_sub(WINDOW_SIZE)..start {
+        chains.insert(i);
+    }
+    let mut tokens = Vec::new();
+    let mut i = start;
+    while i < data.len() {
+        let found = chains.longest_match(i, max_chain, nice);
+        chains.insert(i);
+        let Some((length, distance)) = found else {
+            tokens.push(Token::Literal(data[i]));
+            i += 1;
+            continue;
+        };
+        if lazy && length < nice {
+            let next = chains.longest_match(i + 1, max_chain, nice);
+            if next.is_some_and(|(next, _)| next > length) {
+                tokens.push(Token::Literal(data[i]));
+                i += 1;
+                continue;
+            }
+        }
+        tokens.push(Token::Match {
+            length: length as u16,
+            distance: distance as u16,
+        });
+        // the positions inside the match can be matched later
+        for j in i + 1..i + length {
+            chains.insert(j);
+        }
+        i += length;
+    }
+    tokens
+}
+
+/// writes bits from the least significant bit of each byte
+#[derive(Default)]
+pub(super) struct BitWriter {
+    /// the whole bytes written so far
+    pub(super) output: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+impl BitWriter {
+    fn bits(&mut self, value: u32, n: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.output.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+    /// pads with zeros to the next byte
+    pub(super) fn align(&mut self) {
+        if self.count > 0 {
+            self.bits(0, 8 - self.count);
+        }
+    }
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// text with repetitions at all distances
+    fn sample(len: usize) -> Vec<u8> {
+        let words = ["deflate ", "huffman ", "lz77 ", "window ", "block ", "\n"];
+        let mut state = 12345u32;
+        (0..)
+            .flat_map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                words[(state >> 16) as usize % words.len()].bytes()
+            })
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for data in [
+            vec![],
+            vec![42],
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_vec(),
+            sample(4_000),
+            (0..=255).cycle().take(4_000).collect(),
+        ] {
+            for level in (0..=9).map(Level::new) {
+                let compressed = compress(&data, level);
+                assert_eq!(decompress(&compressed).unwrap(), data, "{level:?}");
+            }
+        }
+        // a bit more than the window for the matches to reach back its whole size
+        for data in [sample(40_000), (0..=255).cycle().take(40_000).collect()] {
+            for level in [Level::NONE, Level::FASTEST, Level::DEFAULT] {
+                let compressed = compress(&data, level);
+                assert_eq!(decompress(&compressed).unwrap(), data, "{level:?}");
+            }
+        }
+        let text = sample(40_000);
+        assert!(compress(&text, Level::FASTEST).len() < text.len() / 2);
+    }
+    /// slow in a debug build, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn large_inputs() {
+        for data in [sample(1_000_000), (0..=255).cycle().take(300_000).collect()] {
+            for level in (0..=9).map(Level::new) {
+                let compressed = compress(&data, level);
+                assert_eq!(decompress(&compressed).unwrap(), data, "{level:?}");
+            }
+        }
+        let text = sample(1_000_000);
+        let sizes: Vec<usize> = [Level::FASTEST, Level::DEFAULT, Level::BEST]
+            .iter()
+            .map(|level| compress(&text, *level).len())
+            .collect();
+        assert!(sizes[0] < text.len() / 2);
+        assert!(sizes[1] < sizes[0]);
+        assert!(sizes[2] <= sizes[1]);
+    }
+    #[test]
+    fn stored_when_smaller() {
+        // random bytes don't compress, a stored block is 5 bytes larger
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..1000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let compressed = compress(&noise, Level::DEFAULT);
+        assert_eq!(compressed.len(), 1005);
+        assert_eq!(compressed[0], 1);
+        assert_eq!(decompress(&compressed).unwrap(), noise);
+    }
+    #[test]
+    fn known_streams() {
+        // as written by zlib
+        assert_eq!(compress(b"", Level::DEFAULT), [3, 0]);
+        assert_eq!(decompress(&[3, 0]).unwrap(), b"");
+        let hello = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(decompress(&hello).unwrap(), b"hello hello hello");
+        // a dynamic block
+        let dynamic = [
+            0x25, 0x88, 0xc1, 0x11, 0x00, 0x30, 0x08, 0x83, 0x66, 0x0d, 0xe8, 0xfe, 0x2b, 0xb4,
+            0x2a, 0x0f, 0x8e, 0x84, 0x98, 0xa5, 0x5b, 0xc3, 0x75, 0x50, 0xb8, 0xf5, 0xe5, 0x58,
+            0xaa, 0xe6, 0xe0, 0x01,
+        ];
+        assert_eq!(
+            decompress(&dynamic).unwrap(),
+            b"bacaaaaaaeeccabaaaaaaabccbbabaaaabaacaabacbddbaaab"
+        );
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(decompress(&[]), Err(Error::UnexpectedEnd));
+        assert_eq!(decompress(&[0x07]), Err(Error::InvalidBlockType));
+        assert_eq!(
+            decompress(&[1, 5, 0, 0, 0, 1, 2, 3, 4, 5]),
+            Err(Error::InvalidStoredLength)
+        );
+        assert_eq!(
+            decompress(&[1, 5, 0, 0xfa, 0xff, 1, 2]),
+            Err(Error::UnexpectedEnd)
+        );
+        // a match before anything was written
+        let mut writer = BitWriter::default();
+        writer.bits(1, 1);
+        writer.bits(1, 2);
+        let (literals, distances) = fixed_lengths();
+        write_tokens(
+            &mut writer,
+            &[Token::Match {
+                length: 3,
+                distance: 1,
+            }],
+            &literals,
+            &distances,
+        );
+        assert_eq!(decompress(&writer.finish()), Err(Error::InvalidDistance));
+        // too many codes of length 1
+        assert!(Huffman::new(&[1, 1, 1]).is_err());
+    }
+    #[test]
+    fn symbols() {
+        assert_eq!(length_index(3), 0);
+        assert_eq!(length_index(10), 7);
+        assert_eq!(length_index(257), 27);
+        assert_eq!(length_index(258), 28);
+        assert_eq!(distance_index(1), 0);
+        assert_eq!(distance_index(32768), 29);
+        // the example of the RFC
+        assert_eq!(
+            codes(&[3, 3, 3, 3, 3, 2, 4, 4]),
+            [0b010, 0b110, 0b001, 0b101, 0b011, 0b00, 0b0111, 0b1111]
+        );
+    }
+    #[test]
+    fn levels() {
+        let text = sample(16_000);
+        let sizes: Vec<usize> = [Level::NONE, Level::FASTEST, Level::DEFAULT, Level::BEST]
+            .iter()
+            .map(|level| compress(&text, *level).len())
+            .collect();
+        assert!(sizes[0] > text.len());
+        assert!(sizes[1] < sizes[0] / 2);
+        assert!(sizes[2] < sizes[1]);
+        assert!(sizes[3] <= sizes[2]);
+        assert_eq!(Level::new(42), Level::BEST);
+        assert_eq!(Level::default().value(), 6);
+    }
+    #[test]
+    fn dynamic_blocks() {
+        // skewed text is cheaper with its own codes
+        let text = sample(10_000);
+        let compressed = compress(&text, Level::DEFAULT);
+        assert_eq!(compressed[0] & 0b111, 0b101);
+        assert_eq!(decompress(&compressed).unwrap(), text);
+        let lengths = huffman_lengths(&[1, 1, 2, 4, 0, 8], 15);
+        assert_eq!(lengths, [4, 4, 3, 2, 0, 1]);
+        // too deep for 3 bits, flattened
+        let lengths = huffman_lengths(&[1, 2, 4, 8, 16, 32], 3);
+        assert!(lengths.iter().all(|l| *l <= 3));
+        assert!(Huffman::new(&lengths).is_ok());
+        // a single symbol still gets a complete code
+        assert_eq!(huffman_lengths(&[0, 0, 5], 15), [1, 0, 1]);
+        assert_eq!(
+            run_lengths(&[3, 3, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 5, 0, 0]),
+            [(3, 0), (16, 3), (3, 0), (17, 1), (5, 0), (0, 0), (0, 0)]
+        );
+        assert_eq!(run_lengths(&[0; 150]), [(18, 127), (18, 1)]);
+    }
+    #[test]
+    fn window_before_start() {
+        // the second part only repeats the first, across two calls
+        let text = sample(20_000);
+        let data = [text.as_slice(), text.as_slice()].concat();
+        let mut writer = BitWriter::default();
+        write_blocks(&mut writer, &data[..20_000], 0, Level::DEFAULT, false);
+        let first = writer.output.len();
+        write_blocks(&mut writer, &data, 20_000, Level::DEFAULT, true);
+        let compressed = writer.finish();
+        assert!(compressed.len() - first < 1000);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+}
@@ -0,0 +1,136 @@
+//! the gzip file format, deflate data between a header and a CRC-32 and size trailer
+//!
+//! RFC 1952 https://www.rfc-editor.org/rfc/rfc1952
+use super::{
+    checksum::crc32,
+    deflate::{self, Error, Level},
+};
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+const DEFLATE: u8 = 8;
+/// the header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const RESERVED: u8 = 0xe0;
+
+pub fn compress(data: &[u8], level: Level) -> Vec<u8> {
+    let mut output = header(level).to_vec();
+    output.extend(deflate::compress(data, level));
+    output.extend_from_slice(&trailer(crc32(data), data.len()));
+    output
+}
+
+/// the first member of the file, names, comments and extra fields of the header are skipped
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let start = header_length(data)?;
+    let (output, used) = deflate::inflate(&data[start..], usize::MAX)?;
+    let trailer_start = start + used;
+    let expected = data
+        .get(trailer_start..trailer_start + 8)
+        .ok_or(Error::UnexpectedEnd)?;
+    if expected != trailer(crc32(&output), output.len()) {
+        return Err(Error::ChecksumMismatch);
+    }
+    Ok(output)
+}
+
+/// no name nor modification time
+pub(super) fn header(level: Level) -> [u8; 10] {
+    // extra flags hinting at the compression, then an unknown operating system
+    let xfl = match level.value() {
+        0 | 1 => 4,
+        9 => 2,
+        _ => 0,
+    };
+    [MAGIC[0], MAGIC[1], DEFLATE, 0, 0, 0, 0, 0, xfl, 255]
+}
+
+/// the CRC-32 and the size modulo 2^32, both little endian
+pub(super) fn trailer(crc: u32, size: usize) -> [u8; 8] {
+    let mut trailer = [0; 8];
+    trailer[..4].copy_from_slice(&crc.to_le_bytes());
+    trailer[4..].copy_from_slice(&(size as u32).to_le_bytes());
+    trailer
+}
+
+/// the length of the header at the start of the data, with its optional fields
+pub(super) fn header_length(data: &[u8]) -> Result<usize, Error> {
+    let fixed = data.get(..10).ok_or(Error::UnexpectedEnd)?;
+    if fixed[..2] != MAGIC || fixed[2] != DEFLATE || fixed[3] & RESERVED != 0 {
+        return Err(Error::InvalidHeader);
+    }
+    let flags = fixed[3];
+    let mut length = 10;
+    if flags & FEXTRA != 0 {
+        let size = data.get(length..length + 2).ok_or(Error::UnexpectedEnd)?;
+        length += 2 + u16::from_le_bytes([size[0], size[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // zero terminated
+            let end = data
+                .get(length..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or(Error::UnexpectedEnd)?;
+            length += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        length += 2;
+    }
+    if length > data.len() {
+        return Err(Error::UnexpectedEnd);
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = b"gzip gzip gzip gzip";
+        let compressed = compress(data, Level::DEFAULT);
+        assert_eq!(&compressed[..4], [0x1f, 0x8b, 8, 0]);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(&compressed[compressed.len() - 4..], [19, 0, 0, 0]);
+    }
+    #[test]
+    fn header_fields() {
+        // as written by `gzip` for a file named a.txt holding "hi\n"
+        let data = [
+            0x1f, 0x8b, 0x08, 0x08, 0x28, 0x94, 0xd5, 0x6a, 0x00, 0x03, 0x61, 0x2e, 0x74, 0x78,
+            0x74, 0x00, 0xcb, 0xc8, 0xe4, 0x02, 0x00, 0x7a, 0x7a, 0x6f, 0xed, 0x03, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(header_length(&data), Ok(16));
+        assert_eq!(decompress(&data).unwrap(), b"hi\n");
+        // an extra field, a comment and a header crc
+        let mut data = vec![0x1f, 0x8b, 8, FEXTRA | FCOMMENT | FHCRC, 0, 0, 0, 0, 0, 255];
+        data.extend_from_slice(&[3, 0, 1, 2, 3]);
+        data.extend_from_slice(b"a comment\0");
+        data.extend_from_slice(&[0xab, 0xcd]);
+        assert_eq!(header_length(&data), Ok(data.len()));
+    }
+    #[test]
+    fn errors() {
+        let mut compressed = compress(b"gzip gzip gzip gzip", Level::BEST);
+        assert_eq!(compressed[8], 2);
+        assert_eq!(
+            decompress(&compressed[..compressed.len() - 1]),
+            Err(Error::UnexpectedEnd)
+        );
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        assert_eq!(decompress(&compressed), Err(Error::ChecksumMismatch));
+        assert_eq!(decompress(&[0x1f, 0x8c]), Err(Error::UnexpectedEnd));
+        let mut bad = header(Level::DEFAULT);
+        bad[3] = 0x20;
+        assert_eq!(decompress(&bad), Err(Error::InvalidHeader));
+        bad[3] = FNAME;
+        assert_eq!(header_length(&bad), Err(Error::UnexpectedEnd));
+    }
+}
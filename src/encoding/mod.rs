@@ -1,2 +1,8 @@
 pub mod huffman;
 pub mod base64;
+pub mod checksum;
+pub mod deflate;
+pub mod gzip;
+pub mod png;
+pub mod stream;
+pub mod zlib;
@@ -0,0 +1,641 @@
+//! portable network graphics, losslessly compressed images
+//!
+//! https://www.w3.org/TR/png/
+use super::{
+    checksum::crc32,
+    deflate::{self, Level},
+    zlib,
+};
+use crate::{
+    color::{rgba, Color},
+    image::Image,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// the largest width or height
+const MAX_SIDE: usize = (1 << 31) - 1;
+/// where the 7 passes of an interlaced image start and how far apart their pixels are
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// why a png couldn't be read
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// not a png file
+    InvalidSignature,
+    /// a missing or wrong IHDR chunk
+    InvalidHeader,
+    /// a chunk not matching its CRC
+    ChecksumMismatch,
+    /// the file ends in the middle of a chunk
+    UnexpectedEnd,
+    /// a critical chunk this decoder doesn't know
+    Unsupported,
+    /// a filter type or a palette index that doesn't exist, or missing pixels
+    InvalidData,
+    /// the pixels can't be decompressed
+    Deflate(deflate::Error),
+}
+impl From<deflate::Error> for Error {
+    fn from(error: deflate::Error) -> Self {
+        Error::Deflate(error)
+    }
+}
+
+/// how pixels are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Rgb8,
+    Rgba8,
+    Rgb16,
+    Rgba16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+impl ColorType {
+    fn channels(self) -> usize {
+        match self {
+            ColorType::Grayscale | ColorType::Indexed => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        let [w0, w1, w2, w3, h0, h1, h2, h3, bit_depth, color_type, 0, 0, interlace] = *data else {
+            return Err(Error::InvalidHeader);
+        };
+        let width = u32::from_be_bytes([w0, w1, w2, w3]) as usize;
+        let height = u32::from_be_bytes([h0, h1, h2, h3]) as usize;
+        let color_type = match (color_type, bit_depth) {
+            (0, 1 | 2 | 4 | 8 | 16) => ColorType::Grayscale,
+            (2, 8 | 16) => ColorType::Rgb,
+            (3, 1 | 2 | 4 | 8) => ColorType::Indexed,
+            (4, 8 | 16) => ColorType::GrayscaleAlpha,
+            (6, 8 | 16) => ColorType::Rgba,
+            _ => return Err(Error::InvalidHeader),
+        };
+        // the sides are limited to 2^31 - 1 by the spec
+        if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE || interlace > 1 {
+            return Err(Error::InvalidHeader);
+        }
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced: interlace == 1,
+        })
+    }
+    fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+    /// the (x0, y0, dx, dy) of each pass
+    fn passes(&self) -> &'static [(usize, usize, usize, usize)] {
+        if self.interlaced {
+            &ADAM7
+        } else {
+            &[(0, 0, 1, 1)]
+        }
+    }
+    /// width and height of the pass' sub image, either may be 0
+    fn pass_size(&self, (x0, y0, dx, dy): (usize, usize, usize, usize)) -> (usize, usize) {
+        (
+            self.width.saturating_sub(x0).div_ceil(dx),
+            self.height.saturating_sub(y0).div_ceil(dy),
+        )
+    }
+    /// bytes of the decompressed data, every row of every pass with its filter type byte.
+    /// None if that doesn't fit in a usize
+    fn filtered_len(&self) -> Option<usize> {
+        self.passes().iter().try_fold(0usize, |total, &pass| {
+            let (width, height) = self.pass_size(pass);
+            if width == 0 || height == 0 {
+                return Some(total);
+            }
+            let stride = width.checked_mul(self.bits_per_pixel())?.div_ceil(8);
+            total.checked_add(stride.checked_add(1)?.checked_mul(height)?)
+        })
+    }
+}
+
+/// panics on an image without pixels, png doesn't have them, or with a side larger than 2^31 - 1
+pub fn encode(image: &Image<Color>, format: Format) -> Vec<u8> {
+    assert!(image.width() > 0 && image.height() > 0, "empty png");
+    assert!(
+        image.width() <= MAX_SIDE && image.height() <= MAX_SIDE,
+        "{}x{} is too large for png",
+        image.width(),
+        image.height()
+    );
+    let (color_type, bit_depth) = match format {
+        Format::Rgb8 => (ColorType::Rgb, 8),
+        Format::Rgba8 => (ColorType::Rgba, 8),
+        Format::Rgb16 => (ColorType::Rgb, 16),
+        Format::Rgba16 => (ColorType::Rgba, 16),
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height() as u32).to_be_bytes());
+    // no compression method, filter method nor interlacing but the default ones
+    header.extend_from_slice(&[bit_depth, color_type as u8, 0, 0, 0]);
+
+    let max = ((1u32 << bit_depth) - 1) as f32;
+    let bytes_per_pixel = color_type.channels() * bit_depth as usize / 8;
+    let mut filtered = Vec::new();
+    let mut previous = vec![0; image.width() * bytes_per_pixel];
+    for row in image.rows() {
+        let mut bytes = Vec::with_capacity(previous.len());
+        for c in row {
+            let channels = [c.r, c.g, c.b, c.a];
+            for v in &channels[..color_type.channels()] {
+                let v = (v.clamp(0.0, 1.0) * max).round() as u16;
+                if bit_depth == 16 {
+                    bytes.extend_from_slice(&v.to_be_bytes());
+                } else {
+                    bytes.push(v as u8);
+                }
+            }
+        }
+        filter_row(&bytes, &previous, bytes_per_pixel, &mut filtered);
+        previous = bytes;
+    }
+
+    let mut output = SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", &header);
+    write_chunk(
+        &mut output,
+        b"IDAT",
+        &zlib::compress(&filtered, Level::DEFAULT),
+    );
+    write_chunk(&mut output, b"IEND", &[]);
+    output
+}
+
+fn write_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+    let crc = crc32(&output[start..]);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// every color type, bit depth and interlacing. samples are scaled to [0, 1], alpha is 1
+/// without an alpha channel or a tRNS chunk
+pub fn decode(data: &[u8]) -> Result<Image<Color>, Error> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(Error::InvalidSignature);
+    }
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency = None;
+    let mut compressed = Vec::new();
+    let mut position = SIGNATURE.len();
+    loop {
+        let length = data
+            .get(position..position + 4)
+            .ok_or(Error::UnexpectedEnd)?;
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        // the type and the data are checked
+        let checked = data
+            .get(position + 4..position + 8 + length)
+            .ok_or(Error::UnexpectedEnd)?;
+        let crc = data
+            .get(position + 8 + length..position + 12 + length)
+            .ok_or(Error::UnexpectedEnd)?;
+        if crc32(checked) != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err(Error::ChecksumMismatch);
+        }
+        position += 12 + length;
+        let (kind, body) = checked.split_at(4);
+        if header.is_none() && kind != b"IHDR" {
+            return Err(Error::InvalidHeader);
+        }
+        match kind {
+            b"IHDR" => header = Some(Header::parse(body)?),
+            b"PLTE" => palette = body,
+            b"tRNS" => transparency = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // ancillary chunks start with a lowercase letter and can be skipped
+            _ if kind[0].is_ascii_uppercase() => return Err(Error::Unsupported),
+            _ => {}
+        }
+    }
+    let header = header.ok_or(Error::InvalidHeader)?;
+    let expected = header.filtered_len().ok_or(Error::InvalidHeader)?;
+    // a few bytes can inflate to gigabytes, so no more than the header asks for
+    let filtered =
+        zlib::decompress_limited(&compressed, expected).map_err(|error| match error {
+            deflate::Error::TooLong => Error::InvalidData,
+            error => Error::Deflate(error),
+        })?;
+    // checked before allocating the image, which the header alone could make huge
+    if filtered.len() != expected {
+        return Err(Error::InvalidData);
+    }
+    let pixel = PixelReader::new(&header, palette, transparency);
+
+    let mut image = Image::new(header.width, header.height, Color::BLACK);
+    let bytes_per_pixel = header.bits_per_pixel().div_ceil(8);
+    let mut offset = 0;
+    for &(x0, y0, dx, dy) in header.passes() {
+        let (width, height) = header.pass_size((x0, y0, dx, dy));
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let stride = (width * header.bits_per_pixel()).div_ceil(8);
+        let mut previous = vec![0; stride];
+        for y in 0..height {
+            let line = filtered
+                .get(offset..offset + 1 + stride)
+                .ok_or(Error::InvalidData)?;
+            offset += 1 + stride;
+            let mut row = line[1..].to_vec();
+            unfilter_row(line[0], &mut row, &previous, bytes_per_pixel)?;
+            for x in 0..width {
+                image.put_pixel(x0 + x * dx, y0 + y * dy, pixel.read(&row, x)?);
+            }
+            previous = row;
+        }
+    }
+    Ok(image)
+}
+
+/// turns the samples of a row into colors
+struct PixelReader<'a> {
+    color_type: ColorType,
+    bit_depth: u8,
+    palette: &'a [u8],
+    /// alpha of the palette entries for indexed images, a single transparent color otherwise
+    transparency: Option<&'a [u8]>,
+}
+impl<'a> PixelReader<'a> {
+    fn new(header: &Header, palette: &'a [u8], transparency: Option<&'a [u8]>) -> Self {
+        Self {
+            color_type: header.color_type,
+            bit_depth: header.bit_depth,
+            palette,
+            transparency,
+        }
+    }
+    /// the `i`th sample of the row, packed from the most significant bit
+    fn sample(&self, row: &[u8], i: usize) -> u16 {
+        match self.bit_depth {
+            16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+            8 => row[i] as u16,
+            depth => {
+                let bit = i * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                (row[bit / 8] >> shift) as u16 & ((1 << depth) - 1)
+            }
+        }
+    }
+    /// the transparent color, if any
+    fn key(&self) -> Option<[u16; 3]> {
+        let t = self.transparency?;
+        let at = |i: usize| Some(u16::from_be_bytes([*t.get(2 * i)?, *t.get(2 * i + 1)?]));
+        match self.color_type {
+            ColorType::Grayscale => at(0).map(|g| [g, g, g]),
+            ColorType::Rgb => Some([at(0)?, at(1)?, at(2)?]),
+            _ => None,
+        }
+    }
+    fn read(&self, row: &[u8], x: usize) -> Result<Color, Error> {
+        let channels = self.color_type.channels();
+        let sample = |c: usize| self.sample(row, x * channels + c);
+        let max = ((1u32 << self.bit_depth) - 1) as f32;
+        let unit = |v: u16| v as f32 / max;
+        let opaque = |rgb: [u16; 3]| if self.key() == Some(rgb) { 0.0 } else { 1.0 };
+        Ok(match self.color_type {
+            ColorType::Grayscale => {
+                let g = sample(0);
+                rgba(unit(g), unit(g), unit(g), opaque([g, g, g]))
+            }
+            ColorType::Rgb => {
+                let rgb = [sample(0), sample(1), sample(2)];
+                rgba(unit(rgb[0]), unit(rgb[1]), unit(rgb[2]), opaque(rgb))
+            }
+            ColorType::Indexed => {
+                let i = sample(0) as usize;
+                let entry = self
+                    .palette
+                    .get(3 * i..3 * i + 3)
+                    .ok_or(Error::InvalidData)?;
+                let alpha = self.transparency.and_then(|t| t.get(i)).map_or(255, |a| *a);
+                let byte = |v: u8| v as f32 / 255.0;
+                rgba(byte(entry[0]), byte(entry[1]), byte(entry[2]), byte(alpha))
+            }
+            ColorType::GrayscaleAlpha => {
+                let g = unit(sample(0));
+                rgba(g, g, g, unit(sample(1)))
+            }
+            ColorType::Rgba => rgba(
+                unit(sample(0)),
+                unit(sample(1)),
+                unit(sample(2)),
+                unit(sample(3)),
+            ),
+        })
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// what filter `kind` predicts for byte `i`, from the byte a pixel before `a`, the one above
+/// `b` and the one above that `c`
+fn predict(kind: u8, row: &[u8], previous: &[u8], i: usize, bytes_per_pixel: usize) -> u8 {
+    let a = if i >= bytes_per_pixel {
+        row[i - bytes_per_pixel]
+    } else {
+        0
+    };
+    let b = previous[i];
+    let c = if i >= bytes_per_pixel {
+        previous[i - bytes_per_pixel]
+    } else {
+        0
+    };
+    match kind {
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => paeth(a, b, c),
+        _ => 0,
+    }
+}
+
+fn unfilter_row(
+    kind: u8,
+    row: &mut [u8],
+    previous: &[u8],
+    bytes_per_pixel: usize,
+) -> Result<(), Error> {
+    if kind > 4 {
+        return Err(Error::InvalidData);
+    }
+    // left to right, predictions use the bytes already unfiltered
+    for i in 0..row.len() {
+        row[i] = row[i].wrapping_add(predict(kind, row, previous, i, bytes_per_pixel));
+    }
+    Ok(())
+}
+
+/// appends the filter type and the filtered row, using the filter with the smallest sum of
+/// absolute differences, the usual heuristic
+fn filter_row(row: &[u8], previous: &[u8], bytes_per_pixel: usize, output: &mut Vec<u8>) {
+    let filtered = |kind: u8| -> Vec<u8> {
+        (0..row.len())
+            .map(|i| row[i].wrapping_sub(predict(kind, row, previous, i, bytes_per_pixel)))
+            .collect()
+    };
+    let score =
+        |bytes: &[u8]| -> u32 { bytes.iter().map(|b| (*b as i8).unsigned_abs() as u32).sum() };
+    let (kind, bytes) = (0..=4)
+        .map(|kind| (kind, filtered(kind)))
+        .min_by_key(|(_, bytes)| score(bytes))
+        .unwrap();
+    output.push(kind);
+    output.extend(bytes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// the sample formula of the fixtures in assets/png
+    fn expected(x: usize, y: usize, channel: usize, bit_depth: u8) -> f32 {
+        let v = x * 3 + y * 5 + channel * 7;
+        if bit_depth == 16 {
+            (v * 997 % 65536) as f32 / 65535.0
+        } else {
+            let max = (1 << bit_depth) - 1;
+            (v % (max + 1)) as f32 / max as f32
+        }
+    }
+    fn check(image: &Image<Color>, bit_depth: u8, channels: usize) {
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let c = image.get_pixel(x, y);
+                let rgb = if channels >= 3 { [0, 1, 2] } else { [0, 0, 0] };
+                assert_eq!(
+                    [c.r, c.g, c.b],
+                    rgb.map(|i| expected(x, y, i, bit_depth)),
+                    "{x} {y}"
+                );
+                if channels == 2 || channels == 4 {
+                    assert_eq!(c.a, expected(x, y, channels - 1, bit_depth));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grayscale_fixtures() {
+        let image = decode(include_bytes!("../../assets/png/gray1.png")).unwrap();
+        assert_eq!((image.width(), image.height()), (13, 5));
+        check(&image, 1, 1);
+        let image = decode(include_bytes!("../../assets/png/gray2_adam7.png")).unwrap();
+        check(&image, 2, 1);
+        let image = decode(include_bytes!("../../assets/png/gray_alpha8.png")).unwrap();
+        check(&image, 8, 2);
+        let image = decode(include_bytes!("../../assets/png/gray16_trns.png")).unwrap();
+        check(&image, 16, 1);
+        // the color of (3, 2) is transparent
+        assert_eq!(image.get_pixel(3, 2).a, 0.0);
+        assert_eq!(image.get_pixel(2, 3).a, 1.0);
+    }
+    #[test]
+    fn color_fixtures() {
+        let image = decode(include_bytes!("../../assets/png/rgb8_adam7.png")).unwrap();
+        assert_eq!((image.width(), image.height()), (17, 11));
+        check(&image, 8, 3);
+        // the color of (1, 0) is transparent
+        assert_eq!(image.get_pixel(1, 0).a, 0.0);
+        assert_eq!(image.get_pixel(0, 0).a, 1.0);
+        let image = decode(include_bytes!("../../assets/png/rgba16_adam7.png")).unwrap();
+        check(&image, 16, 4);
+    }
+    #[test]
+    fn indexed_fixture() {
+        let image = decode(include_bytes!("../../assets/png/indexed4_trns.png")).unwrap();
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let i = (x + y) % 16;
+                let c = image.get_pixel(x, y);
+                let alpha = if i < 8 { i * 30 } else { 255 };
+                assert_eq!(
+                    [c.r, c.g, c.b, c.a],
+                    [i * 16, 255 - i * 16, i * 5, alpha].map(|v| v as f32 / 255.0)
+                );
+            }
+        }
+    }
+    #[test]
+    fn round_trip() {
+        let mut image = Image::new(37, 23, Color::BLACK);
+        for y in 0..23 {
+            for x in 0..37 {
+                let v = |k: usize| ((x * k + y * y) % 256) as f32 / 255.0;
+                image.put_pixel(x, y, rgba(v(1), v(3), v(7), v(11)));
+            }
+        }
+        for format in [Format::Rgb8, Format::Rgba8, Format::Rgb16, Format::Rgba16] {
+            let decoded = decode(&encode(&image, format)).unwrap();
+            let alpha = matches!(format, Format::Rgba8 | Format::Rgba16);
+            for (p, q) in image.pixels().iter().zip(decoded.pixels()) {
+                let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+                assert!(
+                    close(p.r, q.r) && close(p.g, q.g) && close(p.b, q.b),
+                    "{format:?}"
+                );
+                assert!(close(if alpha { p.a } else { 1.0 }, q.a));
+            }
+        }
+        // 16 bits keep more than 8
+        let fine = Image::new(1, 1, rgba(0.3, 0.3, 0.3, 1.0));
+        let decoded = decode(&encode(&fine, Format::Rgb16)).unwrap();
+        assert!((decoded.get_pixel(0, 0).r - 0.3).abs() < 1e-5);
+        let decoded = decode(&encode(&fine, Format::Rgb8)).unwrap();
+        assert!((decoded.get_pixel(0, 0).r - 0.3).abs() > 1e-5);
+    }
+    #[test]
+    fn filters() {
+        let previous = [10, 20, 30, 40, 50, 60];
+        let row: [u8; 6] = [11, 25, 29, 200, 0, 61];
+        for kind in 0..=4 {
+            let mut output = Vec::new();
+            let filtered: Vec<u8> = (0..row.len())
+                .map(|i| row[i].wrapping_sub(predict(kind, &row, &previous, i, 2)))
+                .collect();
+            output.extend(&filtered);
+            unfilter_row(kind, &mut output, &previous, 2).unwrap();
+            assert_eq!(output, row);
+        }
+        // a row like the one above is all zeros once filtered with it
+        let mut output = Vec::new();
+        filter_row(&[7; 8], &[7; 8], 1, &mut output);
+        assert_eq!(output, [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(paeth(10, 20, 15), 15);
+        assert_eq!(paeth(10, 20, 30), 10);
+        assert_eq!(unfilter_row(5, &mut [0], &[0], 1), Err(Error::InvalidData));
+    }
+    #[test]
+    fn errors() {
+        let png = encode(&Image::new(2, 2, Color::WHITE), Format::Rgb8);
+        assert_eq!(decode(&png[1..]).err(), Some(Error::InvalidSignature));
+        assert_eq!(
+            decode(&png[..png.len() - 1]).err(),
+            Some(Error::UnexpectedEnd)
+        );
+        let mut corrupted = png.clone();
+        corrupted[20] ^= 1;
+        assert_eq!(decode(&corrupted).err(), Some(Error::ChecksumMismatch));
+
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IDAT", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidHeader));
+        let header = &png[16..29];
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", header);
+        write_chunk(&mut output, b"ABCD", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::Unsupported));
+        // a bit depth rgb can't have
+        let mut bad = header.to_vec();
+        bad[8] = 4;
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", &bad);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidHeader));
+        // pixels missing
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", header);
+        write_chunk(
+            &mut output,
+            b"IDAT",
+            &zlib::compress(&[0, 1, 2, 3], Level::DEFAULT),
+        );
+        write_chunk(&mut output, b"IEND", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidData));
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", header);
+        write_chunk(&mut output, b"IDAT", &[1, 2, 3]);
+        write_chunk(&mut output, b"IEND", &[]);
+        assert!(matches!(decode(&output), Err(Error::Deflate(_))));
+        // too many pixels
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", header);
+        write_chunk(
+            &mut output,
+            b"IDAT",
+            &zlib::compress(&[0; 2 * 7 + 1], Level::DEFAULT),
+        );
+        write_chunk(&mut output, b"IEND", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidData));
+        // a huge size in a tiny file fails before allocating the image
+        let mut output = SIGNATURE.to_vec();
+        let mut huge = header.to_vec();
+        huge[..8].copy_from_slice(&[0x7f, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff]);
+        write_chunk(&mut output, b"IHDR", &huge);
+        write_chunk(
+            &mut output,
+            b"IDAT",
+            &zlib::compress(&[0; 7], Level::DEFAULT),
+        );
+        write_chunk(&mut output, b"IEND", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidData));
+        // a bomb behind a 1x1 header stops inflating at the size the header asks for
+        let mut tiny = header.to_vec();
+        tiny[..8].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+        let bomb = zlib::compress(&vec![0; 1 << 20], Level::FASTEST);
+        assert!(bomb.len() < 5000);
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", &tiny);
+        write_chunk(&mut output, b"IDAT", &bomb);
+        write_chunk(&mut output, b"IEND", &[]);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidData));
+        // larger than the spec allows
+        huge[0] = 0x80;
+        let mut output = SIGNATURE.to_vec();
+        write_chunk(&mut output, b"IHDR", &huge);
+        assert_eq!(decode(&output).err(), Some(Error::InvalidHeader));
+    }
+}
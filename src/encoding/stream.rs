@@ -0,0 +1,366 @@
+//! compression and decompression through `Write` and `Read`, a part at a time
+use std::io::{self, Read, Write};
+
+use super::{
+    checksum::{Adler32, Crc32},
+    deflate::{self, BitReader, BitWriter, Error, Level, WINDOW_SIZE},
+    gzip, zlib,
+};
+
+/// how many bytes are compressed at once, and read at once from the compressed data
+const CHUNK: usize = 1 << 16;
+
+/// what is around the deflate data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+    Raw,
+    Zlib,
+    Gzip,
+}
+
+/// the checksum of the trailer
+enum Checksum {
+    None,
+    Adler32(Adler32),
+    Crc32(Crc32),
+}
+impl Checksum {
+    fn new(framing: Framing) -> Self {
+        match framing {
+            Framing::Raw => Checksum::None,
+            Framing::Zlib => Checksum::Adler32(Adler32::new()),
+            Framing::Gzip => Checksum::Crc32(Crc32::new()),
+        }
+    }
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Checksum::None => {}
+            Checksum::Adler32(adler) => adler.update(data),
+            Checksum::Crc32(crc) => crc.update(data),
+        }
+    }
+    /// the trailer after `size` bytes of data
+    fn trailer(&self, size: usize) -> Vec<u8> {
+        match self {
+            Checksum::None => Vec::new(),
+            Checksum::Adler32(adler) => adler.finish().to_be_bytes().to_vec(),
+            Checksum::Crc32(crc) => gzip::trailer(crc.finish(), size).to_vec(),
+        }
+    }
+}
+
+/// compresses what is written to it, a chunk at a time. `finish` writes the end of the data
+pub struct Encoder<W: Write> {
+    inner: W,
+    level: Level,
+    /// the end of the data already compressed that matches can reach, then the data not
+    /// compressed yet from `start`
+    buffer: Vec<u8>,
+    start: usize,
+    writer: BitWriter,
+    checksum: Checksum,
+    size: usize,
+}
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, framing: Framing, level: Level) -> Self {
+        let mut writer = BitWriter::default();
+        match framing {
+            Framing::Raw => {}
+            Framing::Zlib => writer.output.extend_from_slice(&zlib::header(level)),
+            Framing::Gzip => writer.output.extend_from_slice(&gzip::header(level)),
+        }
+        Self {
+            inner,
+            level,
+            buffer: Vec::new(),
+            start: 0,
+            writer,
+            checksum: Checksum::new(framing),
+            size: 0,
+        }
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// compresses the data not compressed yet and writes the whole bytes
+    fn compress(&mut self, last: bool) -> io::Result<()> {
+        deflate::write_blocks(&mut self.writer, &self.buffer, self.start, self.level, last);
+        self.inner.write_all(&self.writer.output)?;
+        self.writer.output.clear();
+        let old = self.buffer.len().saturating_sub(WINDOW_SIZE);
+        self.buffer.drain(..old);
+        self.start = self.buffer.len();
+        Ok(())
+    }
+    /// compresses the rest and writes the trailer, returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.compress(true)?;
+        self.writer.align();
+        let trailer = self.checksum.trailer(self.size);
+        self.writer.output.extend_from_slice(&trailer);
+        self.inner.write_all(&self.writer.output)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.checksum.update(buf);
+        self.size += buf.len();
+        if self.buffer.len() - self.start >= CHUNK {
+            self.compress(false)?;
+        }
+        Ok(buf.len())
+    }
+    /// only flushes the inner writer, the data is compressed by chunks
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Header,
+    Blocks,
+    Trailer,
+    Done,
+}
+
+/// decompresses what is read from the inner reader, a block at a time. a block is decoded
+/// again from its start when the compressed data read so far ends in it
+pub struct Decoder<R: Read> {
+    inner: R,
+    framing: Framing,
+    state: State,
+    /// compressed data not decoded yet, from the `bit`th bit
+    input: Vec<u8>,
+    bit: usize,
+    end_of_input: bool,
+    /// decompressed data not read yet from `read`, after what matches can reach
+    output: Vec<u8>,
+    read: usize,
+    checksum: Checksum,
+    size: usize,
+}
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R, framing: Framing) -> Self {
+        Self {
+            inner,
+            framing,
+            state: State::Header,
+            input: Vec::new(),
+            bit: 0,
+            end_of_input: false,
+            output: Vec::new(),
+            read: 0,
+            checksum: Checksum::new(framing),
+            size: 0,
+        }
+    }
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+    /// the next part of the data, reading more of it while it ends too early
+    fn step(&mut self) -> io::Result<()> {
+        loop {
+            match self.try_step() {
+                Err(Error::UnexpectedEnd) if !self.end_of_input => {
+                    // a chunk at least, to not decode a block again for every few bytes
+                    let mut chunk = vec![0; CHUNK];
+                    let wanted = self.input.len() + CHUNK;
+                    while self.input.len() < wanted {
+                        let n = self.inner.read(&mut chunk)?;
+                        if n == 0 {
+                            self.end_of_input = true;
+                            break;
+                        }
+                        self.input.extend_from_slice(&chunk[..n]);
+                    }
+                }
+                Err(error) => {
+                    let kind = if error == Error::UnexpectedEnd {
+                        io::ErrorKind::UnexpectedEof
+                    } else {
+                        io::ErrorKind::InvalidData
+                    };
+                    return Err(io::Error::new(kind, format!("{error:?}")));
+                }
+                Ok(()) => return Ok(()),
+            }
+        }
+    }
+    /// moves to the next state, or changes nothing if the input ends before
+    fn try_step(&mut self) -> Result<(), Error> {
+        match self.state {
+            State::Header => {
+                let length = match self.framing {
+                    Framing::Raw => 0,
+                    Framing::Zlib => zlib::header_length(&self.input)?,
+                    Framing::Gzip => gzip::header_length(&self.input)?,
+                };
+                self.bit = length * 8;
+                self.state = State::Blocks;
+            }
+            State::Blocks => {
+                // what was read and can't be matched anymore
+                let old = self.read.min(self.output.len().saturating_sub(WINDOW_SIZE));
+                if old >= CHUNK {
+                    self.output.drain(..old);
+                    self.read -= old;
+                }
+                let mut reader = BitReader::new(&self.input, self.bit);
+                let start = self.output.len();
+                let last =
+                    deflate::inflate_block(&mut reader, &mut self.output, usize::MAX).inspect_err(|_| {
+                        self.output.truncate(start);
+                    })?;
+                self.checksum.update(&self.output[start..]);
+                self.size += self.output.len() - start;
+                self.bit = if last {
+                    self.state = State::Trailer;
+                    reader.consumed() * 8
+                } else {
+                    reader.bit_position()
+                };
+                let used = self.bit / 8;
+                self.input.drain(..used);
+                self.bit -= used * 8;
+            }
+            State::Trailer => {
+                let expected = self.checksum.trailer(self.size);
+                let trailer = self
+                    .input
+                    .get(..expected.len())
+                    .ok_or(Error::UnexpectedEnd)?;
+                if trailer != expected {
+                    return Err(Error::ChecksumMismatch);
+                }
+                self.state = State::Done;
+            }
+            State::Done => {}
+        }
+        Ok(())
+    }
+}
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.read == self.output.len() && self.state != State::Done {
+            self.step()?;
+        }
+        let n = buf.len().min(self.output.len() - self.read);
+        buf[..n].copy_from_slice(&self.output[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// compressible but not too much
+    fn data(len: usize) -> Vec<u8> {
+        let mut state = 7u32;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                if (state >> 16) & 3 == 0 {
+                    (state >> 24) as u8
+                } else {
+                    (i / 100) as u8
+                }
+            })
+            .collect()
+    }
+    /// gives the data a few bytes at a time
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn encoder() {
+        // more than a chunk
+        let data = data(70_000);
+        for level in [Level::NONE, Level::FASTEST, Level::BEST] {
+            let mut encoder = Encoder::new(Vec::new(), Framing::Zlib, level);
+            for part in data.chunks(1000) {
+                encoder.write_all(part).unwrap();
+            }
+            // compressed before the end
+            assert!(!encoder.get_ref().is_empty());
+            let compressed = encoder.finish().unwrap();
+            assert_eq!(zlib::decompress(&compressed).unwrap(), data, "{level:?}");
+        }
+        let mut encoder = Encoder::new(Vec::new(), Framing::Gzip, Level::DEFAULT);
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(gzip::decompress(&compressed).unwrap(), data);
+        let encoder = Encoder::new(Vec::new(), Framing::Raw, Level::DEFAULT);
+        assert_eq!(encoder.finish().unwrap(), [3, 0]);
+    }
+    #[test]
+    fn decoder() {
+        // more than the window
+        let data = data(40_000);
+        for (framing, compressed) in [
+            (Framing::Raw, deflate::compress(&data, Level::DEFAULT)),
+            (Framing::Zlib, zlib::compress(&data, Level::NONE)),
+            (Framing::Gzip, gzip::compress(&data, Level::BEST)),
+        ] {
+            let mut output = Vec::new();
+            Decoder::new(Trickle(&compressed), framing)
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, data, "{framing:?}");
+        }
+        let mut output = Vec::new();
+        Decoder::new(&[3, 0][..], Framing::Raw)
+            .read_to_end(&mut output)
+            .unwrap();
+        assert!(output.is_empty());
+    }
+    /// slow in a debug build, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn large_streams() {
+        let data = data(1_000_000);
+        for level in [Level::NONE, Level::FASTEST, Level::DEFAULT, Level::BEST] {
+            let mut encoder = Encoder::new(Vec::new(), Framing::Gzip, level);
+            for part in data.chunks(1000) {
+                encoder.write_all(part).unwrap();
+            }
+            let compressed = encoder.finish().unwrap();
+            let mut output = Vec::new();
+            Decoder::new(Trickle(&compressed), Framing::Gzip)
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, data, "{level:?}");
+        }
+    }
+    #[test]
+    fn errors() {
+        let mut compressed = gzip::compress(&data(1000), Level::DEFAULT);
+        let mut output = Vec::new();
+        let error = Decoder::new(&compressed[..compressed.len() - 2], Framing::Gzip)
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        let error = Decoder::new(&compressed[..], Framing::Gzip)
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = Decoder::new(&compressed[..], Framing::Zlib)
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,110 @@
+//! the zlib format, deflate data between a small header and an Adler-32 checksum
+//!
+//! RFC 1950 https://www.rfc-editor.org/rfc/rfc1950
+use super::{
+    checksum::adler32,
+    deflate::{self, Error, Level},
+};
+
+pub fn compress(data: &[u8], level: Level) -> Vec<u8> {
+    let mut output = header(level).to_vec();
+    output.extend(deflate::compress(data, level));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_limited(data, usize::MAX)
+}
+
+/// fails with `TooLong` as soon as the data decompresses to more than `limit` bytes
+pub fn decompress_limited(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let start = header_length(data)?;
+    let (output, used) = deflate::inflate(&data[start..], limit)?;
+    let trailer = data
+        .get(start + used..start + used + 4)
+        .ok_or(Error::UnexpectedEnd)?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&output) {
+        return Err(Error::ChecksumMismatch);
+    }
+    Ok(output)
+}
+
+/// deflate with a 32K window, no dictionary
+pub(super) fn header(level: Level) -> [u8; 2] {
+    let cmf = 0x78;
+    // a hint of how hard the data was compressed
+    let flevel = match level.value() {
+        0 | 1 => 0,
+        2..=5 => 1,
+        6 => 2,
+        _ => 3,
+    };
+    // the header is a multiple of 31
+    let flg = flevel << 6;
+    let check = (31 - (u16::from_be_bytes([cmf, flg]) % 31)) % 31;
+    [cmf, flg | check as u8]
+}
+
+/// the length of the header at the start of the data
+pub(super) fn header_length(data: &[u8]) -> Result<usize, Error> {
+    let [cmf, flg, ..] = *data else {
+        return Err(Error::UnexpectedEnd);
+    };
+    // preset dictionaries are not supported
+    if cmf & 0x0f != 8
+        || cmf >> 4 > 7
+        || u16::from_be_bytes([cmf, flg]) % 31 != 0
+        || flg & 0x20 != 0
+    {
+        return Err(Error::InvalidHeader);
+    }
+    Ok(2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let compressed = compress(b"hello hello hello", Level::DEFAULT);
+        assert_eq!(&compressed[..2], [0x78, 0x9c]);
+        assert_eq!(decompress(&compressed).unwrap(), b"hello hello hello");
+        assert_eq!(
+            compress(b"", Level::DEFAULT),
+            [0x78, 0x9c, 3, 0, 0, 0, 0, 1]
+        );
+        // the headers zlib writes
+        assert_eq!(header(Level::FASTEST), [0x78, 0x01]);
+        assert_eq!(header(Level::new(4)), [0x78, 0x5e]);
+        assert_eq!(header(Level::BEST), [0x78, 0xda]);
+    }
+    #[test]
+    fn errors() {
+        let mut compressed = compress(b"hello hello hello", Level::DEFAULT);
+        assert_eq!(
+            decompress(&compressed[..compressed.len() - 1]),
+            Err(Error::UnexpectedEnd)
+        );
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        assert_eq!(decompress(&compressed), Err(Error::ChecksumMismatch));
+        assert_eq!(decompress(&[0x78, 0x9d, 3, 0]), Err(Error::InvalidHeader));
+        assert_eq!(decompress(&[0x79, 0x9c, 3, 0]), Err(Error::InvalidHeader));
+        assert_eq!(decompress(&[0x78]), Err(Error::UnexpectedEnd));
+    }
+    #[test]
+    fn limited() {
+        let data = [7; 1000];
+        for level in [Level::NONE, Level::DEFAULT] {
+            let compressed = compress(&data, level);
+            assert_eq!(decompress_limited(&compressed, 1000).unwrap(), data);
+            assert_eq!(
+                decompress_limited(&compressed, 999),
+                Err(Error::TooLong),
+                "{level:?}"
+            );
+        }
+    }
+}
@@ -1,5 +1,12 @@
-use std::{f32::INFINITY, ops::Range};
+use std::{
+    f32::{
+        consts::{PI, TAU},
+        INFINITY,
+    },
+    ops::Range,
+};
 
+use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -7,12 +14,16 @@ use crate::{
     data_structures,
     linear_algebra::{
         vector::{cross, dot, vec3, Vector2},
-        Vector3,
+        Onb, Vector3,
     },
-    ray::{HitRecord, Hitable},
+    model_3d::primitives,
+    polynomial::{solve_quadratic, solve_quartic},
+    ray::{HitRecord, Hitable, Interval},
+    sampling,
 };
 
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Sphere {
     pub center: Vector3,
     pub radius: f32,
@@ -21,42 +32,72 @@ impl Sphere {
     pub fn new(center: Vector3, radius: f32) -> Self {
         Self { center, radius }
     }
-}
-
-impl Hitable for Sphere {
-    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+    /// a latitude-longitude mesh of the sphere, with the same uv mapping
+    pub fn tessellate(&self, segments: usize, rings: usize) -> TriMesh {
+        primitives::uv_sphere(self.radius, segments, rings).translated(self.center)
+    }
+    /// the cosine of the half angle of the cone of directions from `origin` toward the sphere,
+    /// None from inside
+    fn cos_theta_max(&self, origin: Vector3) -> Option<f32> {
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        (distance_squared > radius_squared)
+            .then(|| (1.0 - radius_squared / distance_squared).sqrt())
+    }
+    /// u goes around the Y axis from +X towards -Z, v from the south pole to the north pole
+    fn uv(normal: Vector3) -> (f32, f32) {
+        let u = turns(-normal.z, normal.x);
+        let v = (-normal.y).clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+    /// where the line of the ray enters and leaves the sphere, None if it misses or only
+    /// touches it
+    fn roots(&self, ray: &crate::ray::Ray) -> Option<(f32, f32)> {
         let oc = ray.origin - self.center;
         let a = ray.direction.length_squared();
         let half_b = dot(oc, ray.direction);
         let c = oc.length_squared() - self.radius * self.radius;
         let discriminant = half_b * half_b - a * c;
         if discriminant <= 0.0 {
-            None
-        } else {
-            let sqrtd = discriminant.sqrt();
-            // find the nearest root that lies in the acceptable range
-            let mut root = (-half_b - sqrtd) / a;
-            if !range.contains(&root) {
-                root = (-half_b + sqrtd) / a;
-                if !range.contains(&root) {
-                    return None;
-                }
-            }
-
-            let t = root;
-            let p = ray.at(t);
-            let mut record = HitRecord {
-                point: p,
-                normal: Vector3::ZERO,
-                t,
-                front_face: false,
-                u: 0.0,
-                v: 0.0,
-            };
-            let outward_normal = (p - self.center) / self.radius;
-            record.set_face_normal(&ray, outward_normal);
-            Some(record)
+            return None;
         }
+        let sqrtd = discriminant.sqrt();
+        Some(((-half_b - sqrtd) / a, (-half_b + sqrtd) / a))
+    }
+    fn record(&self, ray: &crate::ray::Ray, t: f32) -> HitRecord {
+        let point = ray.at(t);
+        let outward_normal = (point - self.center) / self.radius;
+        let (u, v) = Self::uv(outward_normal);
+        let mut record = HitRecord {
+            point,
+            normal: Vector3::ZERO,
+            t,
+            front_face: false,
+            u,
+            v,
+        };
+        record.set_face_normal(ray, outward_normal);
+        record
+    }
+}
+
+impl Hitable for Sphere {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let (near, far) = self.roots(&ray)?;
+        // the nearest root that lies in the acceptable range
+        [near, far]
+            .into_iter()
+            .find(|t| range.contains(t))
+            .map(|t| self.record(&ray, t))
+    }
+    fn intervals(&self, ray: crate::ray::Ray) -> Vec<Interval> {
+        self.roots(&ray)
+            .map(|(near, far)| Interval {
+                enter: self.record(&ray, near),
+                exit: self.record(&ray, far),
+            })
+            .into_iter()
+            .collect()
     }
     fn bounding_box(&self) -> Aabb {
         let half = vec3(self.radius, self.radius, self.radius);
@@ -65,10 +106,32 @@ impl Hitable for Sphere {
             max: self.center + half,
         }
     }
+    fn can_sample(&self) -> bool {
+        true
+    }
+    /// uniform over the cone of directions toward the sphere, or over all of them from inside
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let ray = crate::ray::Ray::new(origin, direction);
+        if self.hit(ray, 1e-3..f32::INFINITY).is_none() {
+            return 0.0;
+        }
+        match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+    fn random_direction(&self, origin: Vector3, mut rng: &mut dyn RngCore) -> Vector3 {
+        match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => Onb::from_w((self.center - origin).normalize())
+                .to_world(sampling::cone(cos_theta_max, &mut rng)),
+            None => sampling::unit_sphere(&mut rng),
+        }
+    }
 }
 
 /// an axis-aligned box geometry (renderable, while the `Aabb` struct is a math structure only used for bvh)
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Box {
     pub min: Vector3,
     pub max: Vector3,
@@ -77,45 +140,72 @@ impl Box {
     pub fn new(min: Vector3, max: Vector3) -> Self {
         Self { min, max }
     }
+    pub fn tessellate(&self) -> TriMesh {
+        primitives::cuboid(self.min, self.max)
+    }
+    /// each face is mapped onto the unit square, the same way as the tessellated faces
+    fn uv(&self, point: Vector3, normal: Vector3) -> (f32, f32) {
+        let p = (point - self.min) / (self.max - self.min);
+        if normal.x < 0.0 {
+            (p.z, p.y)
+        } else if normal.x > 0.0 {
+            (1.0 - p.z, p.y)
+        } else if normal.y < 0.0 {
+            (p.x, p.z)
+        } else if normal.y > 0.0 {
+            (p.x, 1.0 - p.z)
+        } else if normal.z < 0.0 {
+            (1.0 - p.x, p.y)
+        } else {
+            (p.x, p.y)
+        }
+    }
 }
 
-impl Hitable for Box {
-    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+impl Box {
+    /// where the line of the ray enters and leaves the box, with the axes of the faces it goes
+    /// through
+    fn slabs(&self, ray: &crate::ray::Ray) -> Option<((f32, usize), (f32, usize))> {
         let t_min = (self.min - ray.origin) / ray.direction;
         let t_max = (self.max - ray.origin) / ray.direction;
         let t1 = t_min.min(t_max);
         let t2 = t_min.max(t_max);
-        let t_near = t1.x.max(t1.y).max(t1.z);
-        let t_far = t2.x.min(t2.y).min(t2.z);
-        if t_near > t_far || !range.contains(&t_near) {
-            None
-        } else {
-            let point = ray.at(t_near);
-            // let box_center = (self.min + self.max)*0.5;
-            // let dir = (point - box_center).normalize();
-            let epsilon = 1e-4;
-            let normal = if (point.x - self.min.x).abs() < epsilon {
-                -Vector3::UNIT_X
-            } else if (point.x - self.max.x).abs() < epsilon {
-                Vector3::UNIT_X
-            } else if (point.y - self.min.y).abs() < epsilon {
-                -Vector3::UNIT_Y
-            } else if (point.y - self.max.y).abs() < epsilon {
-                Vector3::UNIT_Y
-            } else if (point.z - self.min.z).abs() < epsilon {
-                -Vector3::UNIT_Z
-            } else {
-                Vector3::UNIT_Z
-            };
+        // the ray enters through the face of the slab it enters last, and leaves through
+        // the face of the slab it leaves first
+        let near_axis = (0..3).fold(0, |a, i| if t1[i] > t1[a] { i } else { a });
+        let far_axis = (0..3).fold(0, |a, i| if t2[i] < t2[a] { i } else { a });
+        let (t_near, t_far) = (t1[near_axis], t2[far_axis]);
+        (t_near <= t_far).then_some(((t_near, near_axis), (t_far, far_axis)))
+    }
+    fn record(&self, ray: &crate::ray::Ray, t: f32, axis: usize, entering: bool) -> HitRecord {
+        let d = ray.direction[axis];
+        let sign = if entering { -d.signum() } else { d.signum() };
+        let outward_normal = [Vector3::UNIT_X, Vector3::UNIT_Y, Vector3::UNIT_Z][axis] * sign;
+        let point = ray.at(t);
+        let (u, v) = self.uv(point, outward_normal);
+        let mut record = HitRecord {
+            point,
+            normal: Vector3::ZERO,
+            t,
+            front_face: false,
+            u,
+            v,
+        };
+        record.set_face_normal(ray, outward_normal);
+        record
+    }
+}
 
-            Some(HitRecord {
-                point,
-                normal,
-                t: t_near,
-                front_face: true,
-                u: 0.0,
-                v: 0.0,
-            })
+impl Hitable for Box {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let ((t_near, near_axis), (t_far, far_axis)) = self.slabs(&ray)?;
+        // a ray starting inside the box only hits the exit face
+        if range.contains(&t_near) {
+            Some(self.record(&ray, t_near, near_axis, true))
+        } else if range.contains(&t_far) {
+            Some(self.record(&ray, t_far, far_axis, false))
+        } else {
+            None
         }
     }
     fn bounding_box(&self) -> Aabb {
@@ -124,12 +214,21 @@ impl Hitable for Box {
             max: self.max,
         }
     }
+    fn intervals(&self, ray: crate::ray::Ray) -> Vec<Interval> {
+        self.slabs(&ray)
+            .map(|((t_near, near_axis), (t_far, far_axis))| Interval {
+                enter: self.record(&ray, t_near, near_axis, true),
+                exit: self.record(&ray, t_far, far_axis, false),
+            })
+            .into_iter()
+            .collect()
+    }
 }
 /// https://raytracing.github.io/books/RayTracingTheNextWeek.html#quadrilaterals/definingthequadrilateral
 ///
 /// acturally a parallelogram
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
-#[serde(from = "QuadParams")]
+#[serde(from = "QuadParams", into = "QuadParams")]
 pub struct Quad {
     /// a corner of the parallelogram
     q: Vector3,
@@ -159,6 +258,14 @@ impl Quad {
             w,
         }
     }
+    /// the corner `q` and the two edges `u` and `v` from it
+    pub fn corner_edges(&self) -> (Vector3, Vector3, Vector3) {
+        (self.q, self.u, self.v)
+    }
+    /// a grid mesh of the parallelogram, with the same uv mapping
+    pub fn tessellate(&self, u_segments: usize, v_segments: usize) -> TriMesh {
+        primitives::parallelogram(self.q, self.u, self.v, u_segments, v_segments)
+    }
 }
 impl Hitable for Quad {
     fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
@@ -204,9 +311,21 @@ impl Hitable for Quad {
         let max = a.max(b).max(c).max(d);
         Aabb { min, max }
     }
+    fn can_sample(&self) -> bool {
+        true
+    }
+    /// uniform over the area
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        area_pdf(self, cross(self.u, self.v).length(), origin, direction)
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        let (a, b): (f32, f32) = (rng.random(), rng.random());
+        self.q + self.u * a + self.v * b - origin
+    }
 }
-// middleware for serde deserialize
-#[derive(Deserialize)]
+// middleware for serde, the other fields are derived from these
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct QuadParams {
     q: Vector3,
     u: Vector3,
@@ -217,13 +336,69 @@ impl From<QuadParams> for Quad {
         Self::new(params.q, params.u, params.v)
     }
 }
+impl From<Quad> for QuadParams {
+    fn from(quad: Quad) -> Self {
+        Self {
+            q: quad.q,
+            u: quad.u,
+            v: quad.v,
+        }
+    }
+}
 
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Plane {
     /// a random point on the plane
     pub point: Vector3,
     /// normal of the plane, supposed to be normlized
     pub normal: Vector3,
+    /// direction of u on the plane, derived from the normal if not set
+    #[serde(default)]
+    pub tangent: Option<Vector3>,
+    /// uvs repeat every `tile_size` units along the plane
+    #[serde(default = "default_tile_size")]
+    pub tile_size: f32,
+}
+fn default_tile_size() -> f32 {
+    1.0
+}
+impl Plane {
+    pub fn new(point: Vector3, normal: Vector3) -> Self {
+        Self {
+            point,
+            normal,
+            tangent: None,
+            tile_size: default_tile_size(),
+        }
+    }
+    pub fn with_tangent(self, tangent: Vector3) -> Self {
+        Self {
+            tangent: Some(tangent),
+            ..self
+        }
+    }
+    pub fn with_tile_size(self, tile_size: f32) -> Self {
+        Self { tile_size, ..self }
+    }
+    /// positive on the side the normal points to
+    pub fn signed_distance(&self, p: Vector3) -> f32 {
+        dot(p - self.point, self.normal)
+    }
+    /// coordinates along the tangent and the bitangent, wrapped to [0, 1)
+    fn uv(&self, point: Vector3) -> (f32, f32) {
+        let tangent = match self.tangent {
+            // only the part lying on the plane
+            Some(t) => (t - self.normal * dot(t, self.normal)).normalize(),
+            None => Onb::from_w(self.normal).u,
+        };
+        let bitangent = cross(self.normal, tangent);
+        let offset = (point - self.point) / self.tile_size;
+        (
+            dot(offset, tangent).rem_euclid(1.0),
+            dot(offset, bitangent).rem_euclid(1.0),
+        )
+    }
 }
 impl Hitable for Plane {
     fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
@@ -238,14 +413,16 @@ impl Hitable for Plane {
             return None;
         }
         let intersection = ray.at(t);
-        let rec = HitRecord {
+        let (u, v) = self.uv(intersection);
+        let mut rec = HitRecord {
             point: intersection,
             normal: self.normal,
             t,
-            front_face: denom < 0.0,
-            u: 0.,
-            v: 0.,
+            front_face: false,
+            u,
+            v,
         };
+        rec.set_face_normal(&ray, self.normal);
         Some(rec)
     }
     /// it's wrong, but i just don't want to make the function return a Option<Aabb> for now.
@@ -258,30 +435,42 @@ impl Hitable for Plane {
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Circle {
     center: Vector3,
     radius: f32,
     normal: Vector3,
 }
+impl Circle {
+    pub fn new(center: Vector3, radius: f32, normal: Vector3) -> Self {
+        Self {
+            center,
+            radius,
+            normal,
+        }
+    }
+    pub fn tessellate(&self, segments: usize) -> TriMesh {
+        primitives::disk(self.center, self.normal, self.radius, segments)
+    }
+}
 impl Hitable for Circle {
     fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
-        let plane = Plane {
-            point: self.center,
-            normal: self.normal,
-        };
+        let plane = Plane::new(self.center, self.normal);
         if let Some(hit) = plane.hit(ray, range) {
             let intersection = hit.point;
             let distance = intersection - self.center;
             if distance.length_squared() > self.radius * self.radius {
                 return None;
             }
+            // polar coordinates: u goes around the center, v from the center to the edge
+            let local = Onb::from_w(self.normal.normalize()).to_local(distance);
             let rec = HitRecord {
                 point: intersection,
-                normal: self.normal,
+                normal: hit.normal,
                 t: hit.t,
                 front_face: hit.front_face,
-                u: 0.,
-                v: 0.,
+                u: turns(local.y, local.x),
+                v: local.length() / self.radius,
             };
             Some(rec)
         } else {
@@ -294,6 +483,447 @@ impl Hitable for Circle {
         let max = self.center + delta;
         Aabb { min, max }
     }
+    fn can_sample(&self) -> bool {
+        true
+    }
+    /// uniform over the area
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        area_pdf(self, PI * self.radius * self.radius, origin, direction)
+    }
+    fn random_direction(&self, origin: Vector3, mut rng: &mut dyn RngCore) -> Vector3 {
+        let point = Onb::from_w(self.normal.normalize()).to_world(sampling::unit_disk(&mut rng));
+        self.center + point * self.radius - origin
+    }
+}
+
+/// a cylinder standing on the disk at `base`, extending `height` along `axis`
+///
+/// u goes around the axis, v along it. caps are mapped onto the unit square.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cylinder {
+    /// center of the bottom disk
+    pub base: Vector3,
+    /// supposed to be normalized
+    pub axis: Vector3,
+    pub radius: f32,
+    pub height: f32,
+    /// closed with a disk on both ends, otherwise a tube
+    pub capped: bool,
+}
+impl Cylinder {
+    pub fn new(base: Vector3, axis: Vector3, radius: f32, height: f32, capped: bool) -> Self {
+        Self {
+            base,
+            axis: axis.normalize(),
+            radius,
+            height,
+            capped,
+        }
+    }
+}
+impl Cylinder {
+    /// gives `offer` all the points where the line of the ray crosses the surface, in the
+    /// returned basis
+    fn crossings(&self, ray: &crate::ray::Ray, mut offer: impl FnMut(Crossing)) -> Onb {
+        let onb = Onb::from_w(self.axis);
+        let p = onb.to_local(ray.origin - self.base);
+        let d = onb.to_local(ray.direction);
+        let (r, h) = (self.radius, self.height);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (p.x * d.x + p.y * d.y);
+        let c = p.x * p.x + p.y * p.y - r * r;
+        for t in solve_quadratic(a as f64, b as f64, c as f64) {
+            let q = p + d * t as f32;
+            if (0.0..=h).contains(&q.z) {
+                let normal = vec3(q.x / r, q.y / r, 0.0);
+                offer((t as f32, normal, turns(q.y, q.x), q.z / h));
+            }
+        }
+        if self.capped {
+            for (z, normal) in [(0.0, -Vector3::UNIT_Z), (h, Vector3::UNIT_Z)] {
+                let t = (z - p.z) / d.z;
+                let q = p + d * t;
+                if q.x * q.x + q.y * q.y <= r * r {
+                    let (u, v) = (0.5 + q.x / (2.0 * r), 0.5 + q.y / (2.0 * r));
+                    offer((t, normal, u, v));
+                }
+            }
+        }
+        onb
+    }
+}
+impl Hitable for Cylinder {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let mut closest = Closest::new(range);
+        let onb = self.crossings(&ray, |(t, normal, u, v)| closest.offer(t, normal, u, v));
+        closest.record(&ray, &onb)
+    }
+    fn intervals(&self, ray: crate::ray::Ray) -> Vec<Interval> {
+        let mut crossings = vec![];
+        let onb = self.crossings(&ray, |crossing| crossings.push(crossing));
+        spans(&ray, &onb, crossings)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let top = self.base + self.axis * self.height;
+        let extent = disk_extent(self.axis, self.radius);
+        Aabb {
+            min: self.base.min(top) - extent,
+            max: self.base.max(top) + extent,
+        }
+    }
+}
+
+/// a cone with its base disk at `base` and its apex at `base + axis * height`
+///
+/// u goes around the axis, v from the base to the apex.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cone {
+    /// center of the base disk
+    pub base: Vector3,
+    /// supposed to be normalized
+    pub axis: Vector3,
+    /// radius of the base disk
+    pub radius: f32,
+    pub height: f32,
+    /// closed with the base disk
+    pub capped: bool,
+}
+impl Cone {
+    pub fn new(base: Vector3, axis: Vector3, radius: f32, height: f32, capped: bool) -> Self {
+        Self {
+            base,
+            axis: axis.normalize(),
+            radius,
+            height,
+            capped,
+        }
+    }
+}
+impl Hitable for Cone {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let onb = Onb::from_w(self.axis);
+        let p = onb.to_local(ray.origin - self.base);
+        let d = onb.to_local(ray.direction);
+        let (r, h) = (self.radius, self.height);
+        let mut closest = Closest::new(range);
+        // x² + y² = k²(h - z)², k being the slope of the side
+        let k2 = (r / h) * (r / h);
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2.0 * (p.x * d.x + p.y * d.y + k2 * (h - p.z) * d.z);
+        let c = p.x * p.x + p.y * p.y - k2 * (h - p.z) * (h - p.z);
+        for t in solve_quadratic(a as f64, b as f64, c as f64) {
+            let q = p + d * t as f32;
+            // the equation also describes the mirrored cone above the apex
+            if (0.0..=h).contains(&q.z) {
+                let normal = vec3(q.x, q.y, k2 * (h - q.z)).normalize_or_zero();
+                closest.offer(t as f32, normal, turns(q.y, q.x), q.z / h);
+            }
+        }
+        if self.capped {
+            let t = -p.z / d.z;
+            let q = p + d * t;
+            if q.x * q.x + q.y * q.y <= r * r {
+                let (u, v) = (0.5 + q.x / (2.0 * r), 0.5 + q.y / (2.0 * r));
+                closest.offer(t, -Vector3::UNIT_Z, u, v);
+            }
+        }
+        closest.record(&ray, &onb)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let apex = self.base + self.axis * self.height;
+        let extent = disk_extent(self.axis, self.radius);
+        Aabb {
+            min: (self.base - extent).min(apex),
+            max: (self.base + extent).max(apex),
+        }
+    }
+}
+
+/// a flat ring, i.e. a disk with a hole
+///
+/// u goes around the center, v from the inner to the outer edge.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annulus {
+    pub center: Vector3,
+    /// supposed to be normalized
+    pub normal: Vector3,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+}
+impl Annulus {
+    pub fn new(center: Vector3, normal: Vector3, inner_radius: f32, outer_radius: f32) -> Self {
+        Self {
+            center,
+            normal: normal.normalize(),
+            inner_radius,
+            outer_radius,
+        }
+    }
+}
+impl Hitable for Annulus {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let onb = Onb::from_w(self.normal);
+        let p = onb.to_local(ray.origin - self.center);
+        let d = onb.to_local(ray.direction);
+        let mut closest = Closest::new(range);
+        let t = -p.z / d.z;
+        let q = p + d * t;
+        let distance = (q.x * q.x + q.y * q.y).sqrt();
+        if (self.inner_radius..=self.outer_radius).contains(&distance) {
+            let v = (distance - self.inner_radius) / (self.outer_radius - self.inner_radius);
+            closest.offer(t, Vector3::UNIT_Z, turns(q.y, q.x), v);
+        }
+        closest.record(&ray, &onb)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let extent = disk_extent(self.normal, self.outer_radius);
+        Aabb {
+            min: self.center - extent,
+            max: self.center + extent,
+        }
+    }
+}
+
+/// a ring-shaped tube around `axis`
+///
+/// u goes around the axis, v around the tube starting from its outer side.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Torus {
+    pub center: Vector3,
+    /// supposed to be normalized
+    pub axis: Vector3,
+    /// distance from the center to the middle of the tube
+    pub major_radius: f32,
+    /// radius of the tube
+    pub minor_radius: f32,
+}
+impl Torus {
+    pub fn new(center: Vector3, axis: Vector3, major_radius: f32, minor_radius: f32) -> Self {
+        Self {
+            center,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+impl Torus {
+    /// gives `offer` all the points where the line of the ray crosses the surface, in the
+    /// returned basis
+    fn crossings(&self, ray: &crate::ray::Ray, mut offer: impl FnMut(Crossing)) -> Onb {
+        let onb = Onb::from_w(self.axis);
+        let length = ray.direction.length();
+        let d = onb.to_local(ray.direction) / length;
+        let origin = onb.to_local(ray.origin - self.center);
+        // start from the point closest to the center to keep the coefficients small
+        let shift = -dot(origin, d);
+        let p = origin + d * shift;
+        let (big_r, r) = (self.major_radius, self.minor_radius);
+        if p.length() > big_r + r {
+            return onb;
+        }
+        // (|p + td|² + R² - r²)² = 4R²((p + td)x² + (p + td)y²), with |d| = 1
+        // Kevin Suffern, Ray Tracing from the Ground Up, chapter 19
+        let (p64, d64) = (
+            [p.x as f64, p.y as f64, p.z as f64],
+            [d.x as f64, d.y as f64, d.z as f64],
+        );
+        let (big_r2, r2) = ((big_r as f64).powi(2), (r as f64).powi(2));
+        let e = p64.iter().map(|x| x * x).sum::<f64>() - big_r2 - r2;
+        let f = p64.iter().zip(d64).map(|(p, d)| p * d).sum::<f64>();
+        let roots = solve_quartic(
+            1.0,
+            4.0 * f,
+            2.0 * e + 4.0 * f * f + 4.0 * big_r2 * d64[2] * d64[2],
+            4.0 * f * e + 8.0 * big_r2 * p64[2] * d64[2],
+            e * e - 4.0 * big_r2 * (r2 - p64[2] * p64[2]),
+        );
+        for t in roots {
+            let q = p + d * t as f32;
+            let distance = (q.x * q.x + q.y * q.y).sqrt();
+            let ring = vec3(q.x, q.y, 0.0) * (big_r / distance);
+            let normal = (q - ring) / r;
+            let (u, v) = (turns(q.y, q.x), turns(q.z, distance - big_r));
+            offer(((t as f32 + shift) / length, normal, u, v));
+        }
+        onb
+    }
+}
+impl Hitable for Torus {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let mut closest = Closest::new(range);
+        let onb = self.crossings(&ray, |(t, normal, u, v)| closest.offer(t, normal, u, v));
+        closest.record(&ray, &onb)
+    }
+    fn intervals(&self, ray: crate::ray::Ray) -> Vec<Interval> {
+        let mut crossings = vec![];
+        let onb = self.crossings(&ray, |crossing| crossings.push(crossing));
+        spans(&ray, &onb, crossings)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let r = self.minor_radius;
+        let extent = disk_extent(self.axis, self.major_radius) + vec3(r, r, r);
+        Aabb {
+            min: self.center - extent,
+            max: self.center + extent,
+        }
+    }
+}
+
+/// all the points within `radius` of the segment from `a` to `b`
+///
+/// u goes around the segment, v along it from the far end of the `a` cap.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Capsule {
+    pub a: Vector3,
+    pub b: Vector3,
+    pub radius: f32,
+}
+impl Capsule {
+    pub fn new(a: Vector3, b: Vector3, radius: f32) -> Self {
+        Self { a, b, radius }
+    }
+}
+impl Hitable for Capsule {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let ab = self.b - self.a;
+        let length = ab.length();
+        let axis = if length > 0.0 {
+            ab / length
+        } else {
+            Vector3::UNIT_Z
+        };
+        let onb = Onb::from_w(axis);
+        let p = onb.to_local(ray.origin - self.a);
+        let d = onb.to_local(ray.direction);
+        let r = self.radius;
+        let mut closest = Closest::new(range);
+        let v = |z: f32| (z + r) / (length + 2.0 * r);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (p.x * d.x + p.y * d.y);
+        let c = p.x * p.x + p.y * p.y - r * r;
+        for t in solve_quadratic(a as f64, b as f64, c as f64) {
+            let q = p + d * t as f32;
+            if (0.0..=length).contains(&q.z) {
+                let normal = vec3(q.x / r, q.y / r, 0.0);
+                closest.offer(t as f32, normal, turns(q.y, q.x), v(q.z));
+            }
+        }
+        // the hemispheres on both ends
+        for z in [0.0, length] {
+            let center = vec3(0.0, 0.0, z);
+            let oc = p - center;
+            let a = dot(d, d);
+            let b = 2.0 * dot(oc, d);
+            let c = dot(oc, oc) - r * r;
+            for t in solve_quadratic(a as f64, b as f64, c as f64) {
+                let q = p + d * t as f32;
+                if (z == 0.0 && q.z <= 0.0) || (z == length && q.z >= length) {
+                    let normal = (q - center) / r;
+                    closest.offer(t as f32, normal, turns(q.y, q.x), v(q.z));
+                }
+            }
+        }
+        closest.record(&ray, &onb)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let r = vec3(self.radius, self.radius, self.radius);
+        Aabb {
+            min: self.a.min(self.b) - r,
+            max: self.a.max(self.b) + r,
+        }
+    }
+}
+
+/// the nearest of several candidate hits in a local frame
+/// t, local outward normal, u, v
+type Crossing = (f32, Vector3, f32, f32);
+
+struct Closest {
+    range: Range<f32>,
+    hit: Option<Crossing>,
+}
+impl Closest {
+    fn new(range: Range<f32>) -> Self {
+        Self { range, hit: None }
+    }
+    fn offer(&mut self, t: f32, normal: Vector3, u: f32, v: f32) {
+        if self.range.contains(&t) && self.hit.is_none_or(|hit| t < hit.0) {
+            self.hit = Some((t, normal, u, v));
+        }
+    }
+    fn record(self, ray: &crate::ray::Ray, onb: &Onb) -> Option<HitRecord> {
+        self.hit.map(|crossing| local_record(ray, onb, crossing))
+    }
+}
+
+fn local_record(ray: &crate::ray::Ray, onb: &Onb, (t, normal, u, v): Crossing) -> HitRecord {
+    let mut record = HitRecord {
+        point: ray.at(t),
+        normal: Vector3::ZERO,
+        t,
+        front_face: false,
+        u,
+        v,
+    };
+    record.set_face_normal(ray, onb.to_world(normal));
+    record
+}
+
+/// the spans inside a closed surface from all the `crossings` of the line of the ray with it
+fn spans(ray: &crate::ray::Ray, onb: &Onb, mut crossings: Vec<Crossing>) -> Vec<Interval> {
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Interval::pair(
+        crossings
+            .into_iter()
+            .map(|crossing| local_record(ray, onb, crossing)),
+    )
+}
+
+/// the solid angle density, seen from `origin`, of points uniformly distributed over the
+/// `area` of a flat shape, for the point hit along `direction`
+fn area_pdf(shape: &impl Hitable, area: f32, origin: Vector3, direction: Vector3) -> f32 {
+    let ray = crate::ray::Ray::new(origin, direction);
+    let Some(rec) = shape.hit(ray, 1e-3..f32::INFINITY) else {
+        return 0.0;
+    };
+    let distance_squared = rec.t * rec.t * direction.length_squared();
+    let cosine = dot(direction.normalize(), rec.normal.normalize()).abs();
+    if cosine < 1e-8 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area)
+}
+
+/// a uniformly distributed point of the triangle with the corner `a` and the edges `e1`, `e2`
+fn triangle_point(a: Vector3, e1: Vector3, e2: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+    let (mut s, mut t): (f32, f32) = (rng.random(), rng.random());
+    // folded back into the triangle from the other half of the parallelogram
+    if s + t > 1.0 {
+        (s, t) = (1.0 - s, 1.0 - t);
+    }
+    a + e1 * s + e2 * t
+}
+
+/// angle of (x, y) in turns, in [0, 1)
+fn turns(y: f32, x: f32) -> f32 {
+    let turns = y.atan2(x) / TAU;
+    if turns < 0.0 {
+        turns + 1.0
+    } else {
+        turns
+    }
+}
+
+/// half size of the bounding box of a disk facing `normal`
+fn disk_extent(normal: Vector3, radius: f32) -> Vector3 {
+    let extent = |n: f32| radius * (1.0 - n * n).max(0.0).sqrt();
+    vec3(extent(normal.x), extent(normal.y), extent(normal.z))
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -304,6 +934,7 @@ struct Vertex {
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Triangle {
     a: Vector3,
     b: Vector3,
@@ -313,95 +944,392 @@ impl Triangle {
     pub fn new(a: Vector3, b: Vector3, c: Vector3) -> Self {
         Self { a, b, c }
     }
+    pub fn vertices(&self) -> [Vector3; 3] {
+        [self.a, self.b, self.c]
+    }
+    /// watertight ray-triangle intersection, rays never slip through the edge shared by two triangles.
+    ///
+    /// slower than `hit`
+    pub fn hit_watertight(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let (t, u, v) = watertight_intersect(self.a, self.b, self.c, ray, range)?;
+        Some(triangle_record(
+            ray,
+            t,
+            u,
+            v,
+            cross(self.b - self.a, self.c - self.a).normalize(),
+        ))
+    }
 }
 impl Hitable for Triangle {
     fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
-        // Möller–Trumbore algorithm
-        // https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection.html
         let e1 = self.b - self.a;
         let e2 = self.c - self.a;
-        let direction = ray.direction;
-        let origin = ray.origin;
-        let ray_cross_e2 = cross(direction, e2);
-        let det = dot(e1, ray_cross_e2);
-
-        if det > -f32::EPSILON && det < f32::EPSILON {
-            return None; // This ray is parallel to this triangle.
-        }
-
-        let inv_det = 1.0 / det;
-        let s = origin - self.a;
-        let u = inv_det * dot(s, ray_cross_e2);
-        if u < 0.0 || u > 1.0 {
-            return None;
+        let (t, u, v) = moller_trumbore_intersect(self.a, e1, e2, ray, range)?;
+        Some(triangle_record(ray, t, u, v, cross(e1, e2).normalize()))
+    }
+    fn bounding_box(&self) -> Aabb {
+        Aabb {
+            min: self.a.min(self.b).min(self.c),
+            max: self.a.max(self.b).max(self.c),
         }
+    }
+    fn can_sample(&self) -> bool {
+        true
+    }
+    /// uniform over the area
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let area = cross(self.b - self.a, self.c - self.a).length() / 2.0;
+        area_pdf(self, area, origin, direction)
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        triangle_point(self.a, self.b - self.a, self.c - self.a, rng) - origin
+    }
+}
 
-        let s_cross_e1 = cross(s, e1);
-        let v = inv_det * dot(direction, s_cross_e1);
-        if v < 0.0 || u + v > 1.0 {
-            return None;
-        }
-        // At this stage we can compute t to find out where the intersection point is on the line.
-        let t = inv_det * dot(e2, s_cross_e1);
-
-        if range.contains(&t) {
-            // ray intersection
-            let intersection_point = origin + direction * t;
-            // TODO: save the normal in struct field?
-            let normal = cross(e1, e2).normalize();
-            return Some(HitRecord {
-                point: intersection_point,
-                normal,
-                t,
-                front_face: dot(normal, direction) < 0.0,
-                u: 0.0,
-                v: 0.0,
-            });
-        } else {
-            // This means that there is a line intersection but not a ray intersection.
-            return None;
+/// a triangle with its edges and normal computed ahead, for the triangles hit many times
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(from = "Triangle", into = "Triangle")]
+pub struct PrecomputedTriangle {
+    a: Vector3,
+    b: Vector3,
+    c: Vector3,
+    // b - a
+    e1: Vector3,
+    // c - a
+    e2: Vector3,
+    // normalized e1 x e2
+    normal: Vector3,
+}
+impl PrecomputedTriangle {
+    pub fn new(a: Vector3, b: Vector3, c: Vector3) -> Self {
+        let e1 = b - a;
+        let e2 = c - a;
+        Self {
+            a,
+            b,
+            c,
+            e1,
+            e2,
+            normal: cross(e1, e2).normalize(),
         }
     }
+    pub fn normal(&self) -> Vector3 {
+        self.normal
+    }
+    /// see `Triangle::hit_watertight`
+    pub fn hit_watertight(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let (t, u, v) = watertight_intersect(self.a, self.b, self.c, ray, range)?;
+        Some(triangle_record(ray, t, u, v, self.normal))
+    }
+}
+impl Hitable for PrecomputedTriangle {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        let (t, u, v) = moller_trumbore_intersect(self.a, self.e1, self.e2, ray, range)?;
+        Some(triangle_record(ray, t, u, v, self.normal))
+    }
     fn bounding_box(&self) -> Aabb {
         Aabb {
             min: self.a.min(self.b).min(self.c),
             max: self.a.max(self.b).max(self.c),
         }
     }
+    fn can_sample(&self) -> bool {
+        true
+    }
+    /// uniform over the area
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let area = cross(self.e1, self.e2).length() / 2.0;
+        area_pdf(self, area, origin, direction)
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        triangle_point(self.a, self.e1, self.e2, rng) - origin
+    }
+}
+impl From<Triangle> for PrecomputedTriangle {
+    fn from(t: Triangle) -> Self {
+        Self::new(t.a, t.b, t.c)
+    }
+}
+impl From<PrecomputedTriangle> for Triangle {
+    fn from(t: PrecomputedTriangle) -> Self {
+        Self::new(t.a, t.b, t.c)
+    }
+}
+
+/// u and v are the barycentric coordinates of b and c
+fn triangle_record(ray: crate::ray::Ray, t: f32, u: f32, v: f32, normal: Vector3) -> HitRecord {
+    let mut rec = HitRecord {
+        point: ray.at(t),
+        normal,
+        t,
+        front_face: true,
+        u,
+        v,
+    };
+    rec.set_face_normal(&ray, normal);
+    rec
+}
+
+/// returns (t, u, v), u and v being the barycentric coordinates of the 2nd and 3rd vertices
+fn moller_trumbore_intersect(
+    a: Vector3,
+    e1: Vector3,
+    e2: Vector3,
+    ray: crate::ray::Ray,
+    range: Range<f32>,
+) -> Option<(f32, f32, f32)> {
+    // Möller–Trumbore algorithm
+    // https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection.html
+    let direction = ray.direction;
+    let ray_cross_e2 = cross(direction, e2);
+    let det = dot(e1, ray_cross_e2);
+
+    if det > -f32::EPSILON && det < f32::EPSILON {
+        return None; // This ray is parallel to this triangle.
+    }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin - a;
+    let u = inv_det * dot(s, ray_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let s_cross_e1 = cross(s, e1);
+    let v = inv_det * dot(direction, s_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    // At this stage we can compute t to find out where the intersection point is on the line.
+    let t = inv_det * dot(e2, s_cross_e1);
+    // otherwise there is a line intersection but not a ray intersection.
+    range.contains(&t).then_some((t, u, v))
+}
+
+/// returns (t, u, v) like `moller_trumbore_intersect`
+fn watertight_intersect(
+    a: Vector3,
+    b: Vector3,
+    c: Vector3,
+    ray: crate::ray::Ray,
+    range: Range<f32>,
+) -> Option<(f32, f32, f32)> {
+    // Woop, Benthin and Wald, Watertight Ray/Triangle Intersection
+    // https://jcgt.org/published/0002/01/05/
+    let d = ray.direction;
+    let abs = vec3(d.x.abs(), d.y.abs(), d.z.abs());
+    // the dominant axis of the ray becomes z
+    let kz = if abs.x > abs.y && abs.x > abs.z {
+        0
+    } else if abs.y > abs.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // keep the winding
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    // shear so the ray goes along +z
+    let sx = d[kx] / d[kz];
+    let sy = d[ky] / d[kz];
+    let sz = 1.0 / d[kz];
+
+    let (a, b, c) = (a - ray.origin, b - ray.origin, c - ray.origin);
+    let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+    let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+    let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+    // scaled barycentric coordinates
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+    // fall back to double precision on the edges
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+    let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+    range.contains(&t).then_some((t, v / det, w / det))
 }
 
 pub struct TriMesh {
     vertices: Vec<Vector3>,
     indices: Vec<usize>,
+    /// per vertex normals, empty if the mesh has none
+    normals: Vec<Vector3>,
+    /// per vertex texture coordinates, empty if the mesh has none
+    uvs: Vec<Vector2>,
 }
 
 impl TriMesh {
     pub fn new(vertices: Vec<Vector3>, indices: Vec<usize>) -> Self {
-        Self { vertices, indices }
+        Self {
+            vertices,
+            indices,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+        }
+    }
+    pub fn with_normals(mut self, normals: Vec<Vector3>) -> Self {
+        self.normals = normals;
+        self
+    }
+    pub fn with_uvs(mut self, uvs: Vec<Vector2>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+    /// move every vertex by `offset`
+    pub fn translated(mut self, offset: Vector3) -> Self {
+        for v in &mut self.vertices {
+            *v = *v + offset;
+        }
+        self
+    }
+    pub fn vertices(&self) -> &[Vector3] {
+        &self.vertices
+    }
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    pub fn normals(&self) -> Option<&[Vector3]> {
+        (!self.normals.is_empty()).then_some(&self.normals)
+    }
+    pub fn uvs(&self) -> Option<&[Vector2]> {
+        (!self.uvs.is_empty()).then_some(&self.uvs)
+    }
+    /// number of triangles, assuming the mesh is valid
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+    pub fn triangles(&self) -> impl Iterator<Item = PrecomputedTriangle> + '_ {
+        self.indices.chunks_exact(3).map(|t| {
+            PrecomputedTriangle::new(
+                self.vertices[t[0]],
+                self.vertices[t[1]],
+                self.vertices[t[2]],
+            )
+        })
+    }
+    /// a bvh over the triangles, to hit the mesh as a whole
+    pub fn bvh(&self) -> Bvh<PrecomputedTriangle> {
+        Bvh::new(self.triangles().collect())
     }
     pub fn validate(&self) -> Result<(), &'static str> {
         if self.indices.len() % 3 != 0 {
             return Err("invalid indices count");
         }
-        let vert_len = self.vertices.len() - 1;
         // indices in bound
         for i in &self.indices {
-            if *i > vert_len {
+            if *i >= self.vertices.len() {
                 return Err("indice out of bound");
             }
         }
+        if !self.normals.is_empty() && self.normals.len() != self.vertices.len() {
+            return Err("normals count doesn't match vertices count");
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != self.vertices.len() {
+            return Err("uvs count doesn't match vertices count");
+        }
 
         return Ok(());
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// a mesh of polygons with any number of sides, e.g. the quads of an OBJ model
+pub struct PolyMesh {
+    vertices: Vec<Vector3>,
+    faces: Vec<Vec<usize>>,
+    /// per vertex normals, empty if the mesh has none
+    normals: Vec<Vector3>,
+    /// per vertex texture coordinates, empty if the mesh has none
+    uvs: Vec<Vector2>,
+}
+
+impl PolyMesh {
+    pub fn new(vertices: Vec<Vector3>, faces: Vec<Vec<usize>>) -> Self {
+        Self {
+            vertices,
+            faces,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+        }
+    }
+    pub fn with_normals(mut self, normals: Vec<Vector3>) -> Self {
+        self.normals = normals;
+        self
+    }
+    pub fn with_uvs(mut self, uvs: Vec<Vector2>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+    pub fn vertices(&self) -> &[Vector3] {
+        &self.vertices
+    }
+    pub fn faces(&self) -> &[Vec<usize>] {
+        &self.faces
+    }
+    pub fn normals(&self) -> Option<&[Vector3]> {
+        (!self.normals.is_empty()).then_some(&self.normals)
+    }
+    pub fn uvs(&self) -> Option<&[Vector2]> {
+        (!self.uvs.is_empty()).then_some(&self.uvs)
+    }
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for face in &self.faces {
+            if face.len() < 3 {
+                return Err("face with less than 3 vertices");
+            }
+            if face.iter().any(|&i| i >= self.vertices.len()) {
+                return Err("indice out of bound");
+            }
+        }
+        if !self.normals.is_empty() && self.normals.len() != self.vertices.len() {
+            return Err("normals count doesn't match vertices count");
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != self.vertices.len() {
+            return Err("uvs count doesn't match vertices count");
+        }
+        Ok(())
+    }
+    /// split every polygon into a triangle fan
+    pub fn triangulate(&self) -> TriMesh {
+        let mut indices = Vec::new();
+        for face in &self.faces {
+            for i in 1..face.len().saturating_sub(1) {
+                indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+            }
+        }
+        TriMesh::new(self.vertices.clone(), indices)
+            .with_normals(self.normals.clone())
+            .with_uvs(self.uvs.clone())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Geometry {
     Sphere(Sphere),
     Quad(Quad),
     Box(Box),
     Circle(Circle),
     Plane(Plane),
+    Triangle(PrecomputedTriangle),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Annulus(Annulus),
+    Torus(Torus),
+    Capsule(Capsule),
 }
 impl Geometry {
     pub fn hit(
@@ -416,6 +1344,12 @@ impl Geometry {
             Geometry::Box(b) => ray.hit(b, range, transform),
             Geometry::Circle(circle) => ray.hit(circle, range, transform),
             Geometry::Plane(plane) => ray.hit(plane, range, transform),
+            Geometry::Triangle(triangle) => ray.hit(triangle, range, transform),
+            Geometry::Cylinder(cylinder) => ray.hit(cylinder, range, transform),
+            Geometry::Cone(cone) => ray.hit(cone, range, transform),
+            Geometry::Annulus(annulus) => ray.hit(annulus, range, transform),
+            Geometry::Torus(torus) => ray.hit(torus, range, transform),
+            Geometry::Capsule(capsule) => ray.hit(capsule, range, transform),
         }
     }
     pub fn compute_aabb(&self) -> Aabb {
@@ -425,25 +1359,688 @@ impl Geometry {
             Geometry::Box(b) => b.bounding_box(),
             Geometry::Circle(circle) => circle.bounding_box(),
             Geometry::Plane(plane) => plane.bounding_box(),
+            Geometry::Triangle(triangle) => triangle.bounding_box(),
+            Geometry::Cylinder(cylinder) => cylinder.bounding_box(),
+            Geometry::Cone(cone) => cone.bounding_box(),
+            Geometry::Annulus(annulus) => annulus.bounding_box(),
+            Geometry::Torus(torus) => torus.bounding_box(),
+            Geometry::Capsule(capsule) => capsule.bounding_box(),
+        }
+    }
+}
+
+impl Hitable for Geometry {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        Geometry::hit(self, ray, range, None)
+    }
+    fn intervals(&self, ray: crate::ray::Ray) -> Vec<Interval> {
+        match self {
+            Geometry::Sphere(sphere) => sphere.intervals(ray),
+            Geometry::Box(b) => b.intervals(ray),
+            Geometry::Cylinder(cylinder) => cylinder.intervals(ray),
+            Geometry::Torus(torus) => torus.intervals(ray),
+            _ => Interval::step(self, ray),
+        }
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.compute_aabb()
+    }
+    fn can_sample(&self) -> bool {
+        matches!(
+            self,
+            Geometry::Sphere(_) | Geometry::Quad(_) | Geometry::Circle(_) | Geometry::Triangle(_)
+        )
+    }
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        match self {
+            Geometry::Sphere(sphere) => sphere.pdf_value(origin, direction),
+            Geometry::Quad(quad) => quad.pdf_value(origin, direction),
+            Geometry::Circle(circle) => circle.pdf_value(origin, direction),
+            Geometry::Triangle(triangle) => triangle.pdf_value(origin, direction),
+            _ => 0.0,
+        }
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        match self {
+            Geometry::Sphere(sphere) => sphere.random_direction(origin, rng),
+            Geometry::Quad(quad) => quad.random_direction(origin, rng),
+            Geometry::Circle(circle) => circle.random_direction(origin, rng),
+            Geometry::Triangle(triangle) => triangle.random_direction(origin, rng),
+            _ => panic!("{self:?} can't be sampled"),
         }
     }
 }
 
-pub struct Bvh {
-    tree: data_structures::binary_tree::Node<BvhNode<Geometry>>,
+/// bounding volume hierarchy, objects are split at the median along the longest axis
+pub struct Bvh<T = Geometry> {
+    tree: data_structures::binary_tree::Node<BvhNode<T>>,
 }
 struct BvhNode<T> {
     volume: Aabb,
+    /// only leaves hold objects
     object: Vec<T>,
 }
 
-impl Bvh {
-    pub fn new() -> Self {
+/// max number of objects in a leaf
+const BVH_LEAF_SIZE: usize = 4;
+
+impl<T: Hitable> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Self {
         Self {
-            tree: data_structures::binary_tree::Node::new(BvhNode {
-                volume: Aabb::from_min_max(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)),
+            tree: Self::build(objects),
+        }
+    }
+    fn build(mut objects: Vec<T>) -> data_structures::binary_tree::Node<BvhNode<T>> {
+        let mut volume = Aabb::empty();
+        let mut centers = Aabb::empty();
+        for object in &objects {
+            let aabb = object.bounding_box();
+            volume = volume.union(&aabb);
+            centers.expand_by_point(aabb.center());
+        }
+        if objects.len() <= BVH_LEAF_SIZE {
+            return data_structures::binary_tree::Node::new(BvhNode {
+                volume,
+                object: objects,
+            });
+        }
+        let axis = centers.longest_axis();
+        objects.sort_by(|a, b| {
+            let (a, b) = (a.bounding_box().center(), b.bounding_box().center());
+            a[axis].total_cmp(&b[axis])
+        });
+        let right = objects.split_off(objects.len() / 2);
+        data_structures::binary_tree::Node::with_children(
+            BvhNode {
+                volume,
                 object: Vec::new(),
-            }),
+            },
+            Self::build(objects),
+            Self::build(right),
+        )
+    }
+    /// the closest hit and the object hit, to look up what belongs to it like its material
+    pub fn hit_object(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<(&T, HitRecord)> {
+        Self::hit_node(&self.tree, ray, range)
+    }
+    fn hit_node(
+        node: &data_structures::binary_tree::Node<BvhNode<T>>,
+        ray: crate::ray::Ray,
+        range: Range<f32>,
+    ) -> Option<(&T, HitRecord)> {
+        if !node.value.volume.intersect_ray(ray, range.clone()) {
+            return None;
+        }
+        let mut closest: Option<(&T, HitRecord)> = None;
+        for object in &node.value.object {
+            let end = closest.map_or(range.end, |(_, rec)| rec.t);
+            closest = object
+                .hit(ray, range.start..end)
+                .map(|rec| (object, rec))
+                .or(closest);
+        }
+        for child in [node.left(), node.right()].into_iter().flatten() {
+            let end = closest.map_or(range.end, |(_, rec)| rec.t);
+            closest = Self::hit_node(child, ray, range.start..end).or(closest);
         }
+        closest
+    }
+}
+impl<T: Hitable> Hitable for Bvh<T> {
+    fn hit(&self, ray: crate::ray::Ray, range: Range<f32>) -> Option<HitRecord> {
+        self.hit_object(ray, range).map(|(_, rec)| rec)
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.tree.value.volume
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ray::Ray;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// the sampled directions hit the shape, and the density integrates to 1 over the sphere
+    fn assert_sampling(shape: &impl Hitable, origin: Vector3) {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let direction = shape.random_direction(origin, &mut rng);
+            assert!(shape.pdf_value(origin, direction) > 0.0, "{direction:?}");
+        }
+        let n = 100_000;
+        let integral: f32 = (0..n)
+            .map(|_| shape.pdf_value(origin, sampling::unit_sphere(&mut rng)))
+            .sum::<f32>()
+            * 4.0
+            * PI
+            / n as f32;
+        assert!((integral - 1.0).abs() < 0.05, "{integral}");
+    }
+
+    #[test]
+    fn light_sampling() {
+        let origin = vec3(0.3, 2.0, 0.5);
+        let sphere = Sphere::new(vec3(0.5, 0.0, 0.0), 1.0);
+        assert_sampling(&sphere, origin);
+        // from inside, every direction
+        assert_sampling(&sphere, vec3(0.5, 0.5, 0.0));
+        assert_eq!(sphere.pdf_value(origin, Vector3::UNIT_Y), 0.0);
+        let quad = Quad::new(
+            vec3(-1.0, 0.0, -1.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(0.0, 0.5, 1.5),
+        );
+        assert_sampling(&quad, origin);
+        // seen from behind as well
+        assert_sampling(&quad, vec3(0.0, -1.0, 1.0));
+        assert_sampling(
+            &Circle::new(Vector3::ZERO, 1.5, vec3(0.0, 2.0, 1.0)),
+            origin,
+        );
+        let (a, b, c) = (
+            vec3(-1.0, 0.0, -1.0),
+            vec3(2.0, 0.5, 0.0),
+            vec3(0.0, 0.0, 2.0),
+        );
+        assert_sampling(&Triangle::new(a, b, c), origin);
+        assert_sampling(&PrecomputedTriangle::new(a, b, c), origin);
+        assert_sampling(&Geometry::Quad(quad), origin);
+        // a small quad seen straight on, the density is about distance² / area
+        let small = Quad::new(
+            vec3(-0.05, 0.0, -0.05),
+            vec3(0.1, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.1),
+        );
+        let pdf = small.pdf_value(vec3(0.0, 2.0, 0.0), -Vector3::UNIT_Y);
+        assert!((pdf - 400.0).abs() < 1e-2, "{pdf}");
+        // shapes that aren't sampled
+        assert_eq!(
+            Geometry::Torus(Torus::new(Vector3::ZERO, Vector3::UNIT_Y, 1.0, 0.25))
+                .pdf_value(origin, -origin),
+            0.0
+        );
+    }
+    #[test]
+    fn triangle_barycentric() {
+        let triangle = Triangle::new(
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let ray = Ray::new(vec3(0.25, 0.5, 1.0), -Vector3::UNIT_Z);
+        let rec = triangle.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert_eq!(rec.t, 1.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.5));
+        assert_eq!(rec.normal, Vector3::UNIT_Z);
+        assert!(rec.front_face);
+        let rec = triangle.hit_watertight(ray, 0.0..f32::INFINITY).unwrap();
+        assert_eq!(rec.t, 1.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.5));
+    }
+    #[test]
+    fn triangle_back_face() {
+        let triangle = PrecomputedTriangle::new(
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let ray = Ray::new(vec3(0.2, 0.2, -1.0), Vector3::UNIT_Z);
+        for rec in [
+            triangle.hit(ray, 0.0..f32::INFINITY).unwrap(),
+            triangle.hit_watertight(ray, 0.0..f32::INFINITY).unwrap(),
+        ] {
+            assert!(!rec.front_face);
+            assert_eq!(rec.normal, -Vector3::UNIT_Z);
+            assert_eq!(triangle.normal(), Vector3::UNIT_Z);
+        }
+        assert!(triangle.hit(ray, 0.0..0.5).is_none());
+        assert!(triangle.hit_watertight(ray, 0.0..0.5).is_none());
+    }
+    #[test]
+    fn triangle_watertight() {
+        // two triangles sharing the edge from a to c, rays along the edge must hit one of them
+        let (a, b, c, d) = (
+            vec3(0.1, 0.3, 0.7),
+            vec3(1.3, 0.2, 0.9),
+            vec3(0.9, 1.7, 0.3),
+            vec3(-0.4, 1.1, 0.5),
+        );
+        let first = PrecomputedTriangle::new(a, b, c);
+        let second = PrecomputedTriangle::new(a, c, d);
+        let origin = vec3(0.3, 0.4, 5.0);
+        for i in 0..=1000 {
+            let target = a + (c - a) * (i as f32 / 1000.0);
+            let ray = Ray::new(origin, target - origin);
+            assert!(
+                first.hit_watertight(ray, 0.0..f32::INFINITY).is_some()
+                    || second.hit_watertight(ray, 0.0..f32::INFINITY).is_some()
+            );
+        }
+    }
+    #[test]
+    fn triangle_geometry() {
+        let geometry: Geometry =
+            serde_json::from_str(r#"{"Triangle":{"a":{"x":0,"y":0,"z":0},"b":{"x":1,"y":0,"z":0},"c":{"x":0,"y":1,"z":0}}}"#)
+                .unwrap();
+        let ray = Ray::new(vec3(0.5, 0.25, 1.0), -Vector3::UNIT_Z);
+        let rec = geometry.hit(ray, 0.0..f32::INFINITY, None).unwrap();
+        assert_eq!(rec.t, 1.0);
+        let aabb = geometry.compute_aabb();
+        assert_eq!(aabb.max, vec3(1.0, 1.0, 0.0));
+        // serialized as a plain triangle
+        let json = serde_json::to_string(&geometry).unwrap();
+        assert!(!json.contains("e1"));
+    }
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-4, "{a:?} != {b:?}");
+    }
+    /// a few rays through the object, every hit must lie within the bounding box
+    fn assert_bounded(object: &impl Hitable) {
+        let aabb = object.bounding_box();
+        let center = (aabb.min + aabb.max) * 0.5;
+        for i in 0..64 {
+            let angle = i as f32 * 0.7;
+            let origin = center + vec3(angle.cos(), (i as f32 * 0.3).sin(), angle.sin()) * 10.0;
+            let target = center + vec3((i % 3) as f32, (i % 5) as f32, (i % 7) as f32) * 0.1;
+            let ray = Ray::new(origin, target - origin);
+            if let Some(rec) = object.hit(ray, 0.0..f32::INFINITY) {
+                let p = rec.point;
+                assert!(
+                    aabb.min.min(p) == aabb.min || (aabb.min - p).length() < 1e-3,
+                    "{p:?}"
+                );
+                assert!(
+                    aabb.max.max(p) == aabb.max || (aabb.max - p).length() < 1e-3,
+                    "{p:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cylinder() {
+        let cylinder = Cylinder::new(Vector3::ZERO, Vector3::UNIT_Y, 1.0, 2.0, false);
+        let ray = Ray::new(vec3(-5.0, 1.0, 0.0), Vector3::UNIT_X);
+        let rec = cylinder.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-5);
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!(rec.front_face);
+        assert!((rec.v - 0.5).abs() < 1e-5);
+        // from the inside the far wall is a back face
+        let rec = cylinder.hit(ray, 4.5..f32::INFINITY).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-5);
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!(!rec.front_face);
+        // through the open tube
+        let down = Ray::new(vec3(0.0, 5.0, 0.0), -Vector3::UNIT_Y);
+        assert!(cylinder.hit(down, 0.0..f32::INFINITY).is_none());
+        let capped = Cylinder {
+            capped: true,
+            ..cylinder
+        };
+        let rec = capped.hit(down, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-5);
+        assert_close(rec.normal, Vector3::UNIT_Y);
+        assert_eq!((rec.u, rec.v), (0.5, 0.5));
+        assert_bounded(&capped);
+        assert_bounded(&Cylinder::new(
+            vec3(1.0, 2.0, 3.0),
+            vec3(1.0, 1.0, 0.5),
+            0.5,
+            1.5,
+            true,
+        ));
+    }
+    #[test]
+    fn cone() {
+        let cone = Cone::new(Vector3::ZERO, Vector3::UNIT_Z, 1.0, 1.0, true);
+        // halfway up the radius is 0.5
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.5), Vector3::UNIT_X);
+        let rec = cone.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-5);
+        assert_close(rec.normal, vec3(-1.0, 0.0, 1.0).normalize());
+        assert!((rec.v - 0.5).abs() < 1e-5);
+        // the mirrored cone above the apex is not part of the surface
+        let above = Ray::new(vec3(-5.0, 0.0, 1.5), Vector3::UNIT_X);
+        assert!(cone.hit(above, 0.0..f32::INFINITY).is_none());
+        let up = Ray::new(vec3(0.2, 0.0, -1.0), Vector3::UNIT_Z);
+        let rec = cone.hit(up, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-5);
+        assert_close(rec.normal, -Vector3::UNIT_Z);
+        assert_bounded(&cone);
+        assert_bounded(&Cone::new(
+            vec3(-1.0, 0.0, 2.0),
+            vec3(0.3, -1.0, 0.2),
+            2.0,
+            0.5,
+            false,
+        ));
+    }
+    #[test]
+    fn annulus() {
+        let annulus = Annulus::new(Vector3::ZERO, Vector3::UNIT_Y, 1.0, 2.0);
+        let hole = Ray::new(vec3(0.5, 1.0, 0.0), -Vector3::UNIT_Y);
+        assert!(annulus.hit(hole, 0.0..f32::INFINITY).is_none());
+        let ring = Ray::new(vec3(1.5, 1.0, 0.0), -Vector3::UNIT_Y);
+        let rec = annulus.hit(ring, 0.0..f32::INFINITY).unwrap();
+        assert_eq!(rec.t, 1.0);
+        assert!(rec.front_face);
+        assert_close(rec.normal, Vector3::UNIT_Y);
+        assert!((rec.v - 0.5).abs() < 1e-5);
+        let below = Ray::new(vec3(1.5, -1.0, 0.0), Vector3::UNIT_Y);
+        let rec = annulus.hit(below, 0.0..f32::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert_close(rec.normal, -Vector3::UNIT_Y);
+        assert_bounded(&annulus);
+    }
+    #[test]
+    fn torus() {
+        let torus = Torus::new(Vector3::ZERO, Vector3::UNIT_Z, 2.0, 0.5);
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X * 2.0);
+        let hits: Vec<f32> = std::iter::successors(torus.hit(ray, 0.0..f32::INFINITY), |rec| {
+            torus.hit(ray, rec.t + 1e-3..f32::INFINITY)
+        })
+        .map(|rec| rec.t)
+        .collect();
+        assert_eq!(hits.len(), 4);
+        for (t, expected) in hits.iter().zip([2.5, 3.5, 6.5, 7.5]) {
+            assert!((t * 2.0 - expected).abs() < 1e-4, "{hits:?}");
+        }
+        let rec = torus.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!(rec.front_face);
+        assert!((rec.u - 0.5).abs() < 1e-5);
+        // on the outer side of the tube, v wraps around 0
+        assert!(rec.v.min(1.0 - rec.v) < 1e-4);
+        // from the inside of the tube
+        let inside = Ray::new(vec3(2.0, 0.0, 0.0), Vector3::UNIT_Z);
+        let rec = torus.hit(inside, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-4);
+        assert!(!rec.front_face);
+        assert_close(rec.normal, -Vector3::UNIT_Z);
+        assert!((rec.v - 0.25).abs() < 1e-4);
+        // through the hole
+        let axis = Ray::new(vec3(0.0, 0.0, 5.0), -Vector3::UNIT_Z);
+        assert!(torus.hit(axis, 0.0..f32::INFINITY).is_none());
+        assert_bounded(&torus);
+        assert_bounded(&Torus::new(
+            vec3(3.0, -1.0, 0.0),
+            vec3(1.0, 2.0, 3.0),
+            1.0,
+            0.25,
+        ));
+    }
+    #[test]
+    fn capsule() {
+        let capsule = Capsule::new(Vector3::ZERO, vec3(0.0, 2.0, 0.0), 0.5);
+        let body = Ray::new(vec3(-5.0, 1.0, 0.0), Vector3::UNIT_X);
+        let rec = capsule.hit(body, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-5);
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!((rec.v - 0.5).abs() < 1e-5);
+        let cap = Ray::new(vec3(0.0, 5.0, 0.0), -Vector3::UNIT_Y);
+        let rec = capsule.hit(cap, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-5);
+        assert_close(rec.normal, Vector3::UNIT_Y);
+        assert!((rec.v - 1.0).abs() < 1e-5);
+        let rec = capsule.hit(cap, 3.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 5.5).abs() < 1e-5);
+        assert!(!rec.front_face);
+        assert_bounded(&capsule);
+    }
+    #[test]
+    fn primitives_geometry() {
+        let json = r#"[
+            {"Cylinder":{"base":{"x":0,"y":0,"z":0},"axis":{"x":0,"y":1,"z":0},"radius":1,"height":2,"capped":true}},
+            {"Cone":{"base":{"x":0,"y":0,"z":0},"axis":{"x":0,"y":1,"z":0},"radius":1,"height":2,"capped":false}},
+            {"Annulus":{"center":{"x":0,"y":1,"z":0},"normal":{"x":0,"y":1,"z":0},"inner_radius":0.5,"outer_radius":1}},
+            {"Torus":{"center":{"x":0,"y":1,"z":0},"axis":{"x":0,"y":1,"z":0},"major_radius":1,"minor_radius":0.25}},
+            {"Capsule":{"a":{"x":0,"y":0,"z":0},"b":{"x":0,"y":2,"z":0},"radius":1}}
+        ]"#;
+        let geometries: Vec<Geometry> = serde_json::from_str(json).unwrap();
+        let ray = Ray::new(vec3(0.75, 5.0, 0.0), -Vector3::UNIT_Y);
+        for geometry in &geometries {
+            let rec = geometry.hit(ray, 0.0..f32::INFINITY, None).unwrap();
+            assert!(geometry.compute_aabb().contains_point(rec.point));
+        }
+        let round_trip: Vec<Geometry> =
+            serde_json::from_str(&serde_json::to_string(&geometries).unwrap()).unwrap();
+        assert_eq!(round_trip.len(), 5);
+    }
+    #[test]
+    fn sphere_uv() {
+        let sphere = Sphere::new(vec3(1.0, 1.0, 1.0), 2.0);
+        let uv = |origin: Vector3| {
+            let ray = Ray::new(sphere.center + origin * 5.0, -origin);
+            let rec = sphere.hit(ray, 0.0..f32::INFINITY).unwrap();
+            (rec.u, rec.v)
+        };
+        let (u, v) = uv(Vector3::UNIT_X);
+        assert!(u.abs() < 1e-5 && (v - 0.5).abs() < 1e-5);
+        let (u, v) = uv(-Vector3::UNIT_Z);
+        assert!((u - 0.25).abs() < 1e-5 && (v - 0.5).abs() < 1e-5);
+        let (u, _) = uv(-Vector3::UNIT_X);
+        assert!((u - 0.5).abs() < 1e-5);
+        assert!((uv(Vector3::UNIT_Y).1 - 1.0).abs() < 1e-5);
+        assert!(uv(-Vector3::UNIT_Y).1.abs() < 1e-5);
+    }
+    #[test]
+    fn box_uv() {
+        let b = Box::new(Vector3::ZERO, vec3(2.0, 2.0, 2.0));
+        let uv = |origin: Vector3, direction: Vector3| {
+            let rec = b
+                .hit(Ray::new(origin, direction), 0.0..f32::INFINITY)
+                .unwrap();
+            (rec.u, rec.v)
+        };
+        assert_eq!(uv(vec3(0.5, 1.5, 5.0), -Vector3::UNIT_Z), (0.25, 0.75));
+        assert_eq!(uv(vec3(0.5, 1.5, -5.0), Vector3::UNIT_Z), (0.75, 0.75));
+        assert_eq!(uv(vec3(5.0, 1.5, 0.5), -Vector3::UNIT_X), (0.75, 0.75));
+        assert_eq!(uv(vec3(-5.0, 1.5, 0.5), Vector3::UNIT_X), (0.25, 0.75));
+        assert_eq!(uv(vec3(0.5, 5.0, 1.5), -Vector3::UNIT_Y), (0.25, 0.25));
+        assert_eq!(uv(vec3(0.5, -5.0, 1.5), Vector3::UNIT_Y), (0.25, 0.75));
+    }
+    #[test]
+    fn circle_uv() {
+        let circle = Circle::new(Vector3::ZERO, 2.0, Vector3::UNIT_Z);
+        let onb = Onb::from_w(Vector3::UNIT_Z);
+        for (direction, u) in [(onb.u, 0.0), (onb.v, 0.25), (-onb.u, 0.5)] {
+            let ray = Ray::new(direction + Vector3::UNIT_Z, -Vector3::UNIT_Z);
+            let rec = circle.hit(ray, 0.0..f32::INFINITY).unwrap();
+            assert!((rec.u - u).abs() < 1e-5, "{} != {u}", rec.u);
+            assert!((rec.v - 0.5).abs() < 1e-5);
+        }
+    }
+    #[test]
+    fn plane_uv() {
+        let plane = Plane::new(Vector3::ZERO, Vector3::UNIT_Y).with_tangent(vec3(1.0, 0.5, 0.0));
+        let ray = Ray::new(vec3(2.25, 1.0, -3.5), -Vector3::UNIT_Y);
+        let rec = plane.hit(ray, 0.0..f32::INFINITY).unwrap();
+        // v goes along cross(normal, tangent) = -Z
+        assert_eq!((rec.u, rec.v), (0.25, 0.5));
+        let rec = plane
+            .with_tile_size(2.0)
+            .hit(ray, 0.0..f32::INFINITY)
+            .unwrap();
+        assert_eq!((rec.u, rec.v), (0.125, 0.75));
+        // tangent and tile size are optional in json
+        let plane: Plane =
+            serde_json::from_str(r#"{"point":{"x":0,"y":0,"z":0},"normal":{"x":0,"y":1,"z":0}}"#)
+                .unwrap();
+        let rec = plane.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((0.0..1.0).contains(&rec.u) && (0.0..1.0).contains(&rec.v));
+    }
+    /// rays from all around `center`: hits must lie on the ray with a unit normal facing it,
+    /// and convex closed objects must be entered through front faces and left through back faces
+    fn assert_normal_invariants(object: &dyn Hitable, center: Vector3, convex: bool) {
+        let mut hits = 0;
+        for i in 0..200 {
+            let (theta, phi) = (i as f32 * 2.4, (i as f32 * 0.37).sin() * 1.5);
+            let origin =
+                center + vec3(theta.cos() * phi.cos(), phi.sin(), theta.sin() * phi.cos()) * 10.0;
+            let offset =
+                vec3((i % 3) as f32, (i % 5) as f32, (i % 7) as f32) * 0.1 - vec3(0.1, 0.2, 0.3);
+            let ray = Ray::new(origin, center + offset - origin);
+            let range = 0.0..f32::INFINITY;
+            let Some(entry) = object.hit(ray, range.clone()) else {
+                continue;
+            };
+            hits += 1;
+            for rec in
+                std::iter::successors(Some(entry), |rec| object.hit(ray, rec.t + 1e-3..range.end))
+            {
+                assert!(range.contains(&rec.t));
+                assert_close(rec.point, ray.at(rec.t));
+                assert!((rec.normal.length() - 1.0).abs() < 1e-4, "{:?}", rec.normal);
+                assert!(dot(rec.normal, ray.direction) <= 1e-6, "{rec:?}");
+            }
+            if convex {
+                assert!(entry.front_face, "{entry:?}");
+                let exit = object.hit(ray, entry.t + 1e-3..f32::INFINITY).unwrap();
+                assert!(!exit.front_face, "{exit:?}");
+                assert!(object.hit(ray, exit.t + 1e-3..f32::INFINITY).is_none());
+                // starting inside, only the exit is hit
+                let inside = Ray::new(center, ray.direction);
+                assert!(!object.hit(inside, 0.0..f32::INFINITY).unwrap().front_face);
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn normal_invariants() {
+        let center = vec3(0.5, -0.25, 1.0);
+        let (a, b, c) = (
+            center + vec3(-1.0, -1.0, 0.3),
+            center + vec3(1.0, -0.5, -0.2),
+            center + vec3(0.0, 1.0, 0.0),
+        );
+        let objects: Vec<(std::boxed::Box<dyn Hitable>, bool)> = vec![
+            (std::boxed::Box::new(Sphere::new(center, 1.0)), true),
+            (
+                std::boxed::Box::new(Box::new(
+                    center - vec3(1.0, 0.5, 0.75),
+                    center + vec3(0.5, 1.0, 1.0),
+                )),
+                true,
+            ),
+            (
+                std::boxed::Box::new(Quad::new(
+                    center - vec3(1.0, 1.0, 0.0),
+                    vec3(2.0, 0.0, 0.5),
+                    vec3(0.0, 2.0, 0.0),
+                )),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Plane::new(center, vec3(0.0, 1.0, 1.0).normalize())),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Circle::new(center, 1.0, vec3(1.0, 0.0, 1.0).normalize())),
+                false,
+            ),
+            (std::boxed::Box::new(Triangle::new(a, b, c)), false),
+            (
+                std::boxed::Box::new(PrecomputedTriangle::new(a, b, c)),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Cylinder::new(
+                    center - Vector3::UNIT_Y,
+                    vec3(0.0, 1.0, 0.2),
+                    0.8,
+                    2.0,
+                    true,
+                )),
+                true,
+            ),
+            (
+                std::boxed::Box::new(Cylinder::new(
+                    center - Vector3::UNIT_Y,
+                    vec3(0.0, 1.0, 0.2),
+                    0.8,
+                    2.0,
+                    false,
+                )),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Cone::new(
+                    center - Vector3::UNIT_Y,
+                    vec3(0.2, 1.0, 0.0),
+                    1.0,
+                    2.0,
+                    true,
+                )),
+                true,
+            ),
+            (
+                std::boxed::Box::new(Annulus::new(center, Vector3::UNIT_Z, 0.3, 1.0)),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Torus::new(center, vec3(1.0, 1.0, 0.0), 1.0, 0.3)),
+                false,
+            ),
+            (
+                std::boxed::Box::new(Capsule::new(
+                    center - Vector3::UNIT_X,
+                    center + Vector3::UNIT_X,
+                    0.5,
+                )),
+                true,
+            ),
+        ];
+        for (object, convex) in &objects {
+            assert_normal_invariants(object.as_ref(), center, *convex);
+        }
+    }
+    #[test]
+    fn bvh_matches_brute_force() {
+        let mesh = primitives::torus(2.0, 0.5, 24, 12);
+        let bvh = mesh.bvh();
+        let triangles: Vec<PrecomputedTriangle> = mesh.triangles().collect();
+        assert_eq!(triangles.len(), mesh.triangle_count());
+        for i in 0..200 {
+            let angle = i as f32 * 0.37;
+            let origin = vec3(
+                angle.cos() * 6.0,
+                (i as f32 * 0.11).sin() * 3.0,
+                angle.sin() * 6.0,
+            );
+            let target = vec3((i % 7) as f32 * 0.5 - 1.5, 0.0, (i % 5) as f32 * 0.5 - 1.0);
+            let ray = Ray::new(origin, target - origin);
+            let expected = triangles
+                .iter()
+                .filter_map(|t| t.hit(ray, 0.0..f32::INFINITY))
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            let rec = bvh.hit(ray, 0.0..f32::INFINITY);
+            assert_eq!(rec.map(|r| r.t), expected.map(|r| r.t));
+        }
+        // a ray starting inside the bounds with a short range
+        let ray = Ray::new(vec3(-2.0, 0.0, 0.0), Vector3::UNIT_Y);
+        assert!(bvh.hit(ray, 0.0..1.0).is_some());
+        assert!(bvh.hit(ray, 0.0..0.4).is_none());
+    }
+    #[test]
+    fn bvh_of_geometry() {
+        let geometries = vec![
+            Geometry::Sphere(Sphere::new(vec3(0.0, 0.0, -5.0), 1.0)),
+            Geometry::Sphere(Sphere::new(vec3(0.0, 0.0, -10.0), 1.0)),
+            Geometry::Box(Box::new(vec3(-1.0, -1.0, 2.0), vec3(1.0, 1.0, 3.0))),
+        ];
+        let bvh = Bvh::new(geometries);
+        let rec = bvh.hit(
+            Ray::new(Vector3::ZERO, -Vector3::UNIT_Z),
+            0.0..f32::INFINITY,
+        );
+        assert_eq!(rec.unwrap().t, 4.0);
+        let rec = bvh.hit(Ray::new(Vector3::ZERO, Vector3::UNIT_Z), 0.0..f32::INFINITY);
+        assert_eq!(rec.unwrap().t, 2.0);
+        assert!(Bvh::<Geometry>::new(vec![])
+            .hit(Ray::new(Vector3::ZERO, Vector3::UNIT_Z), 0.0..f32::INFINITY)
+            .is_none());
     }
 }
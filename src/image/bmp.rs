@@ -0,0 +1,128 @@
+//! uncompressed windows bitmaps
+//!
+//! https://learn.microsoft.com/en-us/windows/win32/gdi/bitmap-storage
+use super::{color_from_bytes, to_u8, Error, Image};
+use crate::color::Color;
+
+const FILE_HEADER: usize = 14;
+const INFO_HEADER: usize = 40;
+
+/// 24 bit BGR, rows from the bottom, alpha is dropped
+pub fn encode(image: &Image<Color>) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    // rows are padded to 4 bytes
+    let stride = (width * 3).div_ceil(4) * 4;
+    let offset = FILE_HEADER + INFO_HEADER;
+    let size = offset + stride * height;
+    let mut output = Vec::with_capacity(size);
+    output.extend_from_slice(b"BM");
+    output.extend_from_slice(&(size as u32).to_le_bytes());
+    output.extend_from_slice(&[0; 4]);
+    output.extend_from_slice(&(offset as u32).to_le_bytes());
+    output.extend_from_slice(&(INFO_HEADER as u32).to_le_bytes());
+    output.extend_from_slice(&(width as i32).to_le_bytes());
+    output.extend_from_slice(&(height as i32).to_le_bytes());
+    // planes, bits per pixel
+    output.extend_from_slice(&1u16.to_le_bytes());
+    output.extend_from_slice(&24u16.to_le_bytes());
+    // BI_RGB, image size, 2835 pixels per meter is 72 dpi, palette sizes
+    output.extend_from_slice(&0u32.to_le_bytes());
+    output.extend_from_slice(&((stride * height) as u32).to_le_bytes());
+    output.extend_from_slice(&2835u32.to_le_bytes());
+    output.extend_from_slice(&2835u32.to_le_bytes());
+    output.extend_from_slice(&[0; 8]);
+    for row in image.rows().rev() {
+        for c in row {
+            output.extend_from_slice(&[to_u8(c.b), to_u8(c.g), to_u8(c.r)]);
+        }
+        output.resize(output.len() + stride - width * 3, 0);
+    }
+    output
+}
+
+/// 24 or 32 bit BI_RGB, stored from the bottom or, with a negative height, from the top.
+/// palettes and compression are unsupported, alpha is 1 unless there are 32 bits
+pub fn decode(data: &[u8]) -> Result<Image<Color>, Error> {
+    if data.len() < FILE_HEADER + INFO_HEADER || &data[..2] != b"BM" {
+        return Err(Error::InvalidHeader);
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    let offset = u32_at(10) as usize;
+    if u32_at(14) < INFO_HEADER as u32 {
+        // the old OS/2 header
+        return Err(Error::Unsupported);
+    }
+    let width = u32_at(18) as i32;
+    let height = u32_at(22) as i32;
+    let bits = u16_at(28);
+    if width < 0 {
+        return Err(Error::InvalidHeader);
+    }
+    if u32_at(30) != 0 || !(bits == 24 || bits == 32) {
+        return Err(Error::Unsupported);
+    }
+    let (width, top_down) = (width as usize, height < 0);
+    let height = height.unsigned_abs() as usize;
+    let bytes = bits as usize / 8;
+    let stride = (width * bytes).div_ceil(4) * 4;
+    let pixels = data.get(offset..).ok_or(Error::UnexpectedEnd)?;
+    if pixels.len() < stride * height {
+        return Err(Error::UnexpectedEnd);
+    }
+    let mut image = Image::new(width, height, Color::BLACK);
+    for (y, row) in pixels.chunks(stride.max(1)).take(height).enumerate() {
+        let y = if top_down { y } else { height - 1 - y };
+        for (x, p) in row.chunks_exact(bytes).take(width).enumerate() {
+            let a = if bytes == 4 { p[3] } else { 255 };
+            image.put_pixel(x, y, color_from_bytes(p[2], p[1], p[0], a));
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::image::test::{assert_same, gradient};
+
+    #[test]
+    fn round_trip() {
+        // 5 pixels need a byte of padding per row
+        let image = gradient(5, 3);
+        let encoded = encode(&image);
+        assert_eq!(encoded.len(), 54 + 16 * 3);
+        assert_eq!(u32::from_le_bytes(encoded[2..6].try_into().unwrap()), 102);
+        assert_same(&image, &decode(&encoded).unwrap(), false);
+        let empty = Image::new(0, 0, Color::BLACK);
+        assert_eq!(decode(&encode(&empty)).unwrap().pixels().len(), 0);
+    }
+    #[test]
+    fn top_down_32_bits() {
+        let mut data = encode(&Image::new(1, 2, Color::BLACK));
+        data.truncate(54);
+        data[18..22].copy_from_slice(&1i32.to_le_bytes());
+        data[22..26].copy_from_slice(&(-2i32).to_le_bytes());
+        data[28..30].copy_from_slice(&32u16.to_le_bytes());
+        // blue then red, both half transparent
+        data.extend_from_slice(&[255, 0, 0, 128, 0, 0, 255, 128]);
+        let image = decode(&data).unwrap();
+        let (top, bottom) = (image.get_pixel(0, 0), image.get_pixel(0, 1));
+        assert_eq!((top.r, top.b), (0.0, 1.0));
+        assert_eq!((bottom.r, bottom.b), (1.0, 0.0));
+        assert_eq!(top.a, 128.0 / 255.0);
+    }
+    #[test]
+    fn errors() {
+        let data = encode(&gradient(2, 2));
+        assert_eq!(decode(&data[..60]).err(), Some(Error::UnexpectedEnd));
+        assert_eq!(decode(&data[..20]).err(), Some(Error::InvalidHeader));
+        assert_eq!(decode(b"PF").err(), Some(Error::InvalidHeader));
+        let mut palette = data.clone();
+        palette[28] = 8;
+        assert_eq!(decode(&palette).err(), Some(Error::Unsupported));
+        let mut rle = data;
+        rle[30] = 1;
+        assert_eq!(decode(&rle).err(), Some(Error::Unsupported));
+    }
+}
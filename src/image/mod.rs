@@ -0,0 +1,286 @@
+//! pixel buffers and simple dependency-free image formats
+use std::ops::{Add, Mul};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, interpolate::lerp::lerp};
+
+pub mod bmp;
+pub mod pfm;
+pub mod ppm;
+pub mod tga;
+
+/// a 2D buffer of pixels, rows from the top
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image<T> {
+    width: usize,
+    height: usize,
+    pixels: Vec<T>,
+}
+impl<T: Clone> Image<T> {
+    /// every pixel set to `value`
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![value; width * height],
+        }
+    }
+    /// a copy of the `width` x `height` part from `(x, y)`, None if it's not all inside
+    pub fn sub_image(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Image<T>> {
+        if x + width > self.width || y + height > self.height {
+            return None;
+        }
+        let pixels = self
+            .rows()
+            .skip(y)
+            .take(height)
+            .flat_map(|row| row[x..x + width].iter().cloned())
+            .collect();
+        Image::from_pixels(width, height, pixels)
+    }
+}
+impl<T> Image<T> {
+    /// None if there are not `width * height` pixels
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<T>) -> Option<Self> {
+        (pixels.len() == width * height).then_some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// all the pixels, row after row
+    pub fn pixels(&self) -> &[T] {
+        &self.pixels
+    }
+    pub fn into_pixels(self) -> Vec<T> {
+        self.pixels
+    }
+    /// panics out of the image
+    pub fn get_pixel(&self, x: usize, y: usize) -> &T {
+        assert!(x < self.width && y < self.height, "pixel out of the image");
+        &self.pixels[y * self.width + x]
+    }
+    /// panics out of the image
+    pub fn put_pixel(&mut self, x: usize, y: usize, value: T) {
+        assert!(x < self.width && y < self.height, "pixel out of the image");
+        self.pixels[y * self.width + x] = value;
+    }
+    /// rows from the top
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> {
+        // chunks panics for 0, an image without columns has no pixel anyway
+        self.pixels.chunks(self.width.max(1))
+    }
+    pub fn rows_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut [T]> {
+        self.pixels.chunks_mut(self.width.max(1))
+    }
+    /// the same image with every pixel converted
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Image<U> {
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(f).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+impl<T> Image<T>
+where
+    T: Copy + Add<T, Output = T> + Mul<f32, Output = T>,
+{
+    /// interpolated between the 4 closest pixel centers, `(0.5, 0.5)` being the center of the
+    /// top left pixel. coordinates outside are clamped to the edge, panics on an empty image.
+    pub fn bilinear(&self, x: f32, y: f32) -> T {
+        let x = (x - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let y = (y - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let top = lerp(*self.get_pixel(x0, y0), *self.get_pixel(x1, y0), tx);
+        let bottom = lerp(*self.get_pixel(x0, y1), *self.get_pixel(x1, y1), tx);
+        lerp(top, bottom, ty)
+    }
+    /// scaled to `width` x `height`, pixel centers are mapped onto each other
+    pub fn resize(&self, width: usize, height: usize, filter: Filter) -> Image<T> {
+        let sx = self.width as f32 / width as f32;
+        let sy = self.height as f32 / height as f32;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (fx, fy) = ((x as f32 + 0.5) * sx, (y as f32 + 0.5) * sy);
+                pixels.push(match filter {
+                    Filter::Nearest => *self.get_pixel(
+                        (fx as usize).min(self.width - 1),
+                        (fy as usize).min(self.height - 1),
+                    ),
+                    Filter::Bilinear => self.bilinear(fx, fy),
+                });
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// why an image file couldn't be read
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// not the format, or a header field that can't be parsed
+    InvalidHeader,
+    /// a valid file using something not implemented, like compression
+    Unsupported,
+    /// the file ends before all the pixels
+    UnexpectedEnd,
+}
+
+/// the first `count` whitespace separated fields of a netpbm-like header, skipping `#`
+/// comments, and where the data starts after the single whitespace ending the header
+fn header_fields(data: &[u8], count: usize) -> Result<(Vec<&str>, usize), Error> {
+    let mut fields = Vec::with_capacity(count);
+    let mut i = 0;
+    while fields.len() < count {
+        match data.get(i) {
+            None => return Err(Error::InvalidHeader),
+            Some(b'#') => {
+                while data.get(i).is_some_and(|c| *c != b'\n') {
+                    i += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => i += 1,
+            Some(_) => {
+                let start = i;
+                while data.get(i).is_some_and(|c| !c.is_ascii_whitespace()) {
+                    i += 1;
+                }
+                let field =
+                    std::str::from_utf8(&data[start..i]).map_err(|_| Error::InvalidHeader)?;
+                fields.push(field);
+            }
+        }
+    }
+    if !data.get(i).is_some_and(|c| c.is_ascii_whitespace()) {
+        return Err(Error::InvalidHeader);
+    }
+    Ok((fields, i + 1))
+}
+fn parse<T: std::str::FromStr>(field: &str) -> Result<T, Error> {
+    field.parse().map_err(|_| Error::InvalidHeader)
+}
+/// `width * height * per_pixel`, the size in the header being invalid if it overflows
+fn sample_count(width: usize, height: usize, per_pixel: usize) -> Result<usize, Error> {
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(per_pixel))
+        .ok_or(Error::InvalidHeader)
+}
+
+/// from [0, 1] to [0, 255], clamped and rounded
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+fn from_u8(value: u8) -> f32 {
+    value as f32 / 255.0
+}
+fn color_from_bytes(r: u8, g: u8, b: u8, a: u8) -> Color {
+    Color {
+        r: from_u8(r),
+        g: from_u8(g),
+        b: from_u8(b),
+        a: from_u8(a),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::rgba;
+
+    /// a small image using all the 8 bit channel values
+    pub(crate) fn gradient(width: usize, height: usize) -> Image<Color> {
+        let mut image = Image::new(width, height, Color::BLACK);
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y * width + x) as u8;
+                image.put_pixel(x, y, color_from_bytes(i, 255 - i, i.wrapping_mul(7), 255));
+            }
+        }
+        image
+    }
+    pub(crate) fn assert_same(a: &Image<Color>, b: &Image<Color>, alpha: bool) {
+        assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+        for (p, q) in a.pixels().iter().zip(b.pixels()) {
+            assert_eq!((p.r, p.g, p.b), (q.r, q.g, q.b), "{p:?} != {q:?}");
+            if alpha {
+                assert_eq!(p.a, q.a);
+            }
+        }
+    }
+
+    #[test]
+    fn pixels() {
+        let mut image = Image::new(3, 2, 0u8);
+        image.put_pixel(2, 1, 7);
+        assert_eq!(*image.get_pixel(2, 1), 7);
+        assert_eq!(image.pixels(), &[0, 0, 0, 0, 0, 7]);
+        assert!(Image::from_pixels(2, 2, vec![0; 3]).is_none());
+        let rows: Vec<&[u8]> = image.rows().collect();
+        assert_eq!(rows, [&[0, 0, 0], &[0, 0, 7]]);
+        for row in image.rows_mut() {
+            row[0] = 1;
+        }
+        assert_eq!(
+            image.map(|p| *p as u32 * 2).into_pixels(),
+            [2, 0, 0, 2, 0, 14]
+        );
+        assert_eq!(Image::new(0, 3, 0u8).rows().count(), 0);
+    }
+    #[test]
+    fn sub_image() {
+        let image = Image::from_pixels(4, 3, (0..12).collect()).unwrap();
+        let sub = image.sub_image(1, 1, 2, 2).unwrap();
+        assert_eq!(sub.pixels(), &[5, 6, 9, 10]);
+        assert_eq!(image.sub_image(0, 0, 4, 3).unwrap(), image);
+        assert!(image.sub_image(3, 0, 2, 1).is_none());
+        assert!(image.sub_image(0, 2, 1, 2).is_none());
+    }
+    #[test]
+    fn resize() {
+        let image = Image::from_pixels(2, 2, vec![0.0, 1.0, 2.0, 3.0]).unwrap();
+        let nearest = image.resize(4, 4, Filter::Nearest);
+        assert_eq!(nearest.rows().next().unwrap(), &[0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(nearest.rows().last().unwrap(), &[2.0, 2.0, 3.0, 3.0]);
+        let bilinear = image.resize(4, 4, Filter::Bilinear);
+        assert_eq!(bilinear.rows().next().unwrap(), &[0.0, 0.25, 0.75, 1.0]);
+        assert_eq!(*bilinear.get_pixel(1, 1), 0.75);
+        // down to a single pixel, the center of the image
+        assert_eq!(*image.resize(1, 1, Filter::Bilinear).get_pixel(0, 0), 1.5);
+        assert_eq!(image.resize(2, 2, Filter::Bilinear), image);
+        // colors interpolate too
+        let colors =
+            Image::from_pixels(2, 1, vec![Color::BLACK, rgba(1.0, 0.5, 0.0, 1.0)]).unwrap();
+        let mid = colors.bilinear(1.0, 0.5);
+        assert_eq!((mid.r, mid.g, mid.a), (0.5, 0.25, 1.0));
+    }
+    #[test]
+    fn byte_conversion() {
+        for i in 0..=255 {
+            assert_eq!(to_u8(from_u8(i)), i);
+        }
+        assert_eq!((to_u8(-1.0), to_u8(2.0)), (0, 255));
+    }
+}
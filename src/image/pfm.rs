@@ -0,0 +1,108 @@
+//! portable float maps, uncompressed HDR images with a 32 bit float per channel
+//!
+//! rows are stored from the bottom. https://www.pauldebevec.com/Research/HDR/PFM/
+use super::{header_fields, parse, sample_count, Error, Image};
+use crate::color::Color;
+
+/// color, little endian, alpha is dropped
+pub fn encode(image: &Image<Color>) -> Vec<u8> {
+    // a negative scale means little endian
+    let mut output = format!("PF\n{} {}\n-1.0\n", image.width(), image.height()).into_bytes();
+    for row in image.rows().rev() {
+        for c in row {
+            for v in [c.r, c.g, c.b] {
+                output.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+    }
+    output
+}
+
+/// color `PF` or grayscale `Pf`, in either byte order. values are multiplied by the
+/// absolute scale, alpha is 1
+pub fn decode(data: &[u8]) -> Result<Image<Color>, Error> {
+    let (fields, start) = header_fields(data, 4)?;
+    let channels = match fields[0] {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(Error::InvalidHeader),
+    };
+    let (width, height): (usize, usize) = (parse(fields[1])?, parse(fields[2])?);
+    let scale: f32 = parse(fields[3])?;
+    if scale == 0.0 || !scale.is_finite() {
+        return Err(Error::InvalidHeader);
+    }
+    let count = sample_count(width, height, channels)?;
+    let values: Vec<f32> = data[start..]
+        .chunks_exact(4)
+        .take(count)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            let v = if scale < 0.0 {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            };
+            v * scale.abs()
+        })
+        .collect();
+    if values.len() < count {
+        return Err(Error::UnexpectedEnd);
+    }
+    let mut pixels: Vec<Color> = values
+        .chunks_exact(channels)
+        .map(|v| Color {
+            r: v[0],
+            g: v[channels / 2],
+            b: v[channels - 1],
+            a: 1.0,
+        })
+        .collect();
+    // from the bottom to the top
+    let mut image = Image::new(width, height, Color::BLACK);
+    for (row, stored) in image.rows_mut().zip(pixels.chunks_mut(width.max(1)).rev()) {
+        row.copy_from_slice(stored);
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::rgba, image::test::assert_same};
+
+    #[test]
+    fn round_trip() {
+        // values out of [0, 1] are kept
+        let mut image = Image::new(3, 2, Color::BLACK);
+        image.put_pixel(0, 0, rgba(12.5, -1.0, 0.25, 1.0));
+        image.put_pixel(2, 1, rgba(1e-6, 1e6, 3.0, 1.0));
+        let encoded = encode(&image);
+        assert!(encoded.starts_with(b"PF\n3 2\n-1.0\n"));
+        assert_eq!(encoded.len(), 12 + 3 * 2 * 12);
+        // the bottom row first
+        assert_eq!(
+            &encoded[12 + 2 * 12..12 + 2 * 12 + 4],
+            &1e-6_f32.to_le_bytes()
+        );
+        assert_same(&image, &decode(&encoded).unwrap(), true);
+    }
+    #[test]
+    fn big_endian_grayscale() {
+        let mut data = b"Pf\n1 2\n2.0\n".to_vec();
+        data.extend_from_slice(&0.5_f32.to_be_bytes());
+        data.extend_from_slice(&4.0_f32.to_be_bytes());
+        let image = decode(&data).unwrap();
+        let top = image.get_pixel(0, 0);
+        assert_eq!((top.r, top.g, top.b), (8.0, 8.0, 8.0));
+        assert_eq!(image.get_pixel(0, 1).g, 1.0);
+        assert_eq!(
+            decode(&data[..data.len() - 1]).err(),
+            Some(Error::UnexpectedEnd)
+        );
+        assert_eq!(decode(b"PF\n1 1\n0.0\n").err(), Some(Error::InvalidHeader));
+        assert_eq!(decode(b"P6\n1 1\n1.0\n").err(), Some(Error::InvalidHeader));
+        let huge = format!("Pf\n{} 4\n1.0\n\0\0\0\0", usize::MAX / 2);
+        assert_eq!(decode(huge.as_bytes()).err(), Some(Error::InvalidHeader));
+    }
+}
@@ -0,0 +1,145 @@
+//! netpbm color images, the plain text P3 and the binary P6
+//!
+//! https://netpbm.sourceforge.net/doc/ppm.html
+use super::{from_u8, header_fields, parse, sample_count, to_u8, Error, Image};
+use crate::color::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// P3, readable numbers
+    Ascii,
+    /// P6, a byte per channel
+    Binary,
+}
+
+/// 8 bits per channel, alpha is dropped
+pub fn encode(image: &Image<Color>, encoding: Encoding) -> Vec<u8> {
+    let magic = match encoding {
+        Encoding::Ascii => "P3",
+        Encoding::Binary => "P6",
+    };
+    let mut output = format!("{magic}\n{} {}\n255\n", image.width(), image.height()).into_bytes();
+    for row in image.rows() {
+        let bytes = row
+            .iter()
+            .flat_map(|c| [to_u8(c.r), to_u8(c.g), to_u8(c.b)]);
+        match encoding {
+            Encoding::Ascii => {
+                let line: Vec<String> = bytes.map(|b| b.to_string()).collect();
+                output.extend_from_slice(line.join(" ").as_bytes());
+                output.push(b'\n');
+            }
+            Encoding::Binary => output.extend(bytes),
+        }
+    }
+    output
+}
+
+/// P3 or P6 with any max value up to 65535, alpha is 1
+pub fn decode(data: &[u8]) -> Result<Image<Color>, Error> {
+    let (fields, start) = header_fields(data, 4)?;
+    let (width, height): (usize, usize) = (parse(fields[1])?, parse(fields[2])?);
+    let max: u16 = parse(fields[3])?;
+    if max == 0 {
+        return Err(Error::InvalidHeader);
+    }
+    let count = sample_count(width, height, 3)?;
+    let samples: Vec<u16> = match fields[0] {
+        "P3" => {
+            let text = std::str::from_utf8(&data[start..]).map_err(|_| Error::InvalidHeader)?;
+            text.split_ascii_whitespace()
+                .take(count)
+                .map(parse)
+                .collect::<Result<_, _>>()?
+        }
+        "P6" if max < 256 => data[start..]
+            .iter()
+            .take(count)
+            .map(|b| *b as u16)
+            .collect(),
+        // 2 bytes per sample, most significant first
+        "P6" => data[start..]
+            .chunks_exact(2)
+            .take(count)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect(),
+        _ => return Err(Error::InvalidHeader),
+    };
+    if samples.len() < count {
+        return Err(Error::UnexpectedEnd);
+    }
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|s| {
+            let channel = |v: u16| {
+                if max == 255 {
+                    from_u8(v as u8)
+                } else {
+                    v as f32 / max as f32
+                }
+            };
+            Color {
+                r: channel(s[0]),
+                g: channel(s[1]),
+                b: channel(s[2]),
+                a: 1.0,
+            }
+        })
+        .collect();
+    Image::from_pixels(width, height, pixels).ok_or(Error::InvalidHeader)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::image::test::{assert_same, gradient};
+
+    #[test]
+    fn round_trip() {
+        let image = gradient(5, 3);
+        for encoding in [Encoding::Ascii, Encoding::Binary] {
+            let decoded = decode(&encode(&image, encoding)).unwrap();
+            assert_same(&image, &decoded, false);
+        }
+        let binary = encode(&image, Encoding::Binary);
+        assert!(binary.starts_with(b"P6\n5 3\n255\n"));
+        assert_eq!(binary.len(), 11 + 5 * 3 * 3);
+    }
+    #[test]
+    fn hand_written() {
+        let plain = b"P3\n# a comment\n2 1 # trailing\n15\n15 0 0\n 0 15 15\n";
+        let image = decode(plain).unwrap();
+        assert_eq!(image.get_pixel(0, 0).r, 1.0);
+        assert_eq!(image.get_pixel(1, 0).r, 0.0);
+        assert_eq!(image.get_pixel(1, 0).b, 1.0);
+        // 16 bits
+        let wide = b"P6 1 1 65535\n\xff\xff\x80\x00\x00\x00";
+        let image = decode(wide).unwrap();
+        let pixel = image.get_pixel(0, 0);
+        assert_eq!((pixel.r, pixel.b), (1.0, 0.0));
+        assert!((pixel.g - 0.5).abs() < 1e-4);
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(
+            decode(b"P5\n1 1\n255\n\0").err(),
+            Some(Error::InvalidHeader)
+        );
+        assert_eq!(decode(b"P6\n1 1\n").err(), Some(Error::InvalidHeader));
+        assert_eq!(
+            decode(b"P6\n2 1\n255\n\0\0\0").err(),
+            Some(Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            decode(b"P3\n1 x\n255\n0 0 0").err(),
+            Some(Error::InvalidHeader)
+        );
+        assert_eq!(
+            decode(b"P3\n1 1\n255\n0 0 z").err(),
+            Some(Error::InvalidHeader)
+        );
+        // a size that doesn't fit in memory
+        let huge = format!("P6\n{} 2\n255\n\0\0\0", usize::MAX / 2);
+        assert_eq!(decode(huge.as_bytes()).err(), Some(Error::InvalidHeader));
+    }
+}
@@ -0,0 +1,120 @@
+//! uncompressed truevision TGA images
+//!
+//! http://www.paulbourke.net/dataformats/tga/
+use super::{color_from_bytes, sample_count, to_u8, Error, Image};
+use crate::color::Color;
+
+const HEADER: usize = 18;
+/// the descriptor bit set when rows are stored from the top
+const TOP_LEFT: u8 = 0x20;
+
+/// 32 bit BGRA, rows from the top. panics if a side is larger than 65535, the most tga can store
+pub fn encode(image: &Image<Color>) -> Vec<u8> {
+    assert!(
+        image.width() <= u16::MAX as usize && image.height() <= u16::MAX as usize,
+        "{}x{} is too large for tga",
+        image.width(),
+        image.height()
+    );
+    let mut output = vec![0; HEADER];
+    // no id nor color map, uncompressed true color
+    output[2] = 2;
+    output[12..14].copy_from_slice(&(image.width() as u16).to_le_bytes());
+    output[14..16].copy_from_slice(&(image.height() as u16).to_le_bytes());
+    output[16] = 32;
+    // 8 alpha bits
+    output[17] = TOP_LEFT | 8;
+    for c in image.pixels() {
+        output.extend_from_slice(&[to_u8(c.b), to_u8(c.g), to_u8(c.r), to_u8(c.a)]);
+    }
+    output
+}
+
+/// true color (type 2) at 24 or 32 bits and grayscale (type 3) at 8 bits, from the top or the
+/// bottom. color maps and run length encoding are unsupported
+pub fn decode(data: &[u8]) -> Result<Image<Color>, Error> {
+    if data.len() < HEADER {
+        return Err(Error::InvalidHeader);
+    }
+    let (id_length, color_map, kind) = (data[0] as usize, data[1], data[2]);
+    let width = u16::from_le_bytes([data[12], data[13]]) as usize;
+    let height = u16::from_le_bytes([data[14], data[15]]) as usize;
+    let (bits, descriptor) = (data[16], data[17]);
+    match (kind, bits) {
+        (2, 24 | 32) | (3, 8) if color_map == 0 => {}
+        (0..=3 | 9..=11, _) => return Err(Error::Unsupported),
+        _ => return Err(Error::InvalidHeader),
+    }
+    let bytes = bits as usize / 8;
+    let pixels = data.get(HEADER + id_length..).ok_or(Error::UnexpectedEnd)?;
+    if pixels.len() < sample_count(width, height, bytes)? {
+        return Err(Error::UnexpectedEnd);
+    }
+    let mut image = Image::new(width, height, Color::BLACK);
+    for (i, p) in pixels.chunks_exact(bytes).take(width * height).enumerate() {
+        let (x, y) = (i % width, i / width);
+        let y = if descriptor & TOP_LEFT != 0 {
+            y
+        } else {
+            height - 1 - y
+        };
+        let color = match bytes {
+            1 => color_from_bytes(p[0], p[0], p[0], 255),
+            3 => color_from_bytes(p[2], p[1], p[0], 255),
+            _ => color_from_bytes(p[2], p[1], p[0], p[3]),
+        };
+        image.put_pixel(x, y, color);
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::image::test::{assert_same, gradient};
+
+    #[test]
+    fn round_trip() {
+        let mut image = gradient(4, 3);
+        image.put_pixel(1, 2, color_from_bytes(255, 0, 51, 102));
+        let encoded = encode(&image);
+        assert_eq!(encoded.len(), 18 + 4 * 3 * 4);
+        assert_same(&image, &decode(&encoded).unwrap(), true);
+    }
+    #[test]
+    fn bottom_left_grayscale_and_24_bits() {
+        // an id to skip, then 2 rows of a pixel from the bottom
+        let mut data = vec![3, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 8, 0];
+        data.extend_from_slice(b"id!");
+        data.extend_from_slice(&[255, 51]);
+        let image = decode(&data).unwrap();
+        assert_eq!(image.get_pixel(0, 0).g, 0.2);
+        assert_eq!(image.get_pixel(0, 1).b, 1.0);
+        assert_eq!(image.get_pixel(0, 1).a, 1.0);
+        let data = [
+            0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 24, TOP_LEFT, 255, 0, 0,
+        ];
+        let pixel = *decode(&data).unwrap().get_pixel(0, 0);
+        assert_eq!((pixel.r, pixel.b, pixel.a), (0.0, 1.0, 1.0));
+    }
+    #[test]
+    fn errors() {
+        let data = encode(&gradient(2, 2));
+        assert_eq!(decode(&data[..20]).err(), Some(Error::UnexpectedEnd));
+        assert_eq!(decode(&data[..10]).err(), Some(Error::InvalidHeader));
+        let mut rle = data.clone();
+        rle[2] = 10;
+        assert_eq!(decode(&rle).err(), Some(Error::Unsupported));
+        let mut mapped = data.clone();
+        mapped[1] = 1;
+        assert_eq!(decode(&mapped).err(), Some(Error::Unsupported));
+        let mut unknown = data;
+        unknown[2] = 42;
+        assert_eq!(decode(&unknown).err(), Some(Error::InvalidHeader));
+    }
+    #[test]
+    #[should_panic]
+    fn too_large() {
+        encode(&Image::new(70000, 1, Color::BLACK));
+    }
+}
@@ -0,0 +1,312 @@
+//! one object placed many times in a scene, each placement with its own transform
+use std::{ops::Range, sync::Arc};
+
+use rand::RngCore;
+
+use crate::{
+    aabb::Aabb,
+    linear_algebra::{vector::vec3, Matrix4, Transform, Vector3},
+    motion::Keyframes,
+    ray::{HitRecord, Hitable, Ray},
+};
+
+/// a shared object under an affine transform.
+///
+/// the object is hit in its own space, so a mesh and its bvh are built once for all instances.
+pub struct Instance<T: ?Sized> {
+    object: Arc<T>,
+    transform: Matrix4,
+    inverse: Matrix4,
+}
+impl<T: Hitable + ?Sized> Instance<T> {
+    /// None if the transform can't be inverted
+    pub fn new(object: Arc<T>, transform: Matrix4) -> Option<Self> {
+        Some(Self {
+            object,
+            inverse: transform.inverse()?,
+            transform,
+        })
+    }
+    pub fn object(&self) -> &Arc<T> {
+        &self.object
+    }
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+}
+impl<T: Hitable + ?Sized> Hitable for Instance<T> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        // the direction isn't normalized so t is the same in both spaces
+        let local = Ray {
+            origin: self.inverse.transform_point(ray.origin),
+            direction: self.inverse.transform_vector(ray.direction),
+            ..ray
+        };
+        let mut record = self.object.hit(local, range)?;
+        record.point = self.transform.transform_point(record.point);
+        // the normal still faces the ray, front_face stays valid
+        record.normal = self.inverse.transform_normal(record.normal);
+        Some(record)
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box().transform(&self.transform)
+    }
+    fn can_sample(&self) -> bool {
+        self.object.can_sample()
+    }
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        // the transform stretches solid angles by |det| / |M d|³ around a local unit direction d
+        let local = self.inverse.transform_vector(direction).normalize();
+        let stretch = self.transform.transform_vector(local).length();
+        let pdf = self
+            .object
+            .pdf_value(self.inverse.transform_point(origin), local);
+        pdf * stretch.powi(3) / self.transform.determinant().abs()
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        let local = self
+            .object
+            .random_direction(self.inverse.transform_point(origin), rng);
+        self.transform.transform_vector(local)
+    }
+}
+
+/// a shared object moved by keyframed transforms, for motion blur
+pub struct MovingInstance<T: ?Sized> {
+    object: Arc<T>,
+    transform: Keyframes<Transform>,
+}
+impl<T: Hitable + ?Sized> MovingInstance<T> {
+    pub fn new(object: Arc<T>, transform: Keyframes<Transform>) -> Self {
+        Self { object, transform }
+    }
+    pub fn object(&self) -> &Arc<T> {
+        &self.object
+    }
+}
+impl<T: Hitable + ?Sized> Hitable for MovingInstance<T> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        // a rotation around Y and a translation: rigid, so it's undone by rotating back and the
+        // normals turn like any other vector
+        let transform = self.transform.at(ray.time);
+        let (sin, cos) = transform.rotation.sin_cos();
+        let local = Ray {
+            origin: rotate_y(ray.origin - transform.translation, -sin, cos),
+            direction: rotate_y(ray.direction, -sin, cos),
+            ..ray
+        };
+        let mut record = self.object.hit(local, range)?;
+        record.point = rotate_y(record.point, sin, cos) + transform.translation;
+        record.normal = rotate_y(record.normal, sin, cos);
+        Some(record)
+    }
+    /// union over the whole motion
+    fn bounding_box(&self) -> Aabb {
+        let local = self.object.bounding_box();
+        let frames = self.transform.frames();
+        let mut aabb = Aabb::empty();
+        for (_, transform) in frames {
+            aabb = aabb.union(&local.transform(&transform.matrix()));
+        }
+        // translations are linear between the frames but rotations sweep in between, bound
+        // them by the circle the farthest corner goes through
+        let radius = (0..4)
+            .map(|i| {
+                let x = if i & 1 == 0 { local.min.x } else { local.max.x };
+                let z = if i & 2 == 0 { local.min.z } else { local.max.z };
+                (x * x + z * z).sqrt()
+            })
+            .fold(0.0, f32::max);
+        for pair in frames.windows(2) {
+            let ((_, a), (_, b)) = (pair[0], pair[1]);
+            if a.rotation != b.rotation {
+                for t in [a.translation, b.translation] {
+                    aabb.expand_by_point(t + vec3(-radius, local.min.y, -radius));
+                    aabb.expand_by_point(t + vec3(radius, local.max.y, radius));
+                }
+            }
+        }
+        aabb
+    }
+}
+
+/// `v` rotated around the Y axis like `Matrix4::rotation_y` does, given the angle's sine and cosine
+fn rotate_y(v: Vector3, sin: f32, cos: f32) -> Vector3 {
+    vec3(cos * v.x + sin * v.z, v.y, cos * v.z - sin * v.x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::{Bvh, Sphere},
+        linear_algebra::Vector3,
+        model_3d::primitives,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-4, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn sampling() {
+        // an ellipsoid, its density is stretched along with the solid angles
+        let sphere = Arc::new(Sphere::new(Vector3::ZERO, 1.0));
+        let m = Matrix4::translation(vec3(0.0, 0.0, -3.0))
+            * Matrix4::rotation_y(0.3)
+            * Matrix4::scale(vec3(2.0, 1.0, 0.5));
+        let instance = Instance::new(sphere, m).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let origin = vec3(0.5, 0.2, 0.0);
+        for _ in 0..1000 {
+            let direction = instance.random_direction(origin, &mut rng);
+            let ray = Ray::new(origin, direction);
+            assert!(instance.hit(ray, 1e-3..f32::INFINITY).is_some());
+            assert!(instance.pdf_value(origin, direction) > 0.0);
+        }
+        let n = 100_000;
+        let integral: f32 = (0..n)
+            .map(|_| instance.pdf_value(origin, crate::sampling::unit_sphere(&mut rng)))
+            .sum::<f32>()
+            * 4.0
+            * PI
+            / n as f32;
+        assert!((integral - 1.0).abs() < 0.05, "{integral}");
+    }
+    #[test]
+    fn transformed_sphere() {
+        let sphere = Arc::new(Sphere::new(Vector3::ZERO, 1.0));
+        let m = Matrix4::translation(vec3(0.0, 0.0, -5.0)) * Matrix4::scale(vec3(2.0, 1.0, 1.0));
+        let instance = Instance::new(sphere, m).unwrap();
+        let ray = Ray::new(vec3(-10.0, 0.0, -5.0), Vector3::UNIT_X);
+        let rec = instance.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 8.0).abs() < 1e-5);
+        assert_close(rec.point, vec3(-2.0, 0.0, -5.0));
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!(rec.front_face);
+        // the normal of the ellipsoid x²/4 + y² + z² = 1 is along (x/4, y, z)
+        let ray = Ray::new(vec3(1.0, 0.5, 5.0), -Vector3::UNIT_Z);
+        let rec = instance.hit(ray, 0.0..f32::INFINITY).unwrap();
+        let z = 0.5_f32.sqrt();
+        assert_close(rec.point, vec3(1.0, 0.5, z - 5.0));
+        assert_close(rec.normal, vec3(0.25, 0.5, z).normalize());
+        let aabb = instance.bounding_box();
+        assert_close(aabb.min, vec3(-2.0, -1.0, -6.0));
+        assert_close(aabb.max, vec3(2.0, 1.0, -4.0));
+        assert!(Instance::new(instance.object().clone(), Matrix4::scale(Vector3::ZERO)).is_none());
+    }
+    #[test]
+    fn rotated_bounds() {
+        let sphere = Arc::new(Sphere::new(vec3(1.0, 0.0, 0.0), 0.5));
+        let instance = Instance::new(sphere, Matrix4::rotation_z(FRAC_PI_2)).unwrap();
+        let aabb = instance.bounding_box();
+        assert_close(aabb.min, vec3(-0.5, 0.5, -0.5));
+        assert_close(aabb.max, vec3(0.5, 1.5, 0.5));
+    }
+    #[test]
+    fn shared_mesh() {
+        let mesh: Arc<Bvh<_>> = Arc::new(primitives::icosphere(1.0, 2).bvh());
+        let instances: Vec<Instance<dyn Hitable + Send + Sync>> = (0..100)
+            .map(|i| {
+                let (x, z) = ((i % 10) as f32 * 3.0, (i / 10) as f32 * 3.0);
+                let m = Matrix4::translation(vec3(x, 0.0, z)) * Matrix4::rotation_y(i as f32);
+                let object: Arc<dyn Hitable + Send + Sync> = mesh.clone();
+                Instance::new(object, m).unwrap()
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&mesh), 101);
+        let scene = Bvh::new(instances);
+        // the rotated bounds of each instance are a bit larger than the sphere
+        let aabb = scene.bounding_box();
+        assert!(aabb.min.x <= -1.0 && aabb.max.z >= 28.0);
+        assert_eq!((aabb.min.y, aabb.max.y), (-1.0, 1.0));
+        // the closest instance along the ray is the one at x = 9
+        let ray = Ray::new(vec3(9.0, 5.0, 6.0), -Vector3::UNIT_Y);
+        let rec = scene.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 0.05, "{}", rec.t);
+        assert!(rec.front_face);
+        let ray = Ray::new(vec3(-5.0, 0.0, 6.0), Vector3::UNIT_X);
+        let rec = scene.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((rec.point.x + 1.0).abs() < 0.05, "{:?}", rec.point);
+        let miss = Ray::new(vec3(1.5, 5.0, 1.5), -Vector3::UNIT_Y);
+        assert!(scene.hit(miss, 0.0..f32::INFINITY).is_none());
+    }
+    #[test]
+    fn same_as_ray_transform() {
+        let sphere = Sphere::new(vec3(1.0, 0.0, 0.5), 0.5);
+        let transform = Transform {
+            rotation: 0.7,
+            translation: vec3(0.0, 0.0, -3.0),
+        };
+        let instance = Instance::new(Arc::new(sphere), transform.matrix()).unwrap();
+        for i in 0..20 {
+            let ray = Ray::new(vec3(i as f32 * 0.1 - 1.0, 0.1, 5.0), -Vector3::UNIT_Z);
+            let expected = ray.hit(&sphere, 0.0..f32::INFINITY, Some(transform));
+            let rec = instance.hit(ray, 0.0..f32::INFINITY);
+            assert_eq!(rec.is_some(), expected.is_some());
+            if let (Some(rec), Some(expected)) = (rec, expected) {
+                assert!((rec.t - expected.t).abs() < 1e-4);
+                assert_close(rec.point, expected.point);
+                assert_close(rec.normal, expected.normal);
+            }
+        }
+    }
+    #[test]
+    fn moving_instance() {
+        let object: Arc<Sphere> = Arc::new(Sphere::new(vec3(2.0, 0.0, 0.0), 0.5));
+        let at = |rotation: f32, x: f32| Transform {
+            rotation,
+            translation: vec3(x, 0.0, 0.0),
+        };
+        let moving = MovingInstance::new(
+            object.clone(),
+            Keyframes::linear(at(0.0, 0.0), at(PI, 1.0), 0.0..1.0),
+        );
+        // the sphere starts at x = 2 and ends at x = -1
+        let down = |x: f32| Ray::new(vec3(x, 5.0, 0.0), -Vector3::UNIT_Y);
+        assert!(moving
+            .hit(down(2.0).with_time(0.0), 0.0..f32::INFINITY)
+            .is_some());
+        assert!(moving
+            .hit(down(2.0).with_time(1.0), 0.0..f32::INFINITY)
+            .is_none());
+        let rec = moving
+            .hit(down(-1.0).with_time(1.0), 0.0..f32::INFINITY)
+            .unwrap();
+        assert_close(rec.point, vec3(-1.0, 0.5, 0.0));
+        // halfway: rotated a quarter turn to z = -2, moved by 0.5
+        let rec = moving.hit(
+            Ray::new(vec3(0.5, 5.0, -2.0), -Vector3::UNIT_Y).with_time(0.5),
+            0.0..f32::INFINITY,
+        );
+        assert!(rec.is_some());
+        // the bounds contain the object at any time
+        let aabb = moving.bounding_box();
+        for i in 0..=50 {
+            let transform = moving.transform.at(i as f32 / 50.0).matrix();
+            let bounds = Instance::new(object.clone(), transform)
+                .unwrap()
+                .bounding_box();
+            assert!(aabb.contains_point(bounds.min) && aabb.contains_point(bounds.max));
+        }
+        // the same hits as a still instance at that time
+        for i in 0..=10 {
+            let time = i as f32 / 10.0;
+            let transform = moving.transform.at(time).matrix();
+            let still = Instance::new(object.clone(), transform).unwrap();
+            let ray = Ray::new(vec3(-3.0, 0.2, 0.1), vec3(1.0, 0.0, -0.3 * time)).with_time(time);
+            let (a, b) = (
+                moving.hit(ray, 0.0..f32::INFINITY),
+                still.hit(ray, 0.0..f32::INFINITY),
+            );
+            assert_eq!(a.is_some(), b.is_some());
+            if let (Some(a), Some(b)) = (a, b) {
+                assert!((a.t - b.t).abs() < 1e-4);
+                assert_close(a.point, b.point);
+                assert_close(a.normal, b.normal);
+            }
+        }
+    }
+}
@@ -1,13 +1,29 @@
 pub mod color;
+pub mod csg;
 pub mod encoding;
 pub mod geometry;
+pub mod image;
 pub mod linear_algebra;
 pub use semantic_version::SemVer;
 pub mod aabb;
+pub mod bounding_sphere;
+pub mod camera;
 pub mod data_structures;
+pub mod instance;
 pub mod interpolate;
 pub mod linked_list;
+pub mod material;
+pub mod medium;
 pub mod model_3d;
+pub mod motion;
+pub mod obb;
+pub mod polynomial;
+pub mod query;
 pub mod radian;
 pub mod ray;
+pub mod render;
+pub mod sampling;
+pub mod scene;
+pub mod sdf;
 pub mod semantic_version;
+pub mod texture;
@@ -1,3 +1,9 @@
+use super::{
+    vector::{vec3, Vector4},
+    Vector3,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Matrix4 {
     /// the matrix elements are in column major order
     elements: [f32; 16],
@@ -11,29 +17,220 @@ impl Matrix4 {
             ],
         }
     }
+    /// from elements in column major order
+    pub fn from_cols_array(elements: [f32; 16]) -> Self {
+        Self { elements }
+    }
+    /// from rows, which reads like the matrix when written in code
+    pub fn from_rows(rows: [[f32; 4]; 4]) -> Self {
+        let mut m = Self::identity();
+        for (i, row) in rows.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                m.elements[j * 4 + i] = *value;
+            }
+        }
+        m
+    }
+    pub fn translation(offset: Vector3) -> Self {
+        Self::from_rows([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    pub fn scale(factor: Vector3) -> Self {
+        Self::from_rows([
+            [factor.x, 0.0, 0.0, 0.0],
+            [0.0, factor.y, 0.0, 0.0],
+            [0.0, 0.0, factor.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// counterclockwise rotation of `angle` radians around the normalized `axis`
+    pub fn rotation(axis: Vector3, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        let Vector3 { x, y, z } = axis;
+        Self::from_rows([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    pub fn rotation_x(angle: f32) -> Self {
+        Self::rotation(Vector3::UNIT_X, angle)
+    }
+    pub fn rotation_y(angle: f32) -> Self {
+        Self::rotation(Vector3::UNIT_Y, angle)
+    }
+    pub fn rotation_z(angle: f32) -> Self {
+        Self::rotation(Vector3::UNIT_Z, angle)
+    }
+    /// the element at row `i` and column `j`
+    pub fn get(&self, i: usize, j: usize) -> f32 {
+        self.elements[j * 4 + i]
+    }
+    pub fn transpose(&self) -> Self {
+        let mut m = *self;
+        for i in 0..4 {
+            for j in 0..4 {
+                m.elements[j * 4 + i] = self.get(j, i);
+            }
+        }
+        m
+    }
+    pub fn determinant(&self) -> f32 {
+        let inv = self.adjugate();
+        let e = &self.elements;
+        e[0] * inv[0] + e[1] * inv[4] + e[2] * inv[8] + e[3] * inv[12]
+    }
+    /// None if the matrix is singular
+    pub fn inverse(&self) -> Option<Self> {
+        let inv = self.adjugate();
+        let e = &self.elements;
+        let det = e[0] * inv[0] + e[1] * inv[4] + e[2] * inv[8] + e[3] * inv[12];
+        if det == 0.0 {
+            return None;
+        }
+        Some(Self {
+            elements: inv.map(|x| x / det),
+        })
+    }
+    /// transpose of the cofactor matrix, by expansion of the 2x2 minors
+    fn adjugate(&self) -> [f32; 16] {
+        let m = &self.elements;
+        let mut inv = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+        inv
+    }
+    /// apply to a position, translation included
+    pub fn transform_point(&self, p: Vector3) -> Vector3 {
+        let v = *self * Vector4::new(p.x, p.y, p.z, 1.0);
+        vec3(v.x, v.y, v.z) / v.w
+    }
+    /// apply to a direction, translation ignored
+    pub fn transform_vector(&self, d: Vector3) -> Vector3 {
+        let v = *self * Vector4::new(d.x, d.y, d.z, 0.0);
+        vec3(v.x, v.y, v.z)
+    }
+    /// apply to a surface normal, which needs the inverse transpose to stay perpendicular
+    /// under non-uniform scaling. `self` should be the inverse of the transform.
+    pub fn transform_normal(&self, n: Vector3) -> Vector3 {
+        self.transpose().transform_vector(n).normalize()
+    }
 }
 
 impl std::ops::Mul for Matrix4 {
     type Output = Self;
 
-    fn mul(self, _rhs: Self) -> Self::Output {
-        todo!()
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut elements = [0.0; 16];
+        for j in 0..4 {
+            for i in 0..4 {
+                elements[j * 4 + i] = (0..4).map(|k| self.get(i, k) * rhs.get(k, j)).sum();
+            }
+        }
+        Self { elements }
     }
 }
 
-use crate::linear_algebra::vector::Vector4;
-
 impl std::ops::Mul<Vector4> for Matrix4 {
     type Output = Vector4;
-    fn mul(self, _rhs: Vector4) -> Self::Output {
-        self.elements;
-        todo!()
+    fn mul(self, rhs: Vector4) -> Self::Output {
+        let v = [rhs.x, rhs.y, rhs.z, rhs.w];
+        let row = |i: usize| (0..4).map(|k| self.get(i, k) * v[k]).sum();
+        Vector4::new(row(0), row(1), row(2), row(3))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Matrix4;
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
 
     #[test]
     fn basic() {
@@ -41,5 +238,54 @@ mod test {
         let idm2 = Matrix4::identity();
 
         let m3 = idm * idm2;
+        assert_eq!(m3, Matrix4::identity());
+    }
+    #[test]
+    fn transforms() {
+        let p = vec3(1.0, 2.0, 3.0);
+        let t = Matrix4::translation(vec3(1.0, 0.0, -1.0));
+        assert_eq!(t.transform_point(p), vec3(2.0, 2.0, 2.0));
+        assert_eq!(t.transform_vector(p), p);
+        assert_close(
+            Matrix4::rotation_z(FRAC_PI_2).transform_point(Vector3::UNIT_X),
+            Vector3::UNIT_Y,
+        );
+        assert_close(
+            Matrix4::rotation_x(FRAC_PI_2).transform_point(Vector3::UNIT_Y),
+            Vector3::UNIT_Z,
+        );
+        assert_close(
+            Matrix4::rotation_y(FRAC_PI_2).transform_point(Vector3::UNIT_Z),
+            Vector3::UNIT_X,
+        );
+        // applied right to left: scale first, then translate
+        let m = t * Matrix4::scale(vec3(2.0, 2.0, 2.0));
+        assert_eq!(m.transform_point(p), vec3(3.0, 4.0, 5.0));
+        assert_eq!(m.determinant(), 8.0);
+    }
+    #[test]
+    fn inverse() {
+        let m = Matrix4::translation(vec3(1.0, -2.0, 0.5))
+            * Matrix4::rotation(vec3(1.0, 1.0, 0.0).normalize(), 0.7)
+            * Matrix4::scale(vec3(2.0, 0.5, 3.0));
+        let inverse = m.inverse().unwrap();
+        let p = vec3(0.3, -1.2, 4.0);
+        assert_close(inverse.transform_point(m.transform_point(p)), p);
+        let product = m * inverse;
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.get(i, j) - expected).abs() < 1e-5);
+            }
+        }
+        assert!(Matrix4::scale(vec3(1.0, 0.0, 1.0)).inverse().is_none());
+        // normals stay perpendicular to the surface under non-uniform scale
+        let scale = Matrix4::scale(vec3(1.0, 4.0, 1.0));
+        let tangent = scale.transform_vector(vec3(1.0, 1.0, 0.0));
+        let normal = scale
+            .inverse()
+            .unwrap()
+            .transform_normal(vec3(1.0, -1.0, 0.0));
+        assert!(tangent.x * normal.x + tangent.y * normal.y + tangent.z * normal.z < 1e-6);
     }
 }
@@ -1,10 +1,38 @@
 pub mod matrix;
+pub mod onb;
 pub mod vector;
 pub use matrix::Matrix4;
+pub use onb::Onb;
 pub use vector::{Vector3, Vector4};
 
-#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub struct Transform {
+    /// radians around the Y axis
     pub rotation: f32,
     pub translation: Vector3,
 }
+impl Transform {
+    /// the same transform as `Ray::hit` applies, rotation first
+    pub fn matrix(&self) -> Matrix4 {
+        Matrix4::translation(self.translation) * Matrix4::rotation_y(self.rotation)
+    }
+}
+/// component-wise, so transforms can be interpolated
+impl std::ops::Add for Transform {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            rotation: self.rotation + rhs.rotation,
+            translation: self.translation + rhs.translation,
+        }
+    }
+}
+impl std::ops::Mul<f32> for Transform {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self {
+            rotation: self.rotation * rhs,
+            translation: self.translation * rhs,
+        }
+    }
+}
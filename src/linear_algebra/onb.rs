@@ -0,0 +1,58 @@
+use super::{
+    vector::{dot, vec3},
+    Vector3,
+};
+
+/// orthonormal basis, `w` being the given direction
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    /// build a basis around the normalized vector `w`, with cross(u, v) == w
+    pub fn from_w(w: Vector3) -> Self {
+        // Duff et al., Building an Orthonormal Basis, Revisited
+        // https://jcgt.org/published/0006/01/01/
+        let sign = 1.0_f32.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = vec3(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x);
+        let v = vec3(b, sign + w.y * w.y * a, -w.y);
+        Self { u, v, w }
+    }
+    /// from the basis' coordinates to world coordinates
+    pub fn to_world(&self, a: Vector3) -> Vector3 {
+        self.u * a.x + self.v * a.y + self.w * a.z
+    }
+    /// from world coordinates to the basis' coordinates
+    pub fn to_local(&self, a: Vector3) -> Vector3 {
+        vec3(dot(a, self.u), dot(a, self.v), dot(a, self.w))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linear_algebra::vector::cross;
+
+    #[test]
+    fn orthonormal() {
+        for w in [
+            Vector3::UNIT_Z,
+            -Vector3::UNIT_Z,
+            Vector3::UNIT_X,
+            vec3(1.0, -2.0, 0.5).normalize(),
+        ] {
+            let onb = Onb::from_w(w);
+            assert!((onb.u.length() - 1.0).abs() < 1e-6);
+            assert!((onb.v.length() - 1.0).abs() < 1e-6);
+            assert!(dot(onb.u, onb.v).abs() < 1e-6);
+            assert!(cross(onb.u, onb.v).distance_to(&w) < 1e-6);
+            let a = vec3(0.3, -1.2, 2.0);
+            assert!(onb.to_local(onb.to_world(a)).distance_to(&a) < 1e-5);
+        }
+    }
+}
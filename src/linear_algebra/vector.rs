@@ -3,6 +3,7 @@ use std::{f32::EPSILON, iter::Sum};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
@@ -196,6 +197,7 @@ pub fn cross<T: CrossProduct>(a: T, b: T) -> T {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -400,6 +402,19 @@ impl Sum for Vector3 {
         return result;
     }
 }
+/// access the components by axis, 0 for x, 1 for y and 2 for z
+impl std::ops::Index<usize> for Vector3 {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 index out of range: {index}"),
+        }
+    }
+}
 impl From<(f32, f32, f32)> for Vector3 {
     fn from(value: (f32, f32, f32)) -> Self {
         Self {
@@ -419,12 +434,19 @@ impl From<[f32; 3]> for Vector3 {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Vector4 {
     pub x: f32,
     pub y: f32,
     pub z: f32,
     pub w: f32,
 }
+impl Vector4 {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+}
 
 #[cfg(test)]
 mod test {
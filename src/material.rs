@@ -0,0 +1,274 @@
+//! how surfaces scatter and emit light
+//!
+//! https://raytracing.github.io/books/RayTracingInOneWeekend.html#metal
+use std::f32::consts::PI;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::Color,
+    linear_algebra::{vector::dot, Onb, Vector3},
+    medium::PhaseFunction,
+    ray::{HitRecord, Ray},
+    sampling,
+    texture::{AnyTexture, Texture},
+};
+
+/// a ray leaving a surface, the light coming back along it is multiplied by `attenuation`
+#[derive(Clone, Copy, Debug)]
+pub struct Scatter {
+    pub attenuation: Color,
+    pub ray: Ray,
+}
+
+/// what a surface is made of, its colors given by textures of type `T`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub enum Material<T = AnyTexture> {
+    /// diffuse, scattering in all directions around the normal
+    Lambertian { albedo: T },
+    /// mirror-like, `fuzz` in [0, 1] blurs the reflections
+    Metal { albedo: T, fuzz: f32 },
+    /// clear like glass or water, reflecting or refracting
+    Dielectric {
+        /// relative to the medium outside, 1.5 for glass
+        refraction_index: f32,
+    },
+    /// emits light and scatters nothing
+    DiffuseLight { emit: T },
+    /// the inside of a `medium::ConstantMedium`, scattering by its phase function
+    Isotropic { albedo: T, phase: PhaseFunction },
+}
+impl<T: Texture> Material<T> {
+    /// None if the ray is absorbed
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord, rng: &mut impl Rng) -> Option<Scatter> {
+        let (attenuation, direction) = match self {
+            Material::Lambertian { albedo } => (
+                albedo.value(rec.u, rec.v, rec.point),
+                Onb::from_w(rec.normal).to_world(sampling::cosine_hemisphere(rng)),
+            ),
+            Material::Metal { albedo, fuzz } => {
+                let albedo = albedo.value(rec.u, rec.v, rec.point);
+                let reflected = ray.direction.normalize().reflect(rec.normal);
+                let direction = reflected + sampling::unit_sphere(rng) * *fuzz;
+                // fuzzed below the surface
+                if dot(direction, rec.normal) <= 0.0 {
+                    return None;
+                }
+                (albedo, direction)
+            }
+            Material::Dielectric { refraction_index } => {
+                let ratio = if rec.front_face {
+                    1.0 / refraction_index
+                } else {
+                    *refraction_index
+                };
+                let unit = ray.direction.normalize();
+                let cos_theta = dot(-unit, rec.normal).min(1.0);
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let direction = if ratio * sin_theta > 1.0
+                    || reflectance(cos_theta, ratio) > rng.random::<f32>()
+                {
+                    unit.reflect(rec.normal)
+                } else {
+                    unit.refract(rec.normal, ratio)
+                };
+                (Color::WHITE, direction)
+            }
+            Material::DiffuseLight { .. } => return None,
+            Material::Isotropic { albedo, phase } => (
+                albedo.value(rec.u, rec.v, rec.point),
+                phase.sample(ray.direction, rng),
+            ),
+        };
+        Some(Scatter {
+            attenuation,
+            ray: Ray::new(rec.point, direction).with_time(ray.time),
+        })
+    }
+    /// probability density of `scatter` sending the ray along `scattered`, zero for the
+    /// specular materials
+    pub fn scattering_pdf(&self, ray: &Ray, rec: &HitRecord, scattered: Vector3) -> f32 {
+        match self {
+            Material::Lambertian { .. } => (dot(rec.normal, scattered.normalize()) / PI).max(0.0),
+            Material::Isotropic { phase, .. } => {
+                phase.pdf(ray.direction.normalize(), scattered.normalize())
+            }
+            _ => 0.0,
+        }
+    }
+    /// scattering along a few directions only, the lights can't be sampled toward for them
+    pub fn is_specular(&self) -> bool {
+        matches!(self, Material::Metal { .. } | Material::Dielectric { .. })
+    }
+    /// light given off at the hit point
+    pub fn emitted(&self, rec: &HitRecord) -> Color {
+        match self {
+            Material::DiffuseLight { emit } => emit.value(rec.u, rec.v, rec.point),
+            _ => Color::BLACK,
+        }
+    }
+}
+impl<T> Material<T> {
+    /// the same material with each texture converted, stopping at the first error
+    pub fn try_map<U, E>(&self, mut f: impl FnMut(&T) -> Result<U, E>) -> Result<Material<U>, E> {
+        Ok(match self {
+            Material::Lambertian { albedo } => Material::Lambertian { albedo: f(albedo)? },
+            Material::Metal { albedo, fuzz } => Material::Metal {
+                albedo: f(albedo)?,
+                fuzz: *fuzz,
+            },
+            Material::Dielectric { refraction_index } => Material::Dielectric {
+                refraction_index: *refraction_index,
+            },
+            Material::DiffuseLight { emit } => Material::DiffuseLight { emit: f(emit)? },
+            Material::Isotropic { albedo, phase } => Material::Isotropic {
+                albedo: f(albedo)?,
+                phase: *phase,
+            },
+        })
+    }
+}
+
+/// Schlick's approximation of the fresnel reflectance
+fn reflectance(cosine: f32, ratio: f32) -> f32 {
+    let r0 = (1.0 - ratio) / (1.0 + ratio);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        color::rgba,
+        linear_algebra::{vector::vec3, Vector3},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
+    /// a hit on the plane y = 0 by a ray coming from `origin` to the origin
+    fn hit(origin: Vector3) -> (Ray, HitRecord) {
+        let ray = Ray::new(origin, -origin).with_time(0.5);
+        let mut rec = HitRecord {
+            point: Vector3::ZERO,
+            normal: Vector3::UNIT_Y,
+            t: 1.0,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+        };
+        rec.set_face_normal(&ray, Vector3::UNIT_Y);
+        (ray, rec)
+    }
+
+    #[test]
+    fn lambertian() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let material = Material::Lambertian {
+            albedo: rgba(0.5, 0.2, 0.1, 1.0),
+        };
+        let (ray, rec) = hit(vec3(1.0, 1.0, 0.0));
+        let mut sum = 0.0;
+        for _ in 0..1000 {
+            let scatter = material.scatter(&ray, &rec, &mut rng).unwrap();
+            assert_eq!(scatter.attenuation.g, 0.2);
+            assert_eq!(scatter.ray.origin, Vector3::ZERO);
+            assert_eq!(scatter.ray.time, 0.5);
+            let cos = dot(scatter.ray.direction.normalize(), Vector3::UNIT_Y);
+            assert!(cos >= 0.0);
+            sum += cos;
+        }
+        // cosine distributed, the mean cosine is 2/3
+        assert!((sum / 1000.0 - 2.0 / 3.0).abs() < 0.03);
+        assert_eq!(material.emitted(&rec).r, 0.0);
+        assert!(!material.is_specular());
+        let pdf = material.scattering_pdf(&ray, &rec, vec3(1.0, 1.0, 0.0));
+        assert!((pdf - 0.5_f32.sqrt() / PI).abs() < 1e-6);
+        assert_eq!(material.scattering_pdf(&ray, &rec, -Vector3::UNIT_Y), 0.0);
+    }
+    #[test]
+    fn metal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mirror = Material::Metal {
+            albedo: Color::WHITE,
+            fuzz: 0.0,
+        };
+        let (ray, rec) = hit(vec3(-1.0, 1.0, 0.0));
+        let scatter = mirror.scatter(&ray, &rec, &mut rng).unwrap();
+        assert_close(scatter.ray.direction, vec3(1.0, 1.0, 0.0).normalize());
+        assert!(mirror.is_specular());
+        assert_eq!(
+            mirror.scattering_pdf(&ray, &rec, scatter.ray.direction),
+            0.0
+        );
+        // grazing rays get fuzzed below the surface sometimes
+        let fuzzy = Material::Metal {
+            albedo: Color::WHITE,
+            fuzz: 1.0,
+        };
+        let (ray, rec) = hit(vec3(-10.0, 0.1, 0.0));
+        let absorbed = (0..1000)
+            .filter(|_| fuzzy.scatter(&ray, &rec, &mut rng).is_none())
+            .count();
+        assert!(absorbed > 100 && absorbed < 900, "{absorbed}");
+    }
+    #[test]
+    fn dielectric() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let glass: Material = Material::Dielectric {
+            refraction_index: 1.5,
+        };
+        // about 4% reflected at normal incidence
+        let (ray, rec) = hit(Vector3::UNIT_Y);
+        let reflected = (0..10000)
+            .filter(|_| glass.scatter(&ray, &rec, &mut rng).unwrap().ray.direction.y > 0.0)
+            .count();
+        assert!(
+            (reflected as f32 / 10000.0 - 0.04).abs() < 0.01,
+            "{reflected}"
+        );
+        // bent toward the normal entering, snell's law
+        let (ray, rec) = hit(vec3(-1.0, 1.0, 0.0));
+        let sin_out = (0..100)
+            .filter_map(|_| {
+                let d = glass.scatter(&ray, &rec, &mut rng).unwrap().ray.direction;
+                (d.y < 0.0).then(|| d.x / d.length())
+            })
+            .next()
+            .unwrap();
+        assert!((sin_out - 0.5_f32.sqrt() / 1.5).abs() < 1e-5);
+        // total internal reflection leaving at a grazing angle
+        let (ray, rec) = hit(vec3(-1.0, -0.2, 0.0));
+        assert!(!rec.front_face);
+        for _ in 0..100 {
+            assert!(glass.scatter(&ray, &rec, &mut rng).unwrap().ray.direction.y < 0.0);
+        }
+        // no change of index, no bending
+        let air: Material = Material::Dielectric {
+            refraction_index: 1.0,
+        };
+        let (ray, rec) = hit(vec3(-1.0, 1.0, 0.0));
+        let scatter = air.scatter(&ray, &rec, &mut rng).unwrap();
+        assert_close(scatter.ray.direction, ray.direction.normalize());
+    }
+    #[test]
+    fn light_and_serde() {
+        let json = r#"[
+            { "DiffuseLight": { "emit": { "Solid": { "r": 4.0, "g": 4.0, "b": 4.0, "a": 1.0 } } } },
+            { "Metal": { "albedo": { "Solid": { "r": 0.8, "g": 0.8, "b": 0.8, "a": 1.0 } }, "fuzz": 0.1 } },
+            { "Isotropic": { "albedo": { "Solid": { "r": 1.0, "g": 1.0, "b": 1.0, "a": 1.0 } }, "phase": "Isotropic" } }
+        ]"#;
+        let materials: Vec<Material> = serde_json::from_str(json).unwrap();
+        let (ray, rec) = hit(Vector3::UNIT_Y);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(materials[0].scatter(&ray, &rec, &mut rng).is_none());
+        assert_eq!(materials[0].emitted(&rec).r, 4.0);
+        assert!(matches!(materials[1], Material::Metal { fuzz, .. } if fuzz == 0.1));
+        let scatter = materials[2].scatter(&ray, &rec, &mut rng).unwrap();
+        assert!((scatter.ray.direction.length() - 1.0).abs() < 1e-5);
+    }
+}
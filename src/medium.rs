@@ -0,0 +1,231 @@
+//! participating media like smoke and fog
+//!
+//! https://raytracing.github.io/books/RayTracingTheNextWeek.html#volumes
+use std::{f32::consts::PI, ops::Range};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    linear_algebra::{vector::dot, Onb, Vector3},
+    ray::{HitRecord, Hitable, Ray},
+    sampling,
+};
+
+/// how the light is scattered inside a medium
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum PhaseFunction {
+    /// equally in every direction
+    Isotropic,
+    /// mostly forward for a positive `g`, backward for a negative one, in (-1, 1)
+    HenyeyGreenstein { g: f32 },
+}
+impl PhaseFunction {
+    /// probability density of scattering from the `direction` the light travels in
+    /// to the `scattered` one, both normalized
+    pub fn pdf(&self, direction: Vector3, scattered: Vector3) -> f32 {
+        match *self {
+            PhaseFunction::Isotropic => 1.0 / (4.0 * PI),
+            PhaseFunction::HenyeyGreenstein { g } => {
+                let cos_theta = dot(direction, scattered);
+                let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+                (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+            }
+        }
+    }
+    /// a scattered direction, distributed as `pdf`
+    pub fn sample(&self, direction: Vector3, rng: &mut impl Rng) -> Vector3 {
+        match *self {
+            PhaseFunction::Isotropic => sampling::unit_sphere(rng),
+            PhaseFunction::HenyeyGreenstein { g } => {
+                let xi: f32 = rng.random();
+                let cos_theta = if g.abs() < 1e-3 {
+                    1.0 - 2.0 * xi
+                } else {
+                    let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+                    (1.0 + g * g - s * s) / (2.0 * g)
+                };
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * rng.random::<f32>();
+                let local = Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                Onb::from_w(direction.normalize()).to_world(local)
+            }
+        }
+    }
+}
+
+/// a volume of constant density filling a closed `boundary`.
+///
+/// rays are scattered at a random distance inside, the fraction passing through a length `l`
+/// being `exp(-density * l)`. the distance is drawn from a hash of the ray rather than a global
+/// rng, so a ray always scatters at the same point and renders stay reproducible.
+pub struct ConstantMedium<T> {
+    pub boundary: T,
+    pub density: f32,
+    pub phase: PhaseFunction,
+}
+impl<T: Hitable> ConstantMedium<T> {
+    pub fn new(boundary: T, density: f32, phase: PhaseFunction) -> Self {
+        Self {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+impl<T: Hitable> Hitable for ConstantMedium<T> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        let length = ray.direction.length();
+        let mut distance = -(1.0 - ray_random(&ray)).ln() / self.density;
+        // walk through the parts of the boundary along the ray, which may be several
+        // for a concave one
+        for interval in self.boundary.intervals(ray) {
+            let start = interval.enter.t.max(range.start);
+            let end = interval.exit.t.min(range.end);
+            if start >= end {
+                continue;
+            }
+            let inside = (end - start) * length;
+            if distance < inside {
+                let t = start + distance / length;
+                return Some(HitRecord {
+                    point: ray.at(t),
+                    // arbitrary, the phase function decides the direction
+                    normal: Vector3::UNIT_X,
+                    t,
+                    front_face: true,
+                    u: 0.0,
+                    v: 0.0,
+                });
+            }
+            distance -= inside;
+        }
+        None
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+/// a number in [0, 1) from the bits of the ray, any change to the ray giving an unrelated one
+fn ray_random(ray: &Ray) -> f32 {
+    let (o, d) = (ray.origin, ray.direction);
+    let mut hash = 0u64;
+    for x in [o.x, o.y, o.z, d.x, d.y, d.z, ray.time] {
+        // splitmix64 https://prng.di.unimi.it/splitmix64.c
+        hash = (hash ^ x.to_bits() as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+    }
+    // the top 24 bits, as many as an f32 holds exactly
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        csg::Csg,
+        geometry::{Box, Sphere},
+        linear_algebra::vector::vec3,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn mean_cosine(phase: PhaseFunction) -> f32 {
+        let mut rng = StdRng::seed_from_u64(7);
+        let direction = vec3(1.0, 2.0, -0.5).normalize();
+        let n = 20000;
+        let sum: f32 = (0..n)
+            .map(|_| {
+                let scattered = phase.sample(direction, &mut rng);
+                assert!((scattered.length() - 1.0).abs() < 1e-4);
+                dot(direction, scattered)
+            })
+            .sum();
+        sum / n as f32
+    }
+
+    #[test]
+    fn phase_functions() {
+        // the mean cosine of henyey-greenstein is g
+        assert!(mean_cosine(PhaseFunction::Isotropic).abs() < 0.02);
+        for g in [-0.5, 0.0, 0.3, 0.8] {
+            let mean = mean_cosine(PhaseFunction::HenyeyGreenstein { g });
+            assert!((mean - g).abs() < 0.02, "{mean} != {g}");
+        }
+        // the pdfs integrate to 1 over the sphere
+        for phase in [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein { g: 0.6 },
+        ] {
+            let n = 2000;
+            let integral: f32 = (0..n)
+                .map(|i| {
+                    let cos_theta = -1.0 + (i as f32 + 0.5) * 2.0 / n as f32;
+                    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                    let scattered = vec3(sin_theta, 0.0, cos_theta);
+                    phase.pdf(Vector3::UNIT_Z, scattered) * 2.0 * PI * 2.0 / n as f32
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-3, "{integral}");
+        }
+    }
+    #[test]
+    fn transmittance() {
+        let fog = ConstantMedium::new(
+            Box::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0)),
+            0.5,
+            PhaseFunction::Isotropic,
+        );
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X * 2.0);
+        let n = 20000;
+        let mut passed = 0;
+        for i in 0..n {
+            // the time doesn't move the fog, it only changes the random distance
+            let ray = ray.with_time(i as f32 / n as f32);
+            match fog.hit(ray, 0.0..f32::INFINITY) {
+                Some(rec) => assert!((2.0..=3.0).contains(&rec.t), "{}", rec.t),
+                None => passed += 1,
+            }
+        }
+        let expected = (-0.5_f32 * 2.0).exp();
+        assert!((passed as f32 / n as f32 - expected).abs() < 0.02);
+        // the same ray always scatters at the same point
+        let first = fog.hit(ray, 0.0..f32::INFINITY).map(|rec| rec.t);
+        for _ in 0..10 {
+            assert_eq!(fog.hit(ray, 0.0..f32::INFINITY).map(|rec| rec.t), first);
+        }
+        // starting inside, the range cuts the path
+        for i in 0..100 {
+            let inside = Ray::new(Vector3::ZERO, Vector3::UNIT_X).with_time(i as f32 / 100.0);
+            if let Some(rec) = fog.hit(inside, 0.5..f32::INFINITY) {
+                assert!((0.5..=1.0).contains(&rec.t));
+            }
+        }
+    }
+    #[test]
+    fn concave_boundary() {
+        // a shell: the hole in the middle is empty
+        let shell = Csg::difference(
+            Sphere::new(Vector3::ZERO, 2.0),
+            Sphere::new(Vector3::ZERO, 1.0),
+        );
+        let smoke = ConstantMedium::new(shell, 0.3, PhaseFunction::HenyeyGreenstein { g: 0.2 });
+        let n = 20000;
+        let mut passed = 0;
+        for i in 0..n {
+            let ray =
+                Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X).with_time(i as f32 / n as f32);
+            match smoke.hit(ray, 0.0..f32::INFINITY) {
+                Some(rec) => assert!(rec.point.length() >= 1.0 - 1e-4, "{:?}", rec.point),
+                None => passed += 1,
+            }
+        }
+        let expected = (-0.3_f32 * 2.0).exp();
+        assert!((passed as f32 / n as f32 - expected).abs() < 0.02);
+        assert_eq!(smoke.bounding_box().max, vec3(2.0, 2.0, 2.0));
+    }
+}
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+
+use crate::{
+    geometry::TriMesh,
+    linear_algebra::{vector::Vector2, Vector3},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// the mesh failed `TriMesh::validate`
+    InvalidMesh(&'static str),
+    /// a triangle references the same vertex more than once
+    DegenerateFace(usize),
+    /// the directed edge (from, to) is used by more than one face,
+    /// either because more than 2 faces share the edge or because neighbouring faces have opposite winding
+    NonManifoldEdge(usize, usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Vertex {
+    pub position: Vector3,
+    /// an outgoing half-edge, a boundary one if the vertex lies on the boundary.
+    /// `None` for isolated vertices
+    pub half_edge: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HalfEdge {
+    /// the vertex this half-edge starts from
+    pub origin: usize,
+    /// the half-edge going the opposite direction on the same edge
+    pub twin: usize,
+    pub next: usize,
+    pub prev: usize,
+    /// `None` for boundary half-edges, which form the boundary loops
+    pub face: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Face {
+    /// any half-edge of the face
+    pub half_edge: usize,
+}
+
+/// half-edge representation of a triangle mesh, for adjacency queries.
+///
+/// boundary edges get an explicit half-edge without a face, so every half-edge has a twin
+/// and the boundary loops can be walked through `next`.
+#[derive(Clone, Debug)]
+pub struct HalfEdgeMesh {
+    vertices: Vec<Vertex>,
+    half_edges: Vec<HalfEdge>,
+    faces: Vec<Face>,
+    /// the normals and uvs of the input mesh, given back by `to_tri_mesh`
+    normals: Vec<Vector3>,
+    uvs: Vec<Vector2>,
+    /// set when a vertex joins more than one fan of faces (e.g. two cones touching at the tip)
+    non_manifold_vertex: bool,
+}
+
+impl HalfEdgeMesh {
+    pub fn from_tri_mesh(mesh: &TriMesh) -> Result<Self, Error> {
+        mesh.validate().map_err(Error::InvalidMesh)?;
+        let indices = mesh.indices();
+        let face_count = mesh.triangle_count();
+        let mut vertices: Vec<Vertex> = mesh
+            .vertices()
+            .iter()
+            .map(|&position| Vertex {
+                position,
+                half_edge: None,
+            })
+            .collect();
+        let mut half_edges = Vec::with_capacity(face_count * 3);
+        let mut faces = Vec::with_capacity(face_count);
+        let mut edge_map = HashMap::<(usize, usize), usize>::with_capacity(face_count * 3);
+
+        for f in 0..face_count {
+            let tri = &indices[f * 3..f * 3 + 3];
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+                return Err(Error::DegenerateFace(f));
+            }
+            let base = f * 3;
+            for k in 0..3 {
+                let (from, to) = (tri[k], tri[(k + 1) % 3]);
+                if edge_map.insert((from, to), base + k).is_some() {
+                    return Err(Error::NonManifoldEdge(from, to));
+                }
+                half_edges.push(HalfEdge {
+                    origin: from,
+                    // patched below
+                    twin: usize::MAX,
+                    next: base + (k + 1) % 3,
+                    prev: base + (k + 2) % 3,
+                    face: Some(f),
+                });
+                vertices[from].half_edge = Some(base + k);
+            }
+            faces.push(Face { half_edge: base });
+        }
+
+        // pair the twins, creating boundary half-edges for the unpaired ones
+        let interior_count = half_edges.len();
+        // boundary half-edges indexed by their origin vertex
+        let mut boundary_from = HashMap::<usize, usize>::new();
+        let mut non_manifold_vertex = false;
+        for h in 0..interior_count {
+            let from = half_edges[h].origin;
+            let to = half_edges[half_edges[h].next].origin;
+            if let Some(&twin) = edge_map.get(&(to, from)) {
+                half_edges[h].twin = twin;
+            } else {
+                let b = half_edges.len();
+                half_edges.push(HalfEdge {
+                    origin: to,
+                    twin: h,
+                    next: usize::MAX,
+                    prev: usize::MAX,
+                    face: None,
+                });
+                half_edges[h].twin = b;
+                if boundary_from.insert(to, b).is_some() {
+                    non_manifold_vertex = true;
+                }
+                // start circulation of boundary vertices at the boundary
+                vertices[to].half_edge = Some(b);
+            }
+        }
+        // link the boundary loops
+        for b in interior_count..half_edges.len() {
+            let to = half_edges[half_edges[b].twin].origin;
+            // a boundary half-edge always ends at a vertex with an outgoing boundary half-edge
+            let next = boundary_from[&to];
+            half_edges[b].next = next;
+            half_edges[next].prev = b;
+        }
+
+        let mut mesh = Self {
+            vertices,
+            half_edges,
+            faces,
+            normals: mesh.normals().unwrap_or_default().to_vec(),
+            uvs: mesh.uvs().unwrap_or_default().to_vec(),
+            non_manifold_vertex,
+        };
+        if !mesh.non_manifold_vertex {
+            mesh.non_manifold_vertex = mesh.has_split_fans();
+        }
+        Ok(mesh)
+    }
+
+    pub fn to_tri_mesh(&self) -> TriMesh {
+        let vertices = self.vertices.iter().map(|v| v.position).collect();
+        let indices = (0..self.faces.len())
+            .flat_map(|f| self.face_vertices(f))
+            .collect();
+        TriMesh::new(vertices, indices)
+            .with_normals(self.normals.clone())
+            .with_uvs(self.uvs.clone())
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+    /// number of undirected edges
+    pub fn edge_count(&self) -> usize {
+        self.half_edges.len() / 2
+    }
+
+    /// the vertex the half-edge points to
+    pub fn destination(&self, h: usize) -> usize {
+        self.half_edges[self.half_edges[h].twin].origin
+    }
+    pub fn is_boundary_edge(&self, h: usize) -> bool {
+        self.half_edges[h].face.is_none() || self.half_edges[self.half_edges[h].twin].face.is_none()
+    }
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.vertex_half_edges(v)
+            .any(|h| self.half_edges[h].face.is_none())
+    }
+
+    /// half-edges going out of vertex `v`
+    pub fn vertex_half_edges(&self, v: usize) -> VertexHalfEdges<'_> {
+        let start = self.vertices[v].half_edge;
+        VertexHalfEdges {
+            mesh: self,
+            start,
+            current: start,
+            remaining: self.half_edges.len(),
+        }
+    }
+    /// faces around vertex `v`
+    pub fn vertex_faces(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_half_edges(v)
+            .filter_map(|h| self.half_edges[h].face)
+    }
+    /// the one-ring neighbours of vertex `v`
+    pub fn vertex_neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_half_edges(v).map(|h| self.destination(h))
+    }
+    /// half-edges around face `f`, in winding order
+    pub fn face_half_edges(&self, f: usize) -> impl Iterator<Item = usize> + '_ {
+        let first = self.faces[f].half_edge;
+        let second = self.half_edges[first].next;
+        let third = self.half_edges[second].next;
+        [first, second, third].into_iter()
+    }
+    /// vertex indices of face `f`, in winding order
+    pub fn face_vertices(&self, f: usize) -> impl Iterator<Item = usize> + '_ {
+        self.face_half_edges(f).map(|h| self.half_edges[h].origin)
+    }
+
+    /// all boundary loops, as lists of boundary half-edges in loop order
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+        for start in 0..self.half_edges.len() {
+            if visited[start] || self.half_edges[start].face.is_some() {
+                continue;
+            }
+            let mut boundary = Vec::new();
+            let mut h = start;
+            while !visited[h] {
+                visited[h] = true;
+                boundary.push(h);
+                h = self.half_edges[h].next;
+            }
+            loops.push(boundary);
+        }
+        loops
+    }
+
+    /// edge-manifold is guaranteed by construction, this checks every vertex has a single fan of faces
+    pub fn is_manifold(&self) -> bool {
+        !self.non_manifold_vertex
+    }
+    pub fn is_closed(&self) -> bool {
+        self.half_edges.iter().all(|h| h.face.is_some())
+    }
+
+    /// V - E + F
+    pub fn euler_characteristic(&self) -> i64 {
+        self.vertices.len() as i64 - self.edge_count() as i64 + self.faces.len() as i64
+    }
+    /// number of connected components, isolated vertices count as components
+    pub fn connected_components(&self) -> usize {
+        let mut parents: Vec<usize> = (0..self.vertices.len()).collect();
+        fn find(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        let mut components = self.vertices.len();
+        for h in &self.half_edges {
+            let a = find(&mut parents, h.origin);
+            let b = find(&mut parents, self.half_edges[h.twin].origin);
+            if a != b {
+                parents[a] = b;
+                components -= 1;
+            }
+        }
+        components
+    }
+    /// total genus of the surface, from χ = 2c - 2g - b summed over the components.
+    ///
+    /// returns `None` if the mesh isn't manifold
+    pub fn genus(&self) -> Option<u32> {
+        if !self.is_manifold() {
+            return None;
+        }
+        let c = self.connected_components() as i64;
+        let b = self.boundary_loops().len() as i64;
+        let twice_genus = 2 * c - b - self.euler_characteristic();
+        if twice_genus < 0 || twice_genus % 2 != 0 {
+            return None;
+        }
+        Some((twice_genus / 2) as u32)
+    }
+
+    /// a vertex is non-manifold if circulating from its half-edge doesn't reach all of its outgoing half-edges
+    fn has_split_fans(&self) -> bool {
+        let mut outgoing = vec![0usize; self.vertices.len()];
+        for h in &self.half_edges {
+            outgoing[h.origin] += 1;
+        }
+        (0..self.vertices.len()).any(|v| self.vertex_half_edges(v).count() != outgoing[v])
+    }
+}
+
+/// circulates the outgoing half-edges of a vertex, see `HalfEdgeMesh::vertex_half_edges`
+pub struct VertexHalfEdges<'a> {
+    mesh: &'a HalfEdgeMesh,
+    start: Option<usize>,
+    current: Option<usize>,
+    /// guards against endless circulation around non-manifold vertices
+    remaining: usize,
+}
+impl Iterator for VertexHalfEdges<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let h = self.current?;
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let half_edges = &self.mesh.half_edges;
+        // the twin ends at the vertex, its next starts from it
+        let next = half_edges[half_edges[h].twin].next;
+        self.current = if Some(next) == self.start {
+            None
+        } else {
+            Some(next)
+        };
+        Some(h)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{linear_algebra::vector::vec3, model_3d::primitives};
+
+    fn tetrahedron() -> TriMesh {
+        TriMesh::new(
+            vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+                vec3(0.0, 0.0, 1.0),
+            ],
+            vec![0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3],
+        )
+    }
+    fn quad() -> TriMesh {
+        TriMesh::new(
+            vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(1.0, 1.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+    }
+    /// a n*m grid with both directions wrapped around
+    fn torus(n: usize, m: usize) -> TriMesh {
+        let vertices = (0..n * m)
+            .map(|i| vec3((i % n) as f32, (i / n) as f32, 0.0))
+            .collect();
+        let mut indices = Vec::new();
+        for j in 0..m {
+            for i in 0..n {
+                let a = j * n + i;
+                let b = j * n + (i + 1) % n;
+                let c = ((j + 1) % m) * n + (i + 1) % n;
+                let d = ((j + 1) % m) * n + i;
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+        TriMesh::new(vertices, indices)
+    }
+
+    #[test]
+    fn closed_mesh() {
+        let mesh = HalfEdgeMesh::from_tri_mesh(&tetrahedron()).unwrap();
+        assert_eq!(mesh.edge_count(), 6);
+        assert!(mesh.is_closed());
+        assert!(mesh.is_manifold());
+        assert!(mesh.boundary_loops().is_empty());
+        assert_eq!(mesh.euler_characteristic(), 2);
+        assert_eq!(mesh.genus(), Some(0));
+        for v in 0..4 {
+            assert_eq!(mesh.vertex_faces(v).count(), 3);
+            let mut ring: Vec<usize> = mesh.vertex_neighbors(v).collect();
+            ring.sort();
+            let expected: Vec<usize> = (0..4).filter(|&i| i != v).collect();
+            assert_eq!(ring, expected);
+        }
+    }
+    #[test]
+    fn open_mesh() {
+        let mesh = HalfEdgeMesh::from_tri_mesh(&quad()).unwrap();
+        assert_eq!(mesh.edge_count(), 5);
+        assert!(!mesh.is_closed());
+        assert!(mesh.is_manifold());
+        let loops = mesh.boundary_loops();
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 4);
+        assert!((0..4).all(|v| mesh.is_boundary_vertex(v)));
+        assert_eq!(mesh.vertex_faces(0).count(), 2);
+        assert_eq!(mesh.vertex_faces(1).count(), 1);
+        assert_eq!(mesh.euler_characteristic(), 1);
+        assert_eq!(mesh.genus(), Some(0));
+    }
+    #[test]
+    fn torus_genus() {
+        let mesh = HalfEdgeMesh::from_tri_mesh(&torus(4, 3)).unwrap();
+        assert!(mesh.is_closed());
+        assert_eq!(mesh.euler_characteristic(), 0);
+        assert_eq!(mesh.genus(), Some(1));
+    }
+    #[test]
+    fn face_iteration() {
+        let mesh = HalfEdgeMesh::from_tri_mesh(&quad()).unwrap();
+        assert_eq!(mesh.face_vertices(1).collect::<Vec<_>>(), vec![0, 2, 3]);
+        for h in mesh.face_half_edges(0) {
+            assert_eq!(mesh.half_edges()[h].face, Some(0));
+        }
+    }
+    #[test]
+    fn round_trip() {
+        let tri_mesh = tetrahedron();
+        let mesh = HalfEdgeMesh::from_tri_mesh(&tri_mesh)
+            .unwrap()
+            .to_tri_mesh();
+        assert_eq!(mesh.indices(), tri_mesh.indices());
+        assert_eq!(mesh.vertices(), tri_mesh.vertices());
+        // normals and uvs are kept
+        let plane = primitives::plane(1.0, 1.0, 2, 2);
+        let mesh = HalfEdgeMesh::from_tri_mesh(&plane).unwrap().to_tri_mesh();
+        assert_eq!(mesh.normals(), plane.normals());
+        assert_eq!(mesh.uvs().unwrap().len(), plane.uvs().unwrap().len());
+    }
+    #[test]
+    fn non_manifold() {
+        // a third triangle on the edge (0, 1) with the same winding
+        let mesh = TriMesh::new(
+            vec![vec3(0.0, 0.0, 0.0); 5],
+            vec![0, 1, 2, 1, 0, 3, 0, 1, 4],
+        );
+        assert_eq!(
+            HalfEdgeMesh::from_tri_mesh(&mesh).unwrap_err(),
+            Error::NonManifoldEdge(0, 1)
+        );
+        // two triangles touching at vertex 0
+        let bowtie = TriMesh::new(vec![vec3(0.0, 0.0, 0.0); 5], vec![0, 1, 2, 0, 3, 4]);
+        let mesh = HalfEdgeMesh::from_tri_mesh(&bowtie).unwrap();
+        assert!(!mesh.is_manifold());
+        assert_eq!(mesh.genus(), None);
+    }
+}
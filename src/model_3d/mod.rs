@@ -1 +1,5 @@
 pub mod obj;
+pub mod half_edge;
+pub mod simplify;
+pub mod subdivision;
+pub mod primitives;
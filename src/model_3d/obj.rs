@@ -1,11 +1,15 @@
-use std::fs;
+use std::{collections::HashMap, fs};
 
 use crate::{
-    geometry::TriMesh,
-    linear_algebra::{vector::Vector2, Vector3},
+    geometry::{PolyMesh, TriMesh},
+    linear_algebra::{
+        vector::{vec2, Vector2},
+        Vector3,
+    },
 };
 
 pub struct Parser;
+#[derive(Debug)]
 pub enum ParserError {
     Io(std::io::Error),
     InvalidVertexValue,
@@ -15,83 +19,230 @@ pub enum ParserError {
 }
 
 impl Parser {
+    /// load an OBJ file, polygons are split into triangles
     pub fn load(path: impl AsRef<std::path::Path>) -> Result<TriMesh, ParserError> {
+        Ok(Self::load_polygons(path)?.triangulate())
+    }
+    /// load an OBJ file keeping its polygons as they are
+    pub fn load_polygons(path: impl AsRef<std::path::Path>) -> Result<PolyMesh, ParserError> {
         let str = fs::read_to_string(path.as_ref()).map_err(ParserError::Io)?;
-        let mut lines = str.lines();
+        Self::parse(&str)
+    }
+    /// parse the content of an OBJ file.
+    ///
+    /// normals and texture coordinates are kept when every face corner has one, the positions
+    /// used with more than one normal or uv are then split into several vertices.
+    pub fn parse(str: &str) -> Result<PolyMesh, ParserError> {
         let mut positions: Vec<Vector3> = vec![];
         let mut normals: Vec<Vector3> = vec![];
         let mut uvs: Vec<Vector2> = vec![];
-        // for line in lines {
-        //     if line.starts_with("v ") {
-        //         let mut value_iter = line.split_whitespace().skip(1);
-        //         let x = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidVertexValue))
-        //             .unwrap_or(Err(ParserError::InvalidVertexValue))?;
-        //         let y = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidVertexValue))
-        //             .unwrap_or(Err(ParserError::InvalidVertexValue))?;
-        //         let z = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidVertexValue))
-        //             .unwrap_or(Err(ParserError::InvalidVertexValue))?;
+        let mut faces: Vec<Vec<Corner>> = vec![];
+        for line in str.lines() {
+            let mut values = line.split_whitespace();
+            match values.next() {
+                Some("v") => {
+                    let [x, y, z] = parse_floats(&mut values, ParserError::InvalidVertexValue)?;
+                    positions.push(Vector3::new(x, y, z));
+                }
+                Some("vn") => {
+                    let [x, y, z] = parse_floats(&mut values, ParserError::InvalidNormalValue)?;
+                    normals.push(Vector3::new(x, y, z));
+                }
+                Some("vt") => {
+                    // u with optional v and w, w is ignored
+                    let uvw = values
+                        .take(3)
+                        .map(|s| s.parse::<f32>())
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|_| ParserError::InvalidTextureValue)?;
+                    let (u, v) = match uvw[..] {
+                        [u] => (u, 0.0),
+                        [u, v, ..] => (u, v),
+                        [] => return Err(ParserError::InvalidTextureValue),
+                    };
+                    uvs.push(vec2(u, v));
+                }
+                Some("f") => {
+                    let face = values
+                        .map(|s| {
+                            // v, v/vt, v//vn or v/vt/vn
+                            let mut parts = s.split('/');
+                            let v = parse_index(parts.next(), positions.len())?;
+                            let vt = match parts.next().filter(|s| !s.is_empty()) {
+                                Some(vt) => Some(parse_index(Some(vt), uvs.len())?),
+                                None => None,
+                            };
+                            let vn = match parts.next() {
+                                Some(vn) => Some(parse_index(Some(vn), normals.len())?),
+                                None => None,
+                            };
+                            Ok((v, vt, vn))
+                        })
+                        .collect::<Result<Vec<_>, ParserError>>()?;
+                    if face.len() < 3 {
+                        return Err(ParserError::InvalidFaceValue);
+                    }
+                    faces.push(face);
+                }
+                // groups, materials, comments etc.
+                _ => {}
+            }
+        }
+
+        let corners = || faces.iter().flatten();
+        let has_uvs = !faces.is_empty() && corners().all(|c| c.1.is_some());
+        let has_normals = !faces.is_empty() && corners().all(|c| c.2.is_some());
+        if !has_uvs && !has_normals {
+            let faces = faces
+                .iter()
+                .map(|face| face.iter().map(|c| c.0).collect())
+                .collect();
+            return Ok(PolyMesh::new(positions, faces));
+        }
+        // one vertex per distinct corner
+        let mut vertex_of = HashMap::new();
+        let mut corner_list = vec![];
+        let faces = faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .map(|&(v, vt, vn)| {
+                        let key = (v, vt.filter(|_| has_uvs), vn.filter(|_| has_normals));
+                        *vertex_of.entry(key).or_insert_with(|| {
+                            corner_list.push(key);
+                            corner_list.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut mesh = PolyMesh::new(corner_list.iter().map(|c| positions[c.0]).collect(), faces);
+        if has_uvs {
+            mesh = mesh.with_uvs(corner_list.iter().map(|c| uvs[c.1.unwrap()]).collect());
+        }
+        if has_normals {
+            mesh = mesh.with_normals(corner_list.iter().map(|c| normals[c.2.unwrap()]).collect());
+        }
+        Ok(mesh)
+    }
+}
+
+/// position, uv and normal indices of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+fn parse_floats<'a, const N: usize>(
+    values: &mut impl Iterator<Item = &'a str>,
+    err: ParserError,
+) -> Result<[f32; N], ParserError> {
+    let mut result = [0.0; N];
+    for r in &mut result {
+        match values.next().map(|s| s.parse()) {
+            Some(Ok(v)) => *r = v,
+            _ => return Err(err),
+        }
+    }
+    Ok(result)
+}
 
-        //         positions.push(Vector3::new(x, y, z));
-        //     } else if line.starts_with("vn ") {
-        //         let mut value_iter = line.split_whitespace().skip(1);
-        //         let x = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidNormalValue))
-        //             .unwrap_or(Err(ParserError::InvalidNormalValue))?;
-        //         let y = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidNormalValue))
-        //             .unwrap_or(Err(ParserError::InvalidNormalValue))?;
-        //         let z = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidNormalValue))
-        //             .unwrap_or(Err(ParserError::InvalidNormalValue))?;
+/// OBJ indices start from 1, negative ones are relative to the end of the list
+fn parse_index(s: Option<&str>, len: usize) -> Result<usize, ParserError> {
+    let i: i64 = s
+        .and_then(|s| s.parse().ok())
+        .ok_or(ParserError::InvalidFaceValue)?;
+    let index = if i < 0 { len as i64 + i } else { i - 1 };
+    if index < 0 || index >= len as i64 {
+        return Err(ParserError::InvalidFaceValue);
+    }
+    Ok(index as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linear_algebra::vector::vec3;
 
-        //         normals.push(Vector3::new(x, y, z));
-        //     } else if line.starts_with("vt ") {
-        //         let mut value_iter = line.split_whitespace().skip(1);
-        //         let x = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidTextureValue))
-        //             .unwrap_or(Err(ParserError::InvalidTextureValue))?;
-        //         let y = value_iter
-        //             .next()
-        //             .map(|s| s.parse().map_err(|_| ParserError::InvalidTextureValue))
-        //             .unwrap_or(Err(ParserError::InvalidTextureValue))?;
-        //         uvs.push(Vector2::new(x, y));
-        //     } else if line.starts_with("f ") {
-        //         let values: Vec<Vec<[usize; 3]>> = line
-        //             .split_whitespace()
-        //             .skip(1)
-        //             .map(|s| {
-        //                 let mut parts = s.split('/');
-        //                 let v = parts
-        //                     .next()
-        //                     .unwrap()
-        //                     .parse()
-        //                     .map_err(|_| ParserError::InvalidFaceValue)?;
-        //                 let vt = parts
-        //                     .next()
-        //                     .unwrap()
-        //                     .parse()
-        //                     .map_err(|_| ParserError::InvalidFaceValue)?;
-        //                 let vn = parts
-        //                     .next()
-        //                     .unwrap()
-        //                     .parse()
-        //                     .map_err(|_| ParserError::InvalidFaceValue)?;
-        //                 Ok([v, vt, vn])
-        //             })
-        //             .collect()?;
-        //         todo!()
-        //     }
-        // }
-        todo!()
+    const QUAD: &str = "
+# a unit quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1 4/1/1
+";
+
+    #[test]
+    fn polygons() {
+        let mesh = Parser::parse(QUAD).unwrap();
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.faces(), &[vec![0, 1, 2, 3]]);
+        let tri_mesh = mesh.triangulate();
+        assert_eq!(tri_mesh.indices(), &[0, 1, 2, 0, 2, 3]);
+    }
+    #[test]
+    fn index_formats() {
+        let mesh =
+            Parser::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 -2//1 3//-1").unwrap();
+        assert_eq!(mesh.faces(), &[vec![0, 1, 2]]);
+    }
+    #[test]
+    fn texture_coordinates() {
+        let mesh =
+            Parser::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5\nvt 0 1 0\nf 1/1 2/2 3/1").unwrap();
+        assert_eq!(mesh.faces(), &[vec![0, 1, 2]]);
+        let uvs = mesh.uvs().unwrap();
+        assert_eq!((uvs[0].x, uvs[0].y), (0.5, 0.0));
+        assert_eq!((uvs[1].x, uvs[1].y), (0.0, 1.0));
+    }
+    #[test]
+    fn attributes() {
+        // two faces sharing the edge 2-3 with different normals
+        let mesh = Parser::parse(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 1\n\
+             vt 0.5\nvt 0.25 1 0\n\
+             vn 0 0 1\nvn 0 -1 1\n\
+             f 1/1/1 2/1/1 3/2/1 4/2/1\nf 4/2/2 3/2/2 5/1/2",
+        )
+        .unwrap();
+        assert!(mesh.validate().is_ok());
+        assert_eq!(mesh.vertices().len(), 7);
+        assert_eq!(mesh.faces(), &[vec![0, 1, 2, 3], vec![4, 5, 6]]);
+        assert_eq!(mesh.vertices()[5], vec3(1.0, 1.0, 0.0));
+        let uvs = mesh.uvs().unwrap();
+        assert_eq!((uvs[0].x, uvs[0].y), (0.5, 0.0));
+        assert_eq!((uvs[2].x, uvs[2].y), (0.25, 1.0));
+        assert_eq!(mesh.normals().unwrap()[4], vec3(0.0, -1.0, 1.0));
+        let tri_mesh = mesh.triangulate();
+        assert_eq!(tri_mesh.uvs().unwrap().len(), 7);
+        assert_eq!(tri_mesh.normals().unwrap().len(), 7);
+
+        // dropped unless every corner has them
+        let mesh = Parser::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2 3").unwrap();
+        assert!(mesh.uvs().is_none());
+        assert_eq!(mesh.faces(), &[vec![0, 1, 2]]);
+    }
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            Parser::parse("v 0 0"),
+            Err(ParserError::InvalidVertexValue)
+        ));
+        assert!(matches!(
+            Parser::parse("v 0 0 0\nf 1 2 3"),
+            Err(ParserError::InvalidFaceValue)
+        ));
+        assert!(matches!(
+            Parser::parse("v 0 0 0\nf 1/1 1/1 1/1"),
+            Err(ParserError::InvalidFaceValue)
+        ));
+        assert!(matches!(
+            Parser::parse("vt"),
+            Err(ParserError::InvalidTextureValue)
+        ));
+        assert!(matches!(
+            Parser::parse("vt 0 a"),
+            Err(ParserError::InvalidTextureValue)
+        ));
     }
 }
@@ -0,0 +1,562 @@
+//! procedural meshes with normals and uvs.
+//!
+//! all of them are centered at the origin with +Y up, and wound counter-clockwise seen from outside.
+//! the surfaces of revolution (sphere, cylinder, cone, torus and capsule) go around the Y axis
+//! with u following the angle and v going from bottom to top, like `Sphere`'s uv mapping.
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::{
+    geometry::TriMesh,
+    linear_algebra::{
+        vector::{cross, vec2, vec3, Vector2},
+        Onb, Vector3,
+    },
+};
+
+/// a point of the profile curve revolved by `lathe`
+#[derive(Clone, Copy)]
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    /// normal of the curve in the (radius, y) plane
+    normal: Vector2,
+    v: f32,
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    uvs: Vec<Vector2>,
+    indices: Vec<usize>,
+}
+
+impl Builder {
+    fn vertex(&mut self, position: Vector3, normal: Vector3, uv: Vector2) -> usize {
+        self.vertices.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        self.vertices.len() - 1
+    }
+    /// adds the triangle unless two of its corners share a position, like the ones at the poles of a sphere
+    fn triangle(&mut self, a: usize, b: usize, c: usize) {
+        let (pa, pb, pc) = (self.vertices[a], self.vertices[b], self.vertices[c]);
+        if pa == pb || pb == pc || pc == pa {
+            return;
+        }
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+    /// triangulates a (columns + 1) * (rows + 1) grid of vertices starting at `base`,
+    /// wound so the triangles face along cross(du, dv)
+    fn grid_triangles(&mut self, base: usize, columns: usize, rows: usize) {
+        for j in 0..rows {
+            for i in 0..columns {
+                let a = base + j * (columns + 1) + i;
+                let (b, c, d) = (a + 1, a + columns + 2, a + columns + 1);
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+    /// a parallelogram from corner `q` along the edges `u` and `v`, facing cross(u, v)
+    fn parallelogram(&mut self, q: Vector3, u: Vector3, v: Vector3, columns: usize, rows: usize) {
+        let normal = cross(u, v).normalize();
+        let base = self.vertices.len();
+        for j in 0..=rows {
+            for i in 0..=columns {
+                let (s, t) = (i as f32 / columns as f32, j as f32 / rows as f32);
+                self.vertex(q + u * s + v * t, normal, vec2(s, t));
+            }
+        }
+        self.grid_triangles(base, columns, rows);
+    }
+    /// revolve the profile around the Y axis
+    fn lathe(&mut self, profile: &[ProfilePoint], segments: usize) {
+        let base = self.vertices.len();
+        for point in profile {
+            for i in 0..=segments {
+                let u = i as f32 / segments as f32;
+                let (sin, cos) = if i == segments {
+                    // close the seam exactly
+                    (0.0, 1.0)
+                } else {
+                    (u * 2.0 * PI).sin_cos()
+                };
+                let position = vec3(point.radius * cos, point.y, -point.radius * sin);
+                let normal = vec3(point.normal.x * cos, point.normal.y, -point.normal.x * sin);
+                self.vertex(position, normal, vec2(u, point.v));
+            }
+        }
+        self.grid_triangles(base, segments, profile.len() - 1);
+    }
+    /// a flat disk facing `normal` with polar uvs like `Circle`'s: u goes around the center,
+    /// v from the center to the edge
+    fn disk(&mut self, center: Vector3, normal: Vector3, radius: f32, segments: usize) {
+        let normal = normal.normalize();
+        let onb = Onb::from_w(normal);
+        let first = self.vertices.len();
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let (sin, cos) = if i == segments {
+                // close the seam exactly
+                (0.0, 1.0)
+            } else {
+                (u * 2.0 * PI).sin_cos()
+            };
+            self.vertex(
+                center + (onb.u * cos + onb.v * sin) * radius,
+                normal,
+                vec2(u, 1.0),
+            );
+        }
+        // the u of the center depends on the segment, like a pole of a sphere
+        for i in 0..segments {
+            let u = (i as f32 + 0.5) / segments as f32;
+            let c = self.vertex(center, normal, vec2(u, 0.0));
+            self.triangle(c, first + i, first + i + 1);
+        }
+    }
+    /// a flat disk facing `normal` mapped to the circle inscribed in the uv square, like the
+    /// caps of `Cylinder`
+    fn cap(&mut self, center: Vector3, normal: Vector3, radius: f32, segments: usize) {
+        let normal = normal.normalize();
+        let onb = Onb::from_w(normal);
+        let c = self.vertex(center, normal, vec2(0.5, 0.5));
+        let first = self.vertices.len();
+        for i in 0..segments {
+            let (sin, cos) = (i as f32 / segments as f32 * 2.0 * PI).sin_cos();
+            let position = center + (onb.u * cos + onb.v * sin) * radius;
+            self.vertex(position, normal, vec2(0.5 + 0.5 * cos, 0.5 + 0.5 * sin));
+        }
+        for i in 0..segments {
+            self.triangle(c, first + i, first + (i + 1) % segments);
+        }
+    }
+    fn build(self) -> TriMesh {
+        TriMesh::new(self.vertices, self.indices)
+            .with_normals(self.normals)
+            .with_uvs(self.uvs)
+    }
+}
+
+/// profile of a sphere of `radius` going from the south pole (angle 0) to the north pole (angle PI)
+fn arc(radius: f32, from: f32, to: f32, steps: usize, y: f32, v: (f32, f32)) -> Vec<ProfilePoint> {
+    (0..=steps)
+        .map(|j| {
+            let t = j as f32 / steps as f32;
+            let theta = from + (to - from) * t;
+            let (sin, cos) = theta.sin_cos();
+            // exact poles
+            let sin = if theta == 0.0 || theta == PI {
+                0.0
+            } else {
+                sin
+            };
+            ProfilePoint {
+                radius: radius * sin,
+                y: y - radius * cos,
+                normal: vec2(sin, -cos),
+                v: v.0 + (v.1 - v.0) * t,
+            }
+        })
+        .collect()
+}
+
+/// a latitude-longitude sphere
+pub fn uv_sphere(radius: f32, segments: usize, rings: usize) -> TriMesh {
+    let mut builder = Builder::default();
+    builder.lathe(&arc(radius, 0.0, PI, rings, 0.0, (0.0, 1.0)), segments);
+    builder.build()
+}
+
+/// a sphere made by subdividing an icosahedron, each level splitting every triangle into 4.
+///
+/// the vertices on the uv seam and at the poles are duplicated so the uvs don't wrap around
+/// inside a triangle, the u of the duplicates may go over 1.
+pub fn icosphere(radius: f32, subdivisions: u32) -> TriMesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut directions: Vec<Vector3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|v| Vector3::from(v).normalize())
+    .collect();
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::<(usize, usize), usize>::new();
+        let mut midpoint = |a: usize, b: usize, directions: &mut Vec<Vector3>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                directions.push(((directions[a] + directions[b]) * 0.5).normalize());
+                directions.len() - 1
+            })
+        };
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let ab = midpoint(a, b, &mut directions);
+                let bc = midpoint(b, c, &mut directions);
+                let ca = midpoint(c, a, &mut directions);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let uv = |d: Vector3| {
+        let u = (-d.z).atan2(d.x) / (2.0 * PI);
+        vec2(if u < 0.0 { u + 1.0 } else { u }, (-d.y).acos() / PI)
+    };
+    let mut builder = Builder::default();
+    for d in &directions {
+        builder.vertex(*d * radius, *d, uv(*d));
+    }
+    for face in faces {
+        let mut corners = face;
+        let us = corners.map(|i| builder.uvs[i].x);
+        // a triangle crossing the seam
+        if us.iter().cloned().fold(f32::MIN, f32::max) - us.iter().cloned().fold(f32::MAX, f32::min)
+            > 0.5
+        {
+            for c in &mut corners {
+                if builder.uvs[*c].x < 0.5 {
+                    let uv = builder.uvs[*c] + vec2(1.0, 0.0);
+                    *c = builder.vertex(builder.vertices[*c], builder.normals[*c], uv);
+                }
+            }
+        }
+        // the u of a pole is the average of the other corners
+        for k in 0..3 {
+            let d = builder.normals[corners[k]];
+            if d.x == 0.0 && d.z == 0.0 {
+                let u = (builder.uvs[corners[(k + 1) % 3]].x + builder.uvs[corners[(k + 2) % 3]].x)
+                    / 2.0;
+                let uv = vec2(u, builder.uvs[corners[k]].y);
+                corners[k] = builder.vertex(builder.vertices[corners[k]], d, uv);
+            }
+        }
+        builder.triangle(corners[0], corners[1], corners[2]);
+    }
+    builder.build()
+}
+
+/// an axis-aligned box from `min` to `max`, each face mapped to the whole uv square
+pub fn cuboid(min: Vector3, max: Vector3) -> TriMesh {
+    let size = max - min;
+    let (x, y, z) = (
+        vec3(size.x, 0.0, 0.0),
+        vec3(0.0, size.y, 0.0),
+        vec3(0.0, 0.0, size.z),
+    );
+    let mut builder = Builder::default();
+    // corner, u edge and v edge of each face, with u x v pointing outward
+    builder.parallelogram(min + x, -x, y, 1, 1); // -Z
+    builder.parallelogram(min + z, x, y, 1, 1); // +Z
+    builder.parallelogram(min, z, y, 1, 1); // -X
+    builder.parallelogram(min + x + z, -z, y, 1, 1); // +X
+    builder.parallelogram(min, x, z, 1, 1); // -Y
+    builder.parallelogram(min + y + z, x, -z, 1, 1); // +Y
+    builder.build()
+}
+
+/// a cube with edges of length `size`
+pub fn cube(size: f32) -> TriMesh {
+    let half = vec3(size, size, size) * 0.5;
+    cuboid(-half, half)
+}
+
+/// a grid on the XZ plane facing +Y, u along +X and v along -Z
+pub fn plane(width: f32, depth: f32, x_segments: usize, z_segments: usize) -> TriMesh {
+    parallelogram(
+        vec3(-width / 2.0, 0.0, depth / 2.0),
+        vec3(width, 0.0, 0.0),
+        vec3(0.0, 0.0, -depth),
+        x_segments,
+        z_segments,
+    )
+}
+
+/// a grid on the parallelogram from corner `q` along the edges `u` and `v`, facing cross(u, v)
+pub fn parallelogram(
+    q: Vector3,
+    u: Vector3,
+    v: Vector3,
+    u_segments: usize,
+    v_segments: usize,
+) -> TriMesh {
+    let mut builder = Builder::default();
+    builder.parallelogram(q, u, v, u_segments, v_segments);
+    builder.build()
+}
+
+/// a flat disk facing `normal`, u going around the center and v from the center to the edge
+pub fn disk(center: Vector3, normal: Vector3, radius: f32, segments: usize) -> TriMesh {
+    let mut builder = Builder::default();
+    builder.disk(center, normal, radius, segments);
+    builder.build()
+}
+
+/// a cylinder along the Y axis, with or without the caps
+pub fn cylinder(radius: f32, height: f32, segments: usize, capped: bool) -> TriMesh {
+    let h = height / 2.0;
+    let side = |y, v| ProfilePoint {
+        radius,
+        y,
+        normal: vec2(1.0, 0.0),
+        v,
+    };
+    let mut builder = Builder::default();
+    builder.lathe(&[side(-h, 0.0), side(h, 1.0)], segments);
+    if capped {
+        builder.cap(vec3(0.0, -h, 0.0), -Vector3::UNIT_Y, radius, segments);
+        builder.cap(vec3(0.0, h, 0.0), Vector3::UNIT_Y, radius, segments);
+    }
+    builder.build()
+}
+
+/// a cone along the Y axis, base at the bottom and apex at the top
+pub fn cone(radius: f32, height: f32, segments: usize) -> TriMesh {
+    let h = height / 2.0;
+    let normal = vec2(height, radius).normalize();
+    let mut builder = Builder::default();
+    builder.lathe(
+        &[
+            ProfilePoint {
+                radius,
+                y: -h,
+                normal,
+                v: 0.0,
+            },
+            ProfilePoint {
+                radius: 0.0,
+                y: h,
+                normal,
+                v: 1.0,
+            },
+        ],
+        segments,
+    );
+    builder.cap(vec3(0.0, -h, 0.0), -Vector3::UNIT_Y, radius, segments);
+    builder.build()
+}
+
+/// a torus around the Y axis, `major_radius` being the distance from the center to the middle of the tube
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: usize,
+    minor_segments: usize,
+) -> TriMesh {
+    let profile: Vec<ProfilePoint> = (0..=minor_segments)
+        .map(|j| {
+            let v = j as f32 / minor_segments as f32;
+            let (sin, cos) = if j == minor_segments {
+                (0.0, 1.0)
+            } else {
+                (v * 2.0 * PI).sin_cos()
+            };
+            ProfilePoint {
+                radius: major_radius + minor_radius * cos,
+                y: minor_radius * sin,
+                normal: vec2(cos, sin),
+                v,
+            }
+        })
+        .collect();
+    let mut builder = Builder::default();
+    builder.lathe(&profile, major_segments);
+    builder.build()
+}
+
+/// a cylinder of `height` along the Y axis, capped with two hemispheres
+pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize) -> TriMesh {
+    let h = height / 2.0;
+    // v proportional to the length along the profile
+    let total = PI * radius + height;
+    let cap = PI * radius / 2.0 / total;
+    let mut profile = arc(radius, 0.0, PI / 2.0, rings, -h, (0.0, cap));
+    profile.extend(arc(radius, PI / 2.0, PI, rings, h, (1.0 - cap, 1.0)));
+    let mut builder = Builder::default();
+    builder.lathe(&profile, segments);
+    builder.build()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::{Circle, Quad, Sphere, Triangle},
+        linear_algebra::vector::dot,
+        ray::{Hitable, Ray},
+    };
+
+    /// valid indices, unit normals agreeing with the winding
+    fn check(mesh: &TriMesh) {
+        assert!(mesh.validate().is_ok());
+        let normals = mesh.normals().unwrap();
+        assert!(mesh.uvs().is_some());
+        for n in normals {
+            assert!((n.length() - 1.0).abs() < 1e-5);
+        }
+        for tri in mesh.indices().chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices()[i]);
+            let face_normal = cross(b - a, c - a);
+            assert!(face_normal.length() > 0.0);
+            let vertex_normals: Vector3 = tri.iter().map(|&i| normals[i]).sum();
+            assert!(dot(face_normal, vertex_normals) > 0.0);
+        }
+    }
+    /// all the normals point away from the center of a convex mesh
+    fn check_convex(mesh: &TriMesh) {
+        for (p, n) in mesh.vertices().iter().zip(mesh.normals().unwrap()) {
+            assert!(dot(*p, *n) > 0.0);
+        }
+    }
+    fn area(mesh: &TriMesh) -> f32 {
+        mesh.indices()
+            .chunks_exact(3)
+            .map(|tri| {
+                let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices()[i]);
+                cross(b - a, c - a).length() / 2.0
+            })
+            .sum()
+    }
+    fn mesh_hit(mesh: &TriMesh, ray: Ray) -> Option<f32> {
+        mesh.indices()
+            .chunks_exact(3)
+            .filter_map(|tri| {
+                let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices()[i]);
+                Triangle::new(a, b, c).hit(ray, 0.0..f32::INFINITY)
+            })
+            .map(|rec| rec.t)
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn spheres() {
+        let sphere = uv_sphere(2.0, 32, 16);
+        check(&sphere);
+        check_convex(&sphere);
+        assert_eq!(sphere.triangle_count(), 32 * 16 * 2 - 32 * 2);
+        assert!((area(&sphere) - 16.0 * PI).abs() < 0.5);
+
+        let ico = icosphere(2.0, 0);
+        check(&ico);
+        assert_eq!(ico.triangle_count(), 20);
+        let ico = icosphere(2.0, 3);
+        check(&ico);
+        check_convex(&ico);
+        assert_eq!(ico.triangle_count(), 20 * 64);
+        assert!((area(&ico) - 16.0 * PI).abs() < 0.5);
+        for uv in ico.uvs().unwrap() {
+            assert!((0.0..1.5).contains(&uv.x) && (0.0..=1.0).contains(&uv.y));
+        }
+    }
+    #[test]
+    fn boxes() {
+        let cube = cube(2.0);
+        check(&cube);
+        check_convex(&cube);
+        assert_eq!(cube.triangle_count(), 12);
+        assert_eq!(area(&cube), 24.0);
+        let plane = plane(2.0, 4.0, 4, 8);
+        check(&plane);
+        assert_eq!(plane.triangle_count(), 64);
+        assert_eq!(plane.normals().unwrap()[0], Vector3::UNIT_Y);
+        assert_eq!(area(&plane), 8.0);
+    }
+    #[test]
+    fn revolutions() {
+        let cylinder = cylinder(1.0, 2.0, 64, true);
+        check(&cylinder);
+        check_convex(&cylinder);
+        assert!((area(&cylinder) - 6.0 * PI).abs() < 0.1);
+        let open = super::cylinder(1.0, 2.0, 64, false);
+        assert_eq!(open.triangle_count(), 128);
+
+        let cone = cone(1.0, 2.0, 64);
+        check(&cone);
+        check_convex(&cone);
+        assert!((area(&cone) - PI * (1.0 + 5.0_f32.sqrt())).abs() < 0.1);
+
+        let torus = torus(2.0, 0.5, 64, 32);
+        check(&torus);
+        assert!((area(&torus) - 4.0 * PI * PI * 2.0 * 0.5).abs() < 0.2);
+
+        let capsule = capsule(1.0, 2.0, 64, 16);
+        check(&capsule);
+        check_convex(&capsule);
+        assert!((area(&capsule) - 8.0 * PI).abs() < 0.1);
+    }
+    #[test]
+    fn analytic_equivalents() {
+        let sphere = Sphere::new(vec3(1.0, 2.0, 3.0), 1.5);
+        let mesh = sphere.tessellate(64, 32);
+        check(&mesh);
+        for i in 0..10 {
+            let offset = vec3(0.1 * i as f32 - 0.5, 0.07 * i as f32 - 0.3, 0.0);
+            let ray = Ray::new(vec3(1.0, 2.0, -5.0) + offset, Vector3::UNIT_Z);
+            let analytic = sphere.hit(ray, 0.0..f32::INFINITY).unwrap().t;
+            let tessellated = mesh_hit(&mesh, ray).unwrap();
+            assert!((analytic - tessellated).abs() < 0.01);
+        }
+
+        let quad = Quad::new(
+            vec3(0.0, 0.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 1.0),
+        );
+        let mesh = quad.tessellate(3, 3);
+        check(&mesh);
+        let ray = Ray::new(vec3(1.2, 0.5, -3.0), Vector3::UNIT_Z);
+        let analytic = quad.hit(ray, 0.0..f32::INFINITY).unwrap().t;
+        assert!((mesh_hit(&mesh, ray).unwrap() - analytic).abs() < 1e-5);
+
+        // same uvs as the analytic disk
+        let circle = Circle::new(vec3(1.0, 2.0, 3.0), 2.0, vec3(1.0, 1.0, 0.0).normalize());
+        let mesh = circle.tessellate(16);
+        check(&mesh);
+        for (p, uv) in mesh.vertices().iter().zip(mesh.uvs().unwrap()) {
+            if uv.y == 0.0 || uv.x == 1.0 {
+                continue;
+            }
+            let target = vec3(1.0, 2.0, 3.0) + (*p - vec3(1.0, 2.0, 3.0)) * 0.5;
+            let origin = target + vec3(1.0, 0.0, 0.2);
+            let rec = circle
+                .hit(Ray::new(origin, target - origin), 0.0..f32::INFINITY)
+                .unwrap();
+            assert!((rec.u - uv.x).abs() < 1e-4, "{} != {}", rec.u, uv.x);
+            assert!((rec.v - 0.5).abs() < 1e-4);
+        }
+    }
+}
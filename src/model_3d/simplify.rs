@@ -0,0 +1,693 @@
+//! mesh decimation by iterative edge collapse with quadric error metrics
+//!
+//! Garland & Heckbert, Surface Simplification Using Quadric Error Metrics
+//! https://www.cs.cmu.edu/~garland/Papers/quadrics.pdf
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::{
+    geometry::TriMesh,
+    linear_algebra::{
+        vector::{cross, dot},
+        Vector3,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// stop when the mesh has no more than this many triangles
+    pub target_triangles: Option<usize>,
+    /// stop before a collapse would introduce more error than this.
+    /// the error is the quadric error, i.e. the sum of squared distances to the original planes
+    pub max_error: Option<f32>,
+    /// keep the vertices on open boundaries in place
+    pub preserve_boundary: bool,
+    /// keep the vertices split in the index buffer (same position, different vertex),
+    /// which is how uv seams and hard edges show up in a `TriMesh`
+    pub preserve_seams: bool,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            target_triangles: None,
+            max_error: None,
+            preserve_boundary: true,
+            preserve_seams: true,
+        }
+    }
+}
+
+/// simplify the mesh until one of the targets in `options` is reached or no edge can be collapsed anymore.
+///
+/// returns the simplified mesh and the largest error among the collapses performed.
+/// without any target the mesh is simplified as far as possible.
+pub fn simplify(mesh: &TriMesh, options: Options) -> (TriMesh, f32) {
+    let mut simplifier = Simplifier::new(mesh, &options);
+    let error = simplifier.run(&options);
+    (simplifier.into_mesh(mesh), error)
+}
+
+/// symmetric 4x4 matrix, upper triangle in row order
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric([f64; 10]);
+impl Quadric {
+    /// quadric of the plane ax+by+cz+d=0, (a, b, c) being normalized
+    fn from_plane(a: f64, b: f64, c: f64, d: f64) -> Self {
+        Self([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+    fn add(&self, rhs: &Self) -> Self {
+        let mut m = self.0;
+        for (a, b) in m.iter_mut().zip(rhs.0) {
+            *a += b;
+        }
+        Self(m)
+    }
+    fn scale(&self, s: f64) -> Self {
+        Self(self.0.map(|a| a * s))
+    }
+    /// vᵀQv
+    fn error(&self, v: [f64; 3]) -> f64 {
+        let m = &self.0;
+        let [x, y, z] = v;
+        m[0] * x * x
+            + 2.0 * m[1] * x * y
+            + 2.0 * m[2] * x * z
+            + 2.0 * m[3] * x
+            + m[4] * y * y
+            + 2.0 * m[5] * y * z
+            + 2.0 * m[6] * y
+            + m[7] * z * z
+            + 2.0 * m[8] * z
+            + m[9]
+    }
+    /// the point minimizing the error, `None` if the quadric is (nearly) singular
+    fn optimal_point(&self) -> Option<[f64; 3]> {
+        let m = &self.0;
+        let (a, b, c) = ([m[0], m[1], m[2]], [m[1], m[4], m[5]], [m[2], m[5], m[7]]);
+        let r = [-m[3], -m[6], -m[8]];
+        let det = a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0]);
+        if det.abs() < 1e-10 {
+            return None;
+        }
+        // Cramer's rule
+        let det_x = r[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (r[1] * c[2] - b[2] * r[2])
+            + a[2] * (r[1] * c[1] - b[1] * r[2]);
+        let det_y = a[0] * (r[1] * c[2] - b[2] * r[2]) - r[0] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * r[2] - r[1] * c[0]);
+        let det_z = a[0] * (b[1] * r[2] - r[1] * c[1]) - a[1] * (b[0] * r[2] - r[1] * c[0])
+            + r[0] * (b[0] * c[1] - b[1] * c[0]);
+        Some([det_x / det, det_y / det, det_z / det])
+    }
+}
+
+fn to_f64(v: Vector3) -> [f64; 3] {
+    [v.x as f64, v.y as f64, v.z as f64]
+}
+fn to_vector3(v: [f64; 3]) -> Vector3 {
+    Vector3::new(v[0] as f32, v[1] as f32, v[2] as f32)
+}
+
+/// a welded vertex: all the vertices of the input sharing one position
+struct Node {
+    position: Vector3,
+    quadric: Quadric,
+    faces: Vec<usize>,
+    locked: bool,
+    alive: bool,
+    /// bumped on every change so the stale heap entries can be skipped
+    version: u32,
+}
+
+struct Face {
+    nodes: [usize; 3],
+    /// the vertex index of the input mesh at each corner
+    corners: [usize; 3],
+    alive: bool,
+}
+
+struct Collapse {
+    cost: f64,
+    /// the node to remove
+    from: usize,
+    /// the node to keep, moved to `position`
+    to: usize,
+    position: Vector3,
+    versions: (u32, u32),
+}
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+impl Eq for Collapse {}
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Collapse {
+    // reversed so BinaryHeap pops the cheapest collapse first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct Simplifier {
+    nodes: Vec<Node>,
+    faces: Vec<Face>,
+    alive_faces: usize,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn new(mesh: &TriMesh, options: &Options) -> Self {
+        let vertices = mesh.vertices();
+        // weld the vertices by position
+        let mut node_of = Vec::with_capacity(vertices.len());
+        let mut vertex_counts = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut welded = HashMap::<[u32; 3], usize>::new();
+        for v in vertices {
+            let key = [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+            let node = *welded.entry(key).or_insert_with(|| {
+                nodes.push(Node {
+                    position: *v,
+                    quadric: Quadric::default(),
+                    faces: Vec::new(),
+                    locked: false,
+                    alive: true,
+                    version: 0,
+                });
+                vertex_counts.push(0);
+                nodes.len() - 1
+            });
+            vertex_counts[node] += 1;
+            node_of.push(node);
+        }
+
+        let mut faces = Vec::with_capacity(mesh.triangle_count());
+        // faces sharing each undirected edge
+        let mut edge_faces = HashMap::<(usize, usize), Vec<usize>>::new();
+        // the edges in order of appearance, so the result doesn't depend on the hash order
+        let mut edges = Vec::new();
+        for corners in mesh.indices().chunks_exact(3) {
+            let corners = [corners[0], corners[1], corners[2]];
+            let face_nodes = corners.map(|c| node_of[c]);
+            let [a, b, c] = face_nodes.map(|n| nodes[n].position);
+            let n = cross(b - a, c - a);
+            let area = n.length();
+            // drop the degenerated triangles, they would break the adjacency
+            if face_nodes[0] == face_nodes[1]
+                || face_nodes[1] == face_nodes[2]
+                || face_nodes[2] == face_nodes[0]
+                || area == 0.0
+            {
+                continue;
+            }
+            let n = n / area;
+            let plane = Quadric::from_plane(n.x as f64, n.y as f64, n.z as f64, -dot(n, a) as f64)
+                // weighted by area so that small triangles don't dominate
+                .scale(area as f64 * 0.5);
+            let f = faces.len();
+            for k in 0..3 {
+                let node = &mut nodes[face_nodes[k]];
+                node.quadric = node.quadric.add(&plane);
+                node.faces.push(f);
+                let (i, j) = (face_nodes[k], face_nodes[(k + 1) % 3]);
+                let adjacent = edge_faces.entry((i.min(j), i.max(j))).or_default();
+                if adjacent.is_empty() {
+                    edges.push((i.min(j), i.max(j)));
+                }
+                adjacent.push(f);
+            }
+            faces.push(Face {
+                nodes: face_nodes,
+                corners,
+                alive: true,
+            });
+        }
+
+        for &(i, j) in &edges {
+            let adjacent = &edge_faces[&(i, j)];
+            if adjacent.len() != 1 {
+                continue;
+            }
+            if options.preserve_boundary {
+                nodes[i].locked = true;
+                nodes[j].locked = true;
+                continue;
+            }
+            // a plane perpendicular to the face through the boundary edge
+            // to keep the boundary from shrinking
+            let face = &faces[adjacent[0]];
+            let [a, b, c] = face.nodes.map(|n| nodes[n].position);
+            let face_normal = cross(b - a, c - a).normalize();
+            let edge = nodes[j].position - nodes[i].position;
+            let n = cross(edge, face_normal).normalize_or_zero();
+            let plane = Quadric::from_plane(
+                n.x as f64,
+                n.y as f64,
+                n.z as f64,
+                -dot(n, nodes[i].position) as f64,
+            )
+            .scale(edge.length_squared() as f64);
+            nodes[i].quadric = nodes[i].quadric.add(&plane);
+            nodes[j].quadric = nodes[j].quadric.add(&plane);
+        }
+        if options.preserve_seams {
+            for (node, count) in nodes.iter_mut().zip(vertex_counts) {
+                if count > 1 {
+                    node.locked = true;
+                }
+            }
+        }
+
+        let mut simplifier = Self {
+            nodes,
+            alive_faces: faces.len(),
+            faces,
+            heap: BinaryHeap::new(),
+        };
+        for (i, j) in edges {
+            simplifier.push_edge(i, j);
+        }
+        simplifier
+    }
+
+    /// compute the cost of collapsing the edge and push it to the heap
+    fn push_edge(&mut self, i: usize, j: usize) {
+        let (a, b) = (&self.nodes[i], &self.nodes[j]);
+        let (from, to, position) = match (a.locked, b.locked) {
+            (true, true) => return,
+            (true, false) => (j, i, a.position),
+            (false, true) => (i, j, b.position),
+            (false, false) => {
+                let quadric = a.quadric.add(&b.quadric);
+                let position = quadric.optimal_point().map(to_vector3).unwrap_or_else(|| {
+                    // pick the best of the end points and the midpoint
+                    let mid = (a.position + b.position) * 0.5;
+                    [a.position, b.position, mid]
+                        .into_iter()
+                        .min_by(|p, q| {
+                            quadric
+                                .error(to_f64(*p))
+                                .total_cmp(&quadric.error(to_f64(*q)))
+                        })
+                        .unwrap()
+                });
+                (i, j, position)
+            }
+        };
+        let quadric = a.quadric.add(&b.quadric);
+        self.heap.push(Collapse {
+            cost: quadric.error(to_f64(position)).max(0.0),
+            from,
+            to,
+            position,
+            versions: (self.nodes[from].version, self.nodes[to].version),
+        });
+    }
+
+    fn run(&mut self, options: &Options) -> f32 {
+        let target = options.target_triangles.unwrap_or(0);
+        let max_error = options.max_error.map(|e| e as f64).unwrap_or(f64::INFINITY);
+        let mut error = 0.0f64;
+        while self.alive_faces > target {
+            let Some(collapse) = self.heap.pop() else {
+                break;
+            };
+            let (from, to) = (&self.nodes[collapse.from], &self.nodes[collapse.to]);
+            if !from.alive || !to.alive || (from.version, to.version) != collapse.versions {
+                continue;
+            }
+            if collapse.cost > max_error {
+                break;
+            }
+            if self.collapse(&collapse) {
+                error = error.max(collapse.cost);
+            }
+        }
+        error as f32
+    }
+
+    /// the one-ring of the node, sorted
+    fn neighbors(&self, node: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.nodes[node]
+            .faces
+            .iter()
+            .flat_map(|&f| self.faces[f].nodes)
+            .filter(|&n| n != node)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    fn is_boundary(&self, node: usize) -> bool {
+        self.neighbors(node).into_iter().any(|n| {
+            self.nodes[node]
+                .faces
+                .iter()
+                .filter(|&&f| self.faces[f].nodes.contains(&n))
+                .count()
+                == 1
+        })
+    }
+
+    /// returns false if the collapse would break the topology or flip a face
+    fn collapse(&mut self, collapse: &Collapse) -> bool {
+        let (from, to) = (collapse.from, collapse.to);
+        let shared: Vec<usize> = self.nodes[from]
+            .faces
+            .iter()
+            .copied()
+            .filter(|&f| self.faces[f].nodes.contains(&to))
+            .collect();
+        // link condition, the edge's end points may only share the neighbours of the faces on the edge
+        let to_neighbors = self.neighbors(to);
+        let common = self
+            .neighbors(from)
+            .iter()
+            .filter(|n| to_neighbors.binary_search(n).is_ok())
+            .count();
+        if common != shared.len() {
+            return false;
+        }
+        // an interior edge between two boundary vertices would pinch the surface
+        if shared.len() > 1 && self.is_boundary(from) && self.is_boundary(to) {
+            return false;
+        }
+        // don't let a collapse remove the last faces of a piece
+        let remaining =
+            self.nodes[from].faces.len() + self.nodes[to].faces.len() - 2 * shared.len();
+        if remaining == 0 {
+            return false;
+        }
+        for &node in &[from, to] {
+            for &f in &self.nodes[node].faces {
+                if shared.contains(&f) {
+                    continue;
+                }
+                let positions = self.faces[f].nodes.map(|n| self.nodes[n].position);
+                let moved = self.faces[f].nodes.map(|n| {
+                    if n == node {
+                        collapse.position
+                    } else {
+                        self.nodes[n].position
+                    }
+                });
+                let before = cross(positions[1] - positions[0], positions[2] - positions[0]);
+                let after = cross(moved[1] - moved[0], moved[2] - moved[0]);
+                if dot(before, after) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+
+        // map the input vertices of `from` to the vertices of `to` on the same side of a seam
+        let mut corner_map = HashMap::new();
+        let mut fallback = None;
+        for &f in &shared {
+            let face = &self.faces[f];
+            let from_corner = face.corners[face.nodes.iter().position(|&n| n == from).unwrap()];
+            let to_corner = face.corners[face.nodes.iter().position(|&n| n == to).unwrap()];
+            corner_map.entry(from_corner).or_insert(to_corner);
+            fallback.get_or_insert(to_corner);
+        }
+        for &f in &shared {
+            self.faces[f].alive = false;
+            for n in self.faces[f].nodes {
+                self.nodes[n].faces.retain(|&g| g != f);
+            }
+            self.alive_faces -= 1;
+        }
+        let from_faces = std::mem::take(&mut self.nodes[from].faces);
+        for &f in &from_faces {
+            let face = &mut self.faces[f];
+            for k in 0..3 {
+                if face.nodes[k] == from {
+                    face.nodes[k] = to;
+                    face.corners[k] = corner_map
+                        .get(&face.corners[k])
+                        .or(fallback.as_ref())
+                        .copied()
+                        .unwrap_or(face.corners[k]);
+                }
+            }
+        }
+        let quadric = self.nodes[from].quadric;
+        self.nodes[from].alive = false;
+        let node = &mut self.nodes[to];
+        node.faces.extend(from_faces);
+        node.position = collapse.position;
+        node.quadric = node.quadric.add(&quadric);
+        node.version += 1;
+        for n in self.neighbors(to) {
+            self.push_edge(to, n);
+        }
+        true
+    }
+
+    fn into_mesh(self, mesh: &TriMesh) -> TriMesh {
+        let input_count = mesh.vertices().len();
+        let mut remap = vec![usize::MAX; input_count];
+        let mut vertices = Vec::new();
+        // the input vertex each output vertex comes from
+        let mut kept = Vec::new();
+        let mut indices = Vec::with_capacity(self.alive_faces * 3);
+        let mut node_of_corner = vec![usize::MAX; input_count];
+        for face in self.faces.iter().filter(|f| f.alive) {
+            for k in 0..3 {
+                node_of_corner[face.corners[k]] = face.nodes[k];
+            }
+        }
+        for face in self.faces.iter().filter(|f| f.alive) {
+            for corner in face.corners {
+                if remap[corner] == usize::MAX {
+                    remap[corner] = vertices.len();
+                    vertices.push(self.nodes[node_of_corner[corner]].position);
+                    kept.push(corner);
+                }
+                indices.push(remap[corner]);
+            }
+        }
+        // the normals and uvs are those of the kept vertices, not updated when they moved
+        let normals = mesh.normals().map(|n| kept.iter().map(|&v| n[v]).collect());
+        let uvs = mesh.uvs().map(|uv| kept.iter().map(|&v| uv[v]).collect());
+        TriMesh::new(vertices, indices)
+            .with_normals(normals.unwrap_or_default())
+            .with_uvs(uvs.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aabb::Aabb, linear_algebra::vector::vec3, model_3d::primitives};
+
+    /// a flat n*n quad grid on the xy plane, from (0, 0) to (1, 1)
+    fn grid(n: usize) -> TriMesh {
+        let mut vertices = Vec::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                vertices.push(vec3(i as f32 / n as f32, j as f32 / n as f32, 0.0));
+            }
+        }
+        let mut indices = Vec::new();
+        for j in 0..n {
+            for i in 0..n {
+                let a = j * (n + 1) + i;
+                let (b, c, d) = (a + 1, a + n + 2, a + n + 1);
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+        TriMesh::new(vertices, indices)
+    }
+    /// a closed box made of 6 grids
+    fn cube(n: usize) -> TriMesh {
+        let face = grid(n);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let sides: [fn(Vector3) -> Vector3; 6] = [
+            |p| vec3(p.x, p.y, 1.0),
+            |p| vec3(p.y, p.x, 0.0),
+            |p| vec3(1.0, p.x, p.y),
+            |p| vec3(0.0, p.y, p.x),
+            |p| vec3(p.y, 1.0, p.x),
+            |p| vec3(p.x, 0.0, p.y),
+        ];
+        // weld the shared edges so the box is closed
+        let mut welded = HashMap::new();
+        for side in sides {
+            let remap: Vec<usize> = face
+                .vertices()
+                .iter()
+                .map(|&p| {
+                    let p = side(p);
+                    let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+                    *welded.entry(key).or_insert_with(|| {
+                        vertices.push(p);
+                        vertices.len() - 1
+                    })
+                })
+                .collect();
+            indices.extend(face.indices().iter().map(|&i| remap[i]));
+        }
+        TriMesh::new(vertices, indices)
+    }
+
+    #[test]
+    fn flat_grid() {
+        let options = Options {
+            max_error: Some(1e-6),
+            preserve_boundary: false,
+            ..Default::default()
+        };
+        let (mesh, error) = simplify(&grid(8), options);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.vertices().len(), 4);
+        assert!(error < 1e-6);
+        let bounds = Aabb::from_points(mesh.vertices());
+        assert!(bounds.min.distance_to(&vec3(0.0, 0.0, 0.0)) < 1e-4);
+        assert!(bounds.max.distance_to(&vec3(1.0, 1.0, 0.0)) < 1e-4);
+    }
+    #[test]
+    fn preserve_boundary() {
+        let input = grid(6);
+        let (mesh, _) = simplify(&input, Options::default());
+        // the 24 boundary vertices are still in place,
+        // a last interior vertex may be left as there's no valid collapse for it on a flat square
+        assert!(mesh.vertices().len() <= 25);
+        for v in input.vertices() {
+            if v.x == 0.0 || v.x == 1.0 || v.y == 0.0 || v.y == 1.0 {
+                assert!(mesh.vertices().contains(v));
+            }
+        }
+    }
+    #[test]
+    fn deterministic() {
+        // an open bumpy grid, simplified to the same mesh every time
+        let input = grid(8);
+        let vertices = input
+            .vertices()
+            .iter()
+            .map(|p| vec3(p.x, p.y, (p.x * 5.0).sin() * (p.y * 3.0).cos() * 0.2))
+            .collect();
+        let input = TriMesh::new(vertices, input.indices().to_vec());
+        let options = || Options {
+            target_triangles: Some(30),
+            preserve_boundary: false,
+            ..Default::default()
+        };
+        let (first, error) = simplify(&input, options());
+        for _ in 0..10 {
+            let (mesh, again) = simplify(&input, options());
+            assert_eq!(mesh.vertices(), first.vertices());
+            assert_eq!(mesh.indices(), first.indices());
+            assert_eq!(again, error);
+        }
+    }
+    #[test]
+    fn target_count() {
+        let input = cube(4);
+        assert_eq!(input.triangle_count(), 192);
+        let options = Options {
+            target_triangles: Some(50),
+            ..Default::default()
+        };
+        let (mesh, error) = simplify(&input, options);
+        assert!(mesh.triangle_count() <= 50);
+        assert!(mesh.validate().is_ok());
+        // the flat sides collapse without any error
+        assert!(error < 1e-6);
+        // the box's corners are kept
+        assert!(mesh.vertices().contains(&vec3(1.0, 1.0, 1.0)));
+        assert!(mesh.vertices().contains(&vec3(0.0, 0.0, 0.0)));
+    }
+    #[test]
+    fn error_threshold() {
+        let input = cube(4);
+        let options = Options {
+            max_error: Some(0.0),
+            ..Default::default()
+        };
+        let (mesh, error) = simplify(&input, options);
+        assert_eq!(error, 0.0);
+        // can't go lower than a box without cutting its corners
+        assert_eq!(mesh.vertices().len(), 8);
+        assert_eq!(mesh.triangle_count(), 12);
+    }
+    #[test]
+    fn preserve_seams() {
+        // split the grid along x = 0.5, as a uv seam would
+        let input = grid(4);
+        let mut vertices = input.vertices().to_vec();
+        let mut indices = input.indices().to_vec();
+        let mut split = HashMap::new();
+        for tri in indices.chunks_exact_mut(3) {
+            let right = tri.iter().all(|&i| input.vertices()[i].x >= 0.5);
+            for i in tri
+                .iter_mut()
+                .filter(|i| right && input.vertices()[**i].x == 0.5)
+            {
+                *i = *split.entry(*i).or_insert_with(|| {
+                    vertices.push(input.vertices()[*i]);
+                    vertices.len() - 1
+                });
+            }
+        }
+        let input = TriMesh::new(vertices, indices);
+        let seam_count = |mesh: &TriMesh, j: usize| {
+            let seam_vertex = vec3(0.5, j as f32 / 4.0, 0.0);
+            mesh.vertices()
+                .iter()
+                .filter(|&&v| v == seam_vertex)
+                .count()
+        };
+        let (mesh, _) = simplify(&input, Options::default());
+        for j in 0..=4 {
+            assert_eq!(seam_count(&mesh, j), 2);
+        }
+        // welded, the seam is just an interior line of the flat grid
+        let options = Options {
+            preserve_seams: false,
+            ..Default::default()
+        };
+        let (mesh, _) = simplify(&input, options);
+        for j in 1..4 {
+            assert_eq!(seam_count(&mesh, j), 0);
+        }
+    }
+    #[test]
+    fn attributes() {
+        let input = primitives::plane(1.0, 1.0, 6, 6);
+        let (mesh, _) = simplify(&input, Options::default());
+        assert!(mesh.triangle_count() < input.triangle_count());
+        assert!(mesh.validate().is_ok());
+        assert!(mesh
+            .normals()
+            .unwrap()
+            .iter()
+            .all(|&n| n == Vector3::UNIT_Y));
+        // the corners stay in place with their uvs
+        for (p, uv) in mesh.vertices().iter().zip(mesh.uvs().unwrap()) {
+            if p.x.abs() == 0.5 && p.z.abs() == 0.5 {
+                assert_eq!((uv.x, uv.y), (p.x + 0.5, 0.5 - p.z));
+            }
+        }
+    }
+}
@@ -0,0 +1,533 @@
+//! Loop subdivision for triangle meshes and Catmull-Clark subdivision for polygon meshes,
+//! with boundary and (semi-)sharp crease rules from
+//! DeRose et al., Subdivision Surfaces in Character Animation
+use std::{
+    collections::HashMap,
+    iter::Sum,
+    ops::{Add, Mul},
+};
+
+use crate::{
+    geometry::{PolyMesh, TriMesh},
+    interpolate::lerp::lerp,
+    linear_algebra::{vector::Vector2, Vector3},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// boundary edges are treated as sharp creases, so the boundary curves get smoothed too
+    Smooth,
+    /// the boundary vertices stay in place
+    Fixed,
+}
+
+/// an edge kept sharp for `sharpness` levels, fractional values blend with the smooth result.
+/// use `f32::INFINITY` for an edge that stays sharp at every level
+#[derive(Clone, Copy, Debug)]
+pub struct Crease {
+    pub edge: (usize, usize),
+    pub sharpness: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// how many times the mesh is subdivided
+    pub levels: u32,
+    pub boundary: Boundary,
+    pub creases: Vec<Crease>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            levels: 1,
+            boundary: Boundary::Smooth,
+            creases: Vec::new(),
+        }
+    }
+}
+
+/// Loop subdivision, each level splits every triangle into 4.
+///
+/// the normals and uvs are interpolated linearly, they don't follow the smoothing of the positions.
+pub fn loop_subdivide(mesh: &TriMesh, options: &Options) -> TriMesh {
+    let faces = mesh
+        .indices()
+        .chunks_exact(3)
+        .map(|tri| tri.to_vec())
+        .collect();
+    let attributes = Attributes {
+        normals: mesh.normals().unwrap_or_default().to_vec(),
+        uvs: mesh.uvs().unwrap_or_default().to_vec(),
+    };
+    let (vertices, faces, attributes) = subdivide(
+        mesh.vertices().to_vec(),
+        faces,
+        attributes,
+        options,
+        Scheme::Loop,
+    );
+    TriMesh::new(vertices, faces.concat())
+        .with_normals(attributes.normals)
+        .with_uvs(attributes.uvs)
+}
+
+/// Catmull-Clark subdivision, each level splits every n-gon into n quads.
+///
+/// the normals and uvs are interpolated linearly, they don't follow the smoothing of the positions.
+pub fn catmull_clark(mesh: &PolyMesh, options: &Options) -> PolyMesh {
+    let attributes = Attributes {
+        normals: mesh.normals().unwrap_or_default().to_vec(),
+        uvs: mesh.uvs().unwrap_or_default().to_vec(),
+    };
+    let (vertices, faces, attributes) = subdivide(
+        mesh.vertices().to_vec(),
+        mesh.faces().to_vec(),
+        attributes,
+        options,
+        Scheme::CatmullClark,
+    );
+    PolyMesh::new(vertices, faces)
+        .with_normals(attributes.normals)
+        .with_uvs(attributes.uvs)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scheme {
+    Loop,
+    CatmullClark,
+}
+
+/// sharpness of the creased edges, keyed by `edge_key`
+type Creases = HashMap<(usize, usize), f32>;
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// per vertex values other than the positions, empty when the mesh has none
+struct Attributes {
+    normals: Vec<Vector3>,
+    uvs: Vec<Vector2>,
+}
+
+struct Edge {
+    a: usize,
+    b: usize,
+    faces: Vec<usize>,
+    sharpness: f32,
+}
+
+fn subdivide(
+    mut vertices: Vec<Vector3>,
+    mut faces: Vec<Vec<usize>>,
+    mut attributes: Attributes,
+    options: &Options,
+    scheme: Scheme,
+) -> (Vec<Vector3>, Vec<Vec<usize>>, Attributes) {
+    let mut creases: Creases = options
+        .creases
+        .iter()
+        .map(|c| (edge_key(c.edge.0, c.edge.1), c.sharpness))
+        .collect();
+    for _ in 0..options.levels {
+        let (new_vertices, new_faces, new_creases, edges) =
+            subdivide_once(&vertices, &faces, &creases, options, scheme);
+        attributes = Attributes {
+            normals: split(&attributes.normals, &edges, &faces, scheme)
+                .into_iter()
+                .map(|n| n.normalize_or_zero())
+                .collect(),
+            uvs: split(&attributes.uvs, &edges, &faces, scheme),
+        };
+        (vertices, faces, creases) = (new_vertices, new_faces, new_creases);
+    }
+    (vertices, faces, attributes)
+}
+
+/// per vertex values at the vertices of the subdivided mesh: the same at the old vertices,
+/// the average of the end points at the edge points and of the corners at the face points
+fn split<T>(values: &[T], edges: &[Edge], faces: &[Vec<usize>], scheme: Scheme) -> Vec<T>
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T> + Sum,
+{
+    if values.is_empty() {
+        return Vec::new();
+    }
+    let mut result = values.to_vec();
+    result.extend(edges.iter().map(|e| (values[e.a] + values[e.b]) * 0.5));
+    if scheme == Scheme::CatmullClark {
+        result.extend(
+            faces
+                .iter()
+                .map(|face| face.iter().map(|&v| values[v]).sum::<T>() * (1.0 / face.len() as f32)),
+        );
+    }
+    result
+}
+
+/// returns the new vertices, faces and creases, and the edges of the input in the order
+/// their edge points were added
+fn subdivide_once(
+    vertices: &[Vector3],
+    faces: &[Vec<usize>],
+    creases: &Creases,
+    options: &Options,
+    scheme: Scheme,
+) -> (Vec<Vector3>, Vec<Vec<usize>>, Creases, Vec<Edge>) {
+    // edges in order of appearance, so the result is deterministic
+    let mut edge_index = HashMap::<(usize, usize), usize>::new();
+    let mut edges: Vec<Edge> = Vec::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..face.len() {
+            let (a, b) = (face[k], face[(k + 1) % face.len()]);
+            let e = *edge_index.entry(edge_key(a, b)).or_insert_with(|| {
+                edges.push(Edge {
+                    a,
+                    b,
+                    faces: Vec::new(),
+                    sharpness: creases.get(&edge_key(a, b)).copied().unwrap_or(0.0),
+                });
+                edges.len() - 1
+            });
+            edges[e].faces.push(f);
+        }
+    }
+    let mut on_boundary = vec![false; vertices.len()];
+    for edge in &mut edges {
+        // boundary and non-manifold edges
+        if edge.faces.len() != 2 {
+            edge.sharpness = f32::INFINITY;
+            on_boundary[edge.a] = true;
+            on_boundary[edge.b] = true;
+        }
+    }
+
+    let face_points: Vec<Vector3> = faces
+        .iter()
+        .map(|face| face.iter().map(|&i| vertices[i]).sum::<Vector3>() / face.len() as f32)
+        .collect();
+
+    let edge_points: Vec<Vector3> = edges
+        .iter()
+        .map(|edge| {
+            let (a, b) = (vertices[edge.a], vertices[edge.b]);
+            let sharp = (a + b) * 0.5;
+            if edge.sharpness >= 1.0 {
+                return sharp;
+            }
+            let smooth = match scheme {
+                Scheme::Loop => {
+                    let opposite: Vector3 = edge
+                        .faces
+                        .iter()
+                        .map(|&f| {
+                            let v = faces[f]
+                                .iter()
+                                .find(|&&v| v != edge.a && v != edge.b)
+                                .unwrap();
+                            vertices[*v]
+                        })
+                        .sum();
+                    (a + b) * 0.375 + opposite * 0.125
+                }
+                Scheme::CatmullClark => {
+                    (a + b + face_points[edge.faces[0]] + face_points[edge.faces[1]]) * 0.25
+                }
+            };
+            lerp(smooth, sharp, edge.sharpness)
+        })
+        .collect();
+
+    // the edges around each vertex
+    let mut vertex_edges = vec![Vec::new(); vertices.len()];
+    for (e, edge) in edges.iter().enumerate() {
+        vertex_edges[edge.a].push(e);
+        vertex_edges[edge.b].push(e);
+    }
+    let mut vertex_faces = vec![Vec::new(); vertices.len()];
+    for (f, face) in faces.iter().enumerate() {
+        for &v in face {
+            vertex_faces[v].push(f);
+        }
+    }
+    let other = |edge: &Edge, v: usize| if edge.a == v { edge.b } else { edge.a };
+
+    let mut new_vertices: Vec<Vector3> = (0..vertices.len())
+        .map(|v| {
+            let p = vertices[v];
+            let around = &vertex_edges[v];
+            // isolated vertex
+            if around.is_empty() {
+                return p;
+            }
+            if options.boundary == Boundary::Fixed && on_boundary[v] {
+                return p;
+            }
+            let sharp: Vec<&Edge> = around
+                .iter()
+                .map(|&e| &edges[e])
+                .filter(|e| e.sharpness > 0.0)
+                .collect();
+            // darts are smooth, corners are fixed
+            if sharp.len() > 2 {
+                return p;
+            }
+            let n = around.len() as f32;
+            let smooth = match scheme {
+                Scheme::Loop => {
+                    let beta = if around.len() == 3 {
+                        3.0 / 16.0
+                    } else {
+                        3.0 / (8.0 * n)
+                    };
+                    let neighbors: Vector3 =
+                        around.iter().map(|&e| vertices[other(&edges[e], v)]).sum();
+                    p * (1.0 - n * beta) + neighbors * beta
+                }
+                Scheme::CatmullClark => {
+                    let f = vertex_faces[v]
+                        .iter()
+                        .map(|&f| face_points[f])
+                        .sum::<Vector3>()
+                        / vertex_faces[v].len() as f32;
+                    let r = around
+                        .iter()
+                        .map(|&e| (vertices[edges[e].a] + vertices[edges[e].b]) * 0.5)
+                        .sum::<Vector3>()
+                        / n;
+                    (f + r * 2.0 + p * (n - 3.0)) / n
+                }
+            };
+            if sharp.len() < 2 {
+                return smooth;
+            }
+            let crease =
+                p * 0.75 + (vertices[other(sharp[0], v)] + vertices[other(sharp[1], v)]) * 0.125;
+            let sharpness = (sharp[0].sharpness + sharp[1].sharpness) * 0.5;
+            if sharpness >= 1.0 {
+                crease
+            } else {
+                lerp(smooth, crease, sharpness)
+            }
+        })
+        .collect();
+
+    let edge_offset = new_vertices.len();
+    new_vertices.extend(edge_points);
+    let face_offset = new_vertices.len();
+    if scheme == Scheme::CatmullClark {
+        new_vertices.extend(face_points);
+    }
+
+    let edge_point = |a: usize, b: usize| edge_offset + edge_index[&edge_key(a, b)];
+    let mut new_faces = Vec::new();
+    for (f, face) in faces.iter().enumerate() {
+        match scheme {
+            Scheme::Loop => {
+                let [a, b, c] = [face[0], face[1], face[2]];
+                let (ab, bc, ca) = (edge_point(a, b), edge_point(b, c), edge_point(c, a));
+                new_faces.push(vec![a, ab, ca]);
+                new_faces.push(vec![b, bc, ab]);
+                new_faces.push(vec![c, ca, bc]);
+                new_faces.push(vec![ab, bc, ca]);
+            }
+            Scheme::CatmullClark => {
+                let len = face.len();
+                for k in 0..len {
+                    let (prev, v, next) = (face[(k + len - 1) % len], face[k], face[(k + 1) % len]);
+                    new_faces.push(vec![
+                        v,
+                        edge_point(v, next),
+                        face_offset + f,
+                        edge_point(prev, v),
+                    ]);
+                }
+            }
+        }
+    }
+
+    // the creases carry on to the split edges one level softer
+    let mut new_creases = HashMap::new();
+    for (e, edge) in edges.iter().enumerate() {
+        if edge.faces.len() == 2 && edge.sharpness > 1.0 {
+            let mid = edge_offset + e;
+            new_creases.insert(edge_key(edge.a, mid), edge.sharpness - 1.0);
+            new_creases.insert(edge_key(mid, edge.b), edge.sharpness - 1.0);
+        }
+    }
+    (new_vertices, new_faces, new_creases, edges)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linear_algebra::vector::{vec2, vec3};
+
+    fn cube() -> PolyMesh {
+        let vertices = (0..8)
+            .map(|i| {
+                vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32) * 2.0
+                    - vec3(1.0, 1.0, 1.0)
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        PolyMesh::new(vertices, faces)
+    }
+    fn octahedron() -> TriMesh {
+        TriMesh::new(
+            vec![
+                vec3(1.0, 0.0, 0.0),
+                vec3(-1.0, 0.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+                vec3(0.0, -1.0, 0.0),
+                vec3(0.0, 0.0, 1.0),
+                vec3(0.0, 0.0, -1.0),
+            ],
+            vec![
+                0, 2, 4, 2, 1, 4, 1, 3, 4, 3, 0, 4, 2, 0, 5, 1, 2, 5, 3, 1, 5, 0, 3, 5,
+            ],
+        )
+    }
+
+    #[test]
+    fn loop_counts() {
+        let options = Options {
+            levels: 2,
+            ..Default::default()
+        };
+        let mesh = loop_subdivide(&octahedron(), &options);
+        assert_eq!(mesh.triangle_count(), 8 * 16);
+        // V - E + F stays 2
+        assert_eq!(mesh.vertices().len(), 66);
+        assert!(mesh.validate().is_ok());
+    }
+    #[test]
+    fn loop_smooths() {
+        let mesh = loop_subdivide(&octahedron(), &Options::default());
+        // the tips get pulled in more than the edges, making it rounder
+        let tip = mesh.vertices()[0].length();
+        let edge = mesh.vertices()[6].length();
+        assert!(tip < 1.0);
+        assert!(tip / edge < 2.0_f32.sqrt());
+    }
+    #[test]
+    fn catmull_clark_cube() {
+        let options = Options {
+            levels: 2,
+            ..Default::default()
+        };
+        let mesh = catmull_clark(&cube(), &options);
+        assert_eq!(mesh.faces().len(), 6 * 16);
+        assert!(mesh.faces().iter().all(|f| f.len() == 4));
+        assert!(mesh.validate().is_ok());
+        // symmetric and shrunk towards a sphere
+        let r = mesh.vertices()[0].length();
+        for i in 1..8 {
+            assert!((mesh.vertices()[i].length() - r).abs() < 1e-5);
+        }
+        assert!(r < 3.0_f32.sqrt());
+        // first level corner: (F + 2R + (n-3)P)/n with F = 1/3, R = 2/3 and P = 1 on each axis
+        let once = catmull_clark(&cube(), &Options::default());
+        assert!(once.vertices()[7].distance_to(&(vec3(5.0, 5.0, 5.0) / 9.0)) < 1e-6);
+    }
+    #[test]
+    fn sharp_creases() {
+        // all the edges of the cube sharp forever: it stays a cube
+        let cube = cube();
+        let mut creases = Vec::new();
+        for face in cube.faces() {
+            for k in 0..4 {
+                creases.push(Crease {
+                    edge: (face[k], face[(k + 1) % 4]),
+                    sharpness: f32::INFINITY,
+                });
+            }
+        }
+        let options = Options {
+            levels: 2,
+            creases,
+            ..Default::default()
+        };
+        let mesh = catmull_clark(&cube, &options);
+        for v in mesh.vertices() {
+            let max = v.x.abs().max(v.y.abs()).max(v.z.abs());
+            assert!((max - 1.0).abs() < 1e-6);
+        }
+    }
+    #[test]
+    fn boundaries() {
+        let grid = PolyMesh::new(
+            vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(2.0, 0.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+                vec3(1.0, 1.0, 1.0),
+                vec3(2.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]],
+        );
+        let smooth = catmull_clark(&grid, &Options::default());
+        // the corners have 2 boundary edges, so they follow the crease rule
+        assert!(smooth.vertices()[0].distance_to(&vec3(0.125, 0.125, 0.0)) < 1e-6);
+        // the boundary edge points are midpoints
+        assert_eq!(smooth.vertices()[6], vec3(0.5, 0.0, 0.0));
+        let fixed = catmull_clark(
+            &grid,
+            &Options {
+                boundary: Boundary::Fixed,
+                ..Default::default()
+            },
+        );
+        assert_eq!(&fixed.vertices()[..6], grid.vertices());
+    }
+    #[test]
+    fn attributes() {
+        // the normals pointing out of the octahedron's vertices, the uvs along x and y
+        let mesh = octahedron();
+        let normals = mesh.vertices().to_vec();
+        let uvs = mesh.vertices().iter().map(|p| vec2(p.x, p.y)).collect();
+        let mesh = mesh.with_normals(normals).with_uvs(uvs);
+        let subdivided = loop_subdivide(&mesh, &Options::default());
+        assert!(subdivided.validate().is_ok());
+        let (normals, uvs) = (subdivided.normals().unwrap(), subdivided.uvs().unwrap());
+        // the edge point between +x and +y
+        let edge = subdivided
+            .vertices()
+            .iter()
+            .position(|p| p.x > 0.0 && p.y > 0.0 && p.z == 0.0)
+            .unwrap();
+        assert!(normals[edge].distance_to(&vec3(1.0, 1.0, 0.0).normalize()) < 1e-6);
+        assert_eq!((uvs[edge].x, uvs[edge].y), (0.5, 0.5));
+
+        let quad = PolyMesh::new(
+            vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(1.0, 1.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 2, 3]],
+        )
+        .with_uvs(vec![
+            vec2(0.0, 0.0),
+            vec2(1.0, 0.0),
+            vec2(1.0, 1.0),
+            vec2(0.0, 1.0),
+        ]);
+        let subdivided = catmull_clark(&quad, &Options::default());
+        assert!(subdivided.validate().is_ok());
+        assert!(subdivided.normals().is_none());
+        let uvs = subdivided.uvs().unwrap();
+        // edge points then the face point
+        assert_eq!((uvs[4].x, uvs[4].y), (0.5, 0.0));
+        assert_eq!((uvs[8].x, uvs[8].y), (0.5, 0.5));
+    }
+}
@@ -0,0 +1,153 @@
+//! objects moving while the shutter is open, hit at the time carried by the ray
+use std::ops::{Add, Mul, Range};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    interpolate::lerp::lerp,
+    linear_algebra::Vector3,
+    ray::{HitRecord, Hitable, Ray},
+};
+
+/// values at given times, linearly interpolated in between and held before the first
+/// and after the last one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(
+    try_from = "Vec<(f32, T)>",
+    into = "Vec<(f32, T)>",
+    bound(serialize = "T: Clone + Serialize")
+)]
+pub struct Keyframes<T> {
+    frames: Vec<(f32, T)>,
+}
+/// sorts the frames by time, fails if there is none
+impl<T> TryFrom<Vec<(f32, T)>> for Keyframes<T> {
+    type Error = &'static str;
+    fn try_from(mut frames: Vec<(f32, T)>) -> Result<Self, Self::Error> {
+        if frames.is_empty() {
+            return Err("keyframes need at least one frame");
+        }
+        frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { frames })
+    }
+}
+impl<T> From<Keyframes<T>> for Vec<(f32, T)> {
+    fn from(keyframes: Keyframes<T>) -> Self {
+        keyframes.frames
+    }
+}
+impl<T> Keyframes<T>
+where
+    T: Copy + Add<T, Output = T> + Mul<f32, Output = T>,
+{
+    /// the frames are sorted by time, panics if there is none
+    pub fn new(frames: Vec<(f32, T)>) -> Self {
+        Self::try_from(frames).unwrap_or_else(|error| panic!("{error}"))
+    }
+    /// a value that doesn't change
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+    /// moving linearly from `from` at `time.start` to `to` at `time.end`
+    pub fn linear(from: T, to: T, time: Range<f32>) -> Self {
+        Self::new(vec![(time.start, from), (time.end, to)])
+    }
+    pub fn frames(&self) -> &[(f32, T)] {
+        &self.frames
+    }
+    /// the time span covered by the frames
+    pub fn interval(&self) -> Range<f32> {
+        self.frames[0].0..self.frames[self.frames.len() - 1].0
+    }
+    pub fn at(&self, time: f32) -> T {
+        let i = self.frames.partition_point(|(t, _)| *t <= time);
+        if i == 0 {
+            return self.frames[0].1;
+        }
+        if i == self.frames.len() {
+            return self.frames[i - 1].1;
+        }
+        let ((t0, a), (t1, b)) = (self.frames[i - 1], self.frames[i]);
+        lerp(a, b, (time - t0) / (t1 - t0))
+    }
+}
+
+/// an object translated by a keyframed offset
+pub struct Moving<T> {
+    pub object: T,
+    pub offset: Keyframes<Vector3>,
+}
+impl<T: Hitable> Moving<T> {
+    pub fn new(object: T, offset: Keyframes<Vector3>) -> Self {
+        Self { object, offset }
+    }
+}
+impl<T: Hitable> Hitable for Moving<T> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        let offset = self.offset.at(ray.time);
+        let local = Ray {
+            origin: ray.origin - offset,
+            ..ray
+        };
+        let mut record = self.object.hit(local, range)?;
+        record.point = record.point + offset;
+        Some(record)
+    }
+    /// union over the whole motion, the path is linear between the frames so the boxes at the
+    /// frames are enough
+    fn bounding_box(&self) -> Aabb {
+        let local = self.object.bounding_box();
+        let mut aabb = Aabb::empty();
+        for (_, offset) in self.offset.frames() {
+            aabb.expand_by_point(local.min + *offset);
+            aabb.expand_by_point(local.max + *offset);
+        }
+        aabb
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{geometry::Sphere, linear_algebra::vector::vec3};
+
+    #[test]
+    fn keyframes() {
+        let frames = Keyframes::new(vec![(1.0, 10.0), (0.0, 0.0), (2.0, 0.0)]);
+        assert_eq!(frames.interval(), 0.0..2.0);
+        assert_eq!(frames.at(-1.0), 0.0);
+        assert_eq!(frames.at(0.25), 2.5);
+        assert_eq!(frames.at(1.0), 10.0);
+        assert_eq!(frames.at(1.5), 5.0);
+        assert_eq!(frames.at(3.0), 0.0);
+        // deserialized frames are checked and sorted the same way
+        let json = serde_json::to_string(&frames).unwrap();
+        assert_eq!(json, "[[0.0,0.0],[1.0,10.0],[2.0,0.0]]");
+        let frames: Keyframes<f32> = serde_json::from_str("[[1.0, 10.0], [0.0, 0.0]]").unwrap();
+        assert_eq!(frames.interval(), 0.0..1.0);
+        assert_eq!(frames.at(0.5), 5.0);
+        let error = serde_json::from_str::<Keyframes<f32>>("[]").unwrap_err();
+        assert_eq!(error.to_string(), "keyframes need at least one frame");
+        assert_eq!(Keyframes::constant(4.0).at(100.0), 4.0);
+    }
+    #[test]
+    fn moving_sphere() {
+        let sphere = Moving::new(
+            Sphere::new(Vector3::ZERO, 1.0),
+            Keyframes::linear(Vector3::ZERO, vec3(0.0, 2.0, 0.0), 0.0..1.0),
+        );
+        let ray = Ray::new(vec3(0.0, 1.0, -5.0), Vector3::UNIT_Z);
+        // grazing at the start, through the center halfway
+        let rec = sphere.hit(ray.with_time(0.5), 0.0..f32::INFINITY).unwrap();
+        assert_eq!(rec.t, 4.0);
+        assert_eq!(rec.point, vec3(0.0, 1.0, -1.0));
+        assert_eq!(rec.normal, -Vector3::UNIT_Z);
+        assert!(sphere.hit(ray.with_time(0.0), 0.0..f32::INFINITY).is_none());
+        let rec = sphere.hit(ray.with_time(0.75), 0.0..f32::INFINITY).unwrap();
+        assert!(rec.t > 4.0);
+        let aabb = sphere.bounding_box();
+        assert_eq!(aabb.min, vec3(-1.0, -1.0, -1.0));
+        assert_eq!(aabb.max, vec3(1.0, 3.0, 1.0));
+    }
+}
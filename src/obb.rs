@@ -0,0 +1,279 @@
+//! oriented bounding boxes, fitted to points along their principal axes, with the separating
+//! axis test for overlaps
+//!
+//! Gottschalk et al., OBBTree: A Hierarchical Structure for Rapid Interference Detection
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    linear_algebra::{
+        vector::{cross, dot, vec3},
+        Onb, Vector3,
+    },
+    query::Overlaps,
+    ray::Ray,
+};
+
+/// an oriented bounding box, a box along the axes of `orientation`
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Obb {
+    pub center: Vector3,
+    /// half of the size along each axis of the orientation
+    pub half_extents: Vector3,
+    pub orientation: Onb,
+}
+impl Obb {
+    pub fn new(center: Vector3, half_extents: Vector3, orientation: Onb) -> Self {
+        Self {
+            center,
+            half_extents,
+            orientation,
+        }
+    }
+    /// the same box with the world axes
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Self::new(
+            aabb.center(),
+            aabb.size() * 0.5,
+            Onb {
+                u: Vector3::UNIT_X,
+                v: Vector3::UNIT_Y,
+                w: Vector3::UNIT_Z,
+            },
+        )
+    }
+    /// a box along the principal axes of the points, usually tighter than the aabb for
+    /// elongated shapes but not the smallest one
+    pub fn from_points(points: &[Vector3]) -> Self {
+        if points.is_empty() {
+            return Self::from_aabb(&Aabb::from_min_max(Vector3::ZERO, Vector3::ZERO));
+        }
+        let mean = points.iter().copied().sum::<Vector3>() / points.len() as f32;
+        let mut covariance = [[0.0; 3]; 3];
+        for p in points {
+            let d = *p - mean;
+            for (i, row) in covariance.iter_mut().enumerate() {
+                for (j, c) in row.iter_mut().enumerate() {
+                    *c += d[i] * d[j];
+                }
+            }
+        }
+        let axes = symmetric_eigenvectors(covariance);
+        let orientation = Onb {
+            u: axes[0],
+            v: axes[1],
+            w: cross(axes[0], axes[1]),
+        };
+        let local: Vec<Vector3> = points
+            .iter()
+            .map(|p| orientation.to_local(*p - mean))
+            .collect();
+        let bounds = Aabb::from_points(&local);
+        Self::new(
+            mean + orientation.to_world(bounds.center()),
+            bounds.size() * 0.5,
+            orientation,
+        )
+    }
+    /// from world coordinates to the box's, centered on it
+    pub fn to_local(&self, p: Vector3) -> Vector3 {
+        self.orientation.to_local(p - self.center)
+    }
+    pub fn contains_point(&self, p: Vector3) -> bool {
+        self.local_aabb().contains_point(self.to_local(p))
+    }
+    pub fn corners(&self) -> [Vector3; 8] {
+        let h = self.half_extents;
+        std::array::from_fn(|i| {
+            let x = if i & 1 == 0 { -h.x } else { h.x };
+            let y = if i & 2 == 0 { -h.y } else { h.y };
+            let z = if i & 4 == 0 { -h.z } else { h.z };
+            self.center + self.orientation.to_world(vec3(x, y, z))
+        })
+    }
+    /// the axis-aligned box containing this one
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&self.corners())
+    }
+    /// the `t` where the ray enters and exits the box clipped to the range, as
+    /// `Aabb::intersect_ray_interval`
+    pub fn intersect_ray_interval(&self, ray: Ray, range: Range<f32>) -> Option<(f32, f32)> {
+        // the basis is orthonormal, t is the same in both spaces
+        let local = Ray {
+            origin: self.to_local(ray.origin),
+            direction: self.orientation.to_local(ray.direction),
+            ..ray
+        };
+        self.local_aabb().intersect_ray_interval(local, range)
+    }
+    pub fn intersect_ray(&self, ray: Ray, range: Range<f32>) -> bool {
+        self.intersect_ray_interval(ray, range).is_some()
+    }
+    fn local_aabb(&self) -> Aabb {
+        Aabb::from_min_max(-self.half_extents, self.half_extents)
+    }
+    fn axes(&self) -> [Vector3; 3] {
+        [self.orientation.u, self.orientation.v, self.orientation.w]
+    }
+    /// half the length of the projection on a normalized axis
+    fn projected_radius(&self, axis: Vector3) -> f32 {
+        let h = self.half_extents;
+        let [u, v, w] = self.axes();
+        h.x * dot(u, axis).abs() + h.y * dot(v, axis).abs() + h.z * dot(w, axis).abs()
+    }
+}
+/// separating axis test over the face normals of both boxes and the cross products of
+/// their edges
+impl Overlaps<Obb> for Obb {
+    fn overlaps(&self, other: &Obb) -> bool {
+        let offset = other.center - self.center;
+        let (a, b) = (self.axes(), other.axes());
+        let edges = a.iter().flat_map(|&a| b.map(|b| cross(a, b)));
+        !a.into_iter().chain(b).chain(edges).any(|axis| {
+            // parallel edges give no axis, the face normals already cover that case
+            let length = axis.length();
+            if length < 1e-6 {
+                return false;
+            }
+            let axis = axis / length;
+            dot(offset, axis).abs() > self.projected_radius(axis) + other.projected_radius(axis)
+        })
+    }
+}
+
+/// eigenvectors of a symmetric matrix sorted by decreasing eigenvalue, by cyclic jacobi
+/// rotations
+fn symmetric_eigenvectors(mut a: [[f32; 3]; 3]) -> [Vector3; 3] {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..32 {
+        let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        if off < 1e-12 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < 1e-12 {
+                continue;
+            }
+            // the rotation zeroing a[p][q]
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            // a = Jᵀ a J and v = v J, on the columns then the rows
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (kp, kq) = (row[p], row[q]);
+                row[p] = c * kp - s * kq;
+                row[q] = s * kp + c * kq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for k in 0..3 {
+                a[p][k] = c * row_p[k] - s * row_q[k];
+                a[q][k] = s * row_p[k] + c * row_q[k];
+            }
+        }
+    }
+    let mut order = [0, 1, 2];
+    order.sort_by(|&i, &j| a[j][j].total_cmp(&a[i][i]));
+    order.map(|i| vec3(v[0][i], v[1][i], v[2][i]).normalize())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{linear_algebra::Matrix4, model_3d::primitives};
+    use std::f32::consts::FRAC_PI_4;
+
+    fn rotated(center: Vector3, half_extents: Vector3, angle: f32) -> Obb {
+        let (s, c) = angle.sin_cos();
+        let orientation = Onb {
+            u: vec3(c, s, 0.0),
+            v: vec3(-s, c, 0.0),
+            w: Vector3::UNIT_Z,
+        };
+        Obb::new(center, half_extents, orientation)
+    }
+
+    #[test]
+    fn ray() {
+        let obb = rotated(Vector3::ZERO, vec3(1.0, 1.0, 1.0), FRAC_PI_4);
+        // the corner is at x = -sqrt(2)
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X);
+        let (enter, exit) = obb.intersect_ray_interval(ray, 0.0..f32::INFINITY).unwrap();
+        assert!((enter - (5.0 - 2.0_f32.sqrt())).abs() < 1e-5);
+        assert!((exit - (5.0 + 2.0_f32.sqrt())).abs() < 1e-5);
+        // inside the aabb but past the rotated edge
+        let ray = Ray::new(vec3(1.0, 1.0, -5.0), Vector3::UNIT_Z);
+        assert!(obb.bounding_box().intersect_ray(ray, 0.0..f32::INFINITY));
+        assert!(!obb.intersect_ray(ray, 0.0..f32::INFINITY));
+        assert!(obb.contains_point(vec3(1.3, 0.0, 0.0)));
+        assert!(!obb.contains_point(vec3(1.0, 1.0, 0.0)));
+    }
+    #[test]
+    fn obb_overlaps() {
+        let a = rotated(Vector3::ZERO, vec3(1.0, 1.0, 1.0), FRAC_PI_4);
+        let aligned = |x: f32| {
+            Obb::from_aabb(&Aabb::from_center_size(
+                vec3(x, 0.0, 0.0),
+                vec3(2.0, 2.0, 2.0),
+            ))
+        };
+        assert!(a.overlaps(&aligned(2.3)));
+        assert!(!a.overlaps(&aligned(2.5)));
+        assert!(a.overlaps(&a));
+        // their aabbs overlap but not the boxes
+        let b = rotated(vec3(2.2, 2.2, 0.0), vec3(1.0, 1.0, 1.0), FRAC_PI_4);
+        assert!(a.bounding_box().intersection(&b.bounding_box()).volume() > 0.0);
+        assert!(!a.overlaps(&b) && !b.overlaps(&a));
+        // only an edge cross product separates these
+        let tilted = |center: Vector3| {
+            let m = Matrix4::rotation(vec3(1.0, 1.0, 0.0).normalize(), FRAC_PI_4);
+            let orientation = Onb {
+                u: m.transform_vector(Vector3::UNIT_X),
+                v: m.transform_vector(Vector3::UNIT_Y),
+                w: m.transform_vector(Vector3::UNIT_Z),
+            };
+            Obb::new(center, vec3(0.2, 0.2, 3.0), orientation)
+        };
+        let mut separated = 0;
+        for i in 0..40 {
+            let other = tilted(vec3(i as f32 * 0.1 - 2.0, 0.5, 0.3));
+            let overlaps = a.overlaps(&other);
+            // any corner inside the other box means overlapping
+            let inside = other.corners().iter().any(|c| a.contains_point(*c))
+                || a.corners().iter().any(|c| other.contains_point(*c));
+            if inside {
+                assert!(overlaps);
+            }
+            separated += !overlaps as usize;
+        }
+        assert!(separated > 0);
+    }
+    #[test]
+    fn from_points() {
+        // an elongated box rotated around z
+        let mesh = primitives::cuboid(vec3(-4.0, -1.0, -0.5), vec3(4.0, 1.0, 0.5));
+        let m = Matrix4::translation(vec3(1.0, 2.0, 3.0)) * Matrix4::rotation_z(0.5);
+        let points: Vec<Vector3> = mesh
+            .vertices()
+            .iter()
+            .map(|p| m.transform_point(*p))
+            .collect();
+        let obb = Obb::from_points(&points);
+        assert!((obb.center - vec3(1.0, 2.0, 3.0)).length() < 1e-4);
+        let mut extents = [obb.half_extents.x, obb.half_extents.y, obb.half_extents.z];
+        extents.sort_by(f32::total_cmp);
+        for (e, expected) in extents.iter().zip([0.5, 1.0, 4.0]) {
+            assert!((e - expected).abs() < 1e-3, "{extents:?}");
+        }
+        assert!(
+            (dot(obb.orientation.u, m.transform_vector(Vector3::UNIT_X)).abs() - 1.0).abs() < 1e-4
+        );
+        for p in &points {
+            assert!(obb.contains_point(*p + (obb.center - *p) * 1e-4));
+        }
+        let aabb = Aabb::from_points(&points);
+        assert!(obb.half_extents.x * obb.half_extents.y * obb.half_extents.z * 8.0 < aabb.volume());
+    }
+}
@@ -0,0 +1,178 @@
+//! real roots of polynomials up to degree 4, e.g. for ray-torus intersection
+//!
+//! Jochen Schwarze, Cubic and Quartic Roots, Graphics Gems I
+use std::f64::consts::PI;
+
+const EPSILON: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPSILON
+}
+
+/// real roots of ax² + bx + c, sorted in ascending order
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+    let p = b / (2.0 * a);
+    let q = c / a;
+    let d = p * p - q;
+    if is_zero(d) {
+        vec![-p]
+    } else if d < 0.0 {
+        vec![]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![-sqrt_d - p, sqrt_d - p]
+    }
+}
+
+/// real roots of ax³ + bx² + cx + d, sorted in ascending order
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+    let mut roots = depressed_cubic_roots(b / a, c / a, d / a);
+    sort(&mut roots);
+    roots
+}
+
+/// real roots of ax⁴ + bx³ + cx² + dx + e, sorted in ascending order
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (a3, a2, a1, a0) = (b / a, c / a, d / a, e / a);
+    // substitute x = y - a3/4 to eliminate the cubic term: y⁴ + py² + qy + r
+    let sq_a = a3 * a3;
+    let p = -3.0 / 8.0 * sq_a + a2;
+    let q = 1.0 / 8.0 * sq_a * a3 - 0.5 * a3 * a2 + a1;
+    let r = -3.0 / 256.0 * sq_a * sq_a + 1.0 / 16.0 * sq_a * a2 - 0.25 * a3 * a1 + a0;
+
+    let mut roots = if is_zero(r) {
+        // y(y³ + py + q) = 0
+        let mut roots = depressed_cubic_roots(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // solve the resolvent cubic, then the quartic splits into 2 quadratics
+        let z = depressed_cubic_roots(-0.5 * p, -r, 0.5 * r * p - 1.0 / 8.0 * q * q)[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if q < 0.0 { -v } else { v };
+        let mut roots = solve_quadratic(1.0, v, z - u);
+        roots.extend(solve_quadratic(1.0, -v, z + u));
+        roots
+    };
+    for root in &mut roots {
+        *root -= 0.25 * a3;
+        // polish with a few newton steps, the closed form loses precision
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((x + a3) * x + a2) * x + a1) * x + a0;
+            let df = ((4.0 * x + 3.0 * a3) * x + 2.0 * a2) * x + a1;
+            if df != 0.0 {
+                *root = x - f / df;
+            }
+        }
+    }
+    sort(&mut roots);
+    roots
+}
+
+/// roots of x³ + ax² + bx + c, unsorted
+fn depressed_cubic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // substitute x = y - a/3 to eliminate the quadratic term: y³ + 3py + 2q
+    let sq_a = a * a;
+    let p = 1.0 / 3.0 * (-1.0 / 3.0 * sq_a + b);
+    let q = 0.5 * (2.0 / 27.0 * a * sq_a - 1.0 / 3.0 * a * b + c);
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let mut roots = if is_zero(d) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        // three real roots
+        let phi = 1.0 / 3.0 * (-q / (-cb_p).sqrt()).acos();
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+    for root in &mut roots {
+        *root -= 1.0 / 3.0 * a;
+    }
+    roots
+}
+
+fn sort(roots: &mut [f64]) {
+    roots.sort_by(f64::total_cmp);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "{roots:?}");
+        for (r, e) in roots.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-6, "{roots:?}");
+        }
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, 2.0, 1.0), &[-1.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(0.0, 2.0, -1.0), &[0.5]);
+    }
+    #[test]
+    fn cubic() {
+        // (x-1)(x-2)(x+3)
+        assert_roots(solve_cubic(1.0, 0.0, -7.0, 6.0), &[-3.0, 1.0, 2.0]);
+        // (x-2)(x²+1)
+        assert_roots(solve_cubic(2.0, -4.0, 2.0, -4.0), &[2.0]);
+    }
+    #[test]
+    fn quartic() {
+        // (x-1)(x-2)(x-3)(x-4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x²-1)(x²+1)
+        assert_roots(solve_quartic(2.0, 0.0, 0.0, 0.0, -2.0), &[-1.0, 1.0]);
+        // x⁴+1
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+        // x(x-1)(x+2)(x-5)
+        assert_roots(
+            solve_quartic(1.0, -4.0, -7.0, 10.0, 0.0),
+            &[-2.0, 0.0, 1.0, 5.0],
+        );
+    }
+}
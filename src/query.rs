@@ -0,0 +1,359 @@
+//! closest points, distances and overlap tests for collision code
+//!
+//! mostly from "Real-Time Collision Detection" by Christer Ericson.
+//! spheres and boxes are solids here, a point inside is its own closest point.
+use crate::{
+    aabb::Aabb,
+    geometry::{Box, Plane, Quad, Sphere, Triangle},
+    linear_algebra::{
+        vector::{cross, dot},
+        Vector3,
+    },
+};
+
+/// the closest point of a shape to a given point
+pub trait ClosestPoint {
+    fn closest_point(&self, p: Vector3) -> Vector3;
+    /// 0 if the point is inside
+    fn distance(&self, p: Vector3) -> f32 {
+        (p - self.closest_point(p)).length()
+    }
+}
+
+/// if two shapes share at least a point, touching counts
+pub trait Overlaps<T> {
+    fn overlaps(&self, other: &T) -> bool;
+}
+
+impl ClosestPoint for Aabb {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        p.max(self.min).min(self.max)
+    }
+}
+impl ClosestPoint for Box {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        p.max(self.min).min(self.max)
+    }
+}
+impl ClosestPoint for Sphere {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        let offset = p - self.center;
+        if offset.length_squared() <= self.radius * self.radius {
+            return p;
+        }
+        self.center + offset.normalize() * self.radius
+    }
+}
+impl ClosestPoint for Plane {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        p - self.normal * self.signed_distance(p)
+    }
+}
+impl ClosestPoint for Triangle {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        closest_on_triangle(p, self.vertices())
+    }
+}
+impl ClosestPoint for Quad {
+    fn closest_point(&self, p: Vector3) -> Vector3 {
+        let (q, u, v) = self.corner_edges();
+        let n = cross(u, v);
+        let w = n / dot(n, n);
+        // projected on the plane, inside if both plane coordinates are in [0, 1]
+        let projected = p - n * dot(p - q, w);
+        let offset = projected - q;
+        let alpha = dot(w, cross(offset, v));
+        let beta = dot(w, cross(u, offset));
+        if (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta) {
+            return projected;
+        }
+        // otherwise on an edge, clamping alpha and beta is only right for rectangles
+        [(q, u), (q, v), (q + u, v), (q + v, u)]
+            .map(|(start, edge)| closest_on_segment(p, start, start + edge))
+            .into_iter()
+            .min_by(|a, b| {
+                (p - *a)
+                    .length_squared()
+                    .total_cmp(&(p - *b).length_squared())
+            })
+            .unwrap()
+    }
+}
+
+/// the closest point of the segment ab
+fn closest_on_segment(p: Vector3, a: Vector3, b: Vector3) -> Vector3 {
+    let ab = b - a;
+    let length_squared = dot(ab, ab);
+    if length_squared == 0.0 {
+        return a;
+    }
+    a + ab * (dot(p - a, ab) / length_squared).clamp(0.0, 1.0)
+}
+
+/// by the voronoi region of the triangle p lies in
+fn closest_on_triangle(p: Vector3, [a, b, c]: [Vector3; 3]) -> Vector3 {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = dot(ab, ap);
+    let d2 = dot(ac, ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let d3 = dot(ab, bp);
+    let d4 = dot(ac, bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let d5 = dot(ab, cp);
+    let d6 = dot(ac, cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    // inside the face
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+impl Overlaps<Aabb> for Aabb {
+    fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+}
+impl Overlaps<Sphere> for Sphere {
+    fn overlaps(&self, other: &Sphere) -> bool {
+        let radius = self.radius + other.radius;
+        (self.center - other.center).length_squared() <= radius * radius
+    }
+}
+impl Overlaps<Aabb> for Sphere {
+    fn overlaps(&self, other: &Aabb) -> bool {
+        let closest = other.closest_point(self.center);
+        (closest - self.center).length_squared() <= self.radius * self.radius
+    }
+}
+impl Overlaps<Sphere> for Aabb {
+    fn overlaps(&self, other: &Sphere) -> bool {
+        other.overlaps(self)
+    }
+}
+impl Overlaps<Triangle> for Sphere {
+    fn overlaps(&self, other: &Triangle) -> bool {
+        let closest = other.closest_point(self.center);
+        (closest - self.center).length_squared() <= self.radius * self.radius
+    }
+}
+impl Overlaps<Sphere> for Triangle {
+    fn overlaps(&self, other: &Sphere) -> bool {
+        other.overlaps(self)
+    }
+}
+/// separating axis test over the box normals, the triangle normal and the cross products of
+/// their edges, by Tomas Akenine-Möller
+impl Overlaps<Aabb> for Triangle {
+    fn overlaps(&self, other: &Aabb) -> bool {
+        let center = (other.min + other.max) / 2.0;
+        let half_size = (other.max - other.min) / 2.0;
+        let [a, b, c] = self.vertices().map(|v| v - center);
+        let edges = [b - a, c - b, a - c];
+        let box_axes = [Vector3::UNIT_X, Vector3::UNIT_Y, Vector3::UNIT_Z];
+        let separated = |axis: Vector3| {
+            let (pa, pb, pc) = (dot(a, axis), dot(b, axis), dot(c, axis));
+            let radius = half_size.x * axis.x.abs()
+                + half_size.y * axis.y.abs()
+                + half_size.z * axis.z.abs();
+            pa.min(pb).min(pc) > radius || pa.max(pb).max(pc) < -radius
+        };
+        // degenerate axes from parallel edges project everything to 0 and never separate
+        let cross_axes = box_axes
+            .iter()
+            .flat_map(|&axis| edges.map(|edge| cross(axis, edge)));
+        !box_axes
+            .into_iter()
+            .chain(std::iter::once(cross(edges[0], edges[1])))
+            .chain(cross_axes)
+            .any(separated)
+    }
+}
+impl Overlaps<Triangle> for Aabb {
+    fn overlaps(&self, other: &Triangle) -> bool {
+        other.overlaps(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linear_algebra::vector::vec3;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
+    fn unit_aabb() -> Aabb {
+        Aabb::from_min_max(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn closest_points() {
+        let aabb = unit_aabb();
+        assert_close(
+            aabb.closest_point(vec3(3.0, 0.5, -2.0)),
+            vec3(1.0, 0.5, -1.0),
+        );
+        assert_eq!(aabb.distance(vec3(0.5, 0.5, 0.5)), 0.0);
+        let cube = Box::new(aabb.min, aabb.max);
+        assert!((cube.distance(vec3(4.0, 5.0, 0.0)) - 5.0).abs() < 1e-6);
+        let sphere = Sphere::new(vec3(1.0, 0.0, 0.0), 2.0);
+        assert_close(
+            sphere.closest_point(vec3(1.0, 5.0, 0.0)),
+            vec3(1.0, 2.0, 0.0),
+        );
+        assert_eq!(sphere.distance(vec3(2.0, 0.0, 0.0)), 0.0);
+        let plane = Plane::new(vec3(0.0, 1.0, 0.0), Vector3::UNIT_Y);
+        assert_close(
+            plane.closest_point(vec3(3.0, -2.0, 4.0)),
+            vec3(3.0, 1.0, 4.0),
+        );
+        assert_eq!(plane.signed_distance(vec3(3.0, -2.0, 4.0)), -3.0);
+        assert_eq!(plane.distance(vec3(3.0, -2.0, 4.0)), 3.0);
+    }
+    #[test]
+    fn closest_on_triangle_regions() {
+        let triangle = Triangle::new(Vector3::ZERO, vec3(2.0, 0.0, 0.0), vec3(0.0, 2.0, 0.0));
+        // face, vertices and edges
+        assert_close(
+            triangle.closest_point(vec3(0.5, 0.5, 3.0)),
+            vec3(0.5, 0.5, 0.0),
+        );
+        assert_close(triangle.closest_point(vec3(-1.0, -1.0, 1.0)), Vector3::ZERO);
+        assert_close(
+            triangle.closest_point(vec3(4.0, -1.0, 0.0)),
+            vec3(2.0, 0.0, 0.0),
+        );
+        assert_close(
+            triangle.closest_point(vec3(-1.0, 5.0, 0.0)),
+            vec3(0.0, 2.0, 0.0),
+        );
+        assert_close(
+            triangle.closest_point(vec3(1.0, -3.0, 0.0)),
+            vec3(1.0, 0.0, 0.0),
+        );
+        assert_close(
+            triangle.closest_point(vec3(-3.0, 1.0, 0.0)),
+            vec3(0.0, 1.0, 0.0),
+        );
+        assert_close(
+            triangle.closest_point(vec3(2.0, 2.0, 1.0)),
+            vec3(1.0, 1.0, 0.0),
+        );
+        assert!((triangle.distance(vec3(2.0, 2.0, 0.0)) - 2.0_f32.sqrt()).abs() < 1e-6);
+    }
+    #[test]
+    fn closest_on_quad() {
+        // a slanted parallelogram, where clamping the plane coordinates would be wrong
+        let quad = Quad::new(Vector3::ZERO, vec3(2.0, 0.0, 0.0), vec3(2.0, 2.0, 0.0));
+        assert_close(quad.closest_point(vec3(2.0, 1.0, 5.0)), vec3(2.0, 1.0, 0.0));
+        assert_close(quad.closest_point(vec3(0.0, 2.0, 0.0)), vec3(1.0, 1.0, 0.0));
+        assert_close(quad.closest_point(vec3(5.0, 3.0, 0.0)), vec3(4.0, 2.0, 0.0));
+        assert_close(
+            quad.closest_point(vec3(1.0, -1.0, 1.0)),
+            vec3(1.0, 0.0, 0.0),
+        );
+    }
+    #[test]
+    fn overlaps() {
+        let aabb = unit_aabb();
+        let touching = Aabb::from_min_max(vec3(1.0, 0.0, 0.0), vec3(2.0, 1.0, 1.0));
+        let apart = Aabb::from_min_max(vec3(1.5, 0.0, 0.0), vec3(2.0, 1.0, 1.0));
+        assert!(aabb.overlaps(&touching) && touching.overlaps(&aabb));
+        assert!(!aabb.overlaps(&apart));
+
+        let sphere = Sphere::new(vec3(2.0, 2.0, 0.0), 1.5);
+        assert!(sphere.overlaps(&aabb) && aabb.overlaps(&sphere));
+        let sphere = Sphere::new(vec3(2.0, 2.0, 2.0), 1.5);
+        // sqrt(3) from the corner
+        assert!(!sphere.overlaps(&aabb));
+        assert!(sphere.overlaps(&Sphere::new(Vector3::ZERO, 2.0)));
+        assert!(!sphere.overlaps(&Sphere::new(Vector3::ZERO, 1.0)));
+
+        let triangle = Triangle::new(Vector3::ZERO, vec3(2.0, 0.0, 0.0), vec3(0.0, 2.0, 0.0));
+        assert!(Sphere::new(vec3(2.0, 2.0, 0.5), 1.6).overlaps(&triangle));
+        assert!(!Sphere::new(vec3(2.0, 2.0, 0.5), 1.0).overlaps(&triangle));
+        assert!(triangle.overlaps(&Sphere::new(vec3(0.5, 0.5, -0.9), 1.0)));
+    }
+    #[test]
+    fn triangle_aabb() {
+        let aabb = unit_aabb();
+        // a vertex inside
+        let inside = Triangle::new(Vector3::ZERO, vec3(5.0, 0.0, 0.0), vec3(0.0, 5.0, 0.0));
+        assert!(inside.overlaps(&aabb) && aabb.overlaps(&inside));
+        // all vertices outside but cutting through the box
+        let through = Triangle::new(
+            vec3(-5.0, -5.0, 0.0),
+            vec3(5.0, -5.0, 0.0),
+            vec3(0.0, 5.0, 0.0),
+        );
+        assert!(through.overlaps(&aabb));
+        // separated by a box axis and by the triangle normal
+        let beside = Triangle::new(
+            vec3(2.0, 0.0, 0.0),
+            vec3(3.0, 0.0, 0.0),
+            vec3(2.0, 1.0, 0.0),
+        );
+        assert!(!beside.overlaps(&aabb));
+        let above = Triangle::new(
+            vec3(3.5, 0.0, 0.0),
+            vec3(0.0, 3.5, 0.0),
+            vec3(0.0, 0.0, 3.5),
+        );
+        assert!(!above.overlaps(&aabb));
+        let past_corner = Triangle::new(
+            vec3(2.5, 0.0, -5.0),
+            vec3(0.0, 2.5, -5.0),
+            vec3(0.0, 2.5, 5.0),
+        );
+        assert!(!past_corner.overlaps(&aabb));
+        let at_corner = Triangle::new(
+            vec3(1.5, 0.0, -5.0),
+            vec3(0.0, 1.5, -5.0),
+            vec3(0.0, 1.5, 5.0),
+        );
+        assert!(at_corner.overlaps(&aabb));
+        // against brute force sampling of the triangle
+        for i in 0..200 {
+            let f = i as f32;
+            let offset = vec3((f * 0.37).sin(), (f * 0.71).cos(), (f * 0.13).sin()) * 2.0;
+            let triangle = Triangle::new(
+                offset,
+                offset + vec3((f * 1.3).cos(), 0.5, (f * 0.9).sin()),
+                offset + vec3(-0.3, (f * 2.1).sin(), 0.8),
+            );
+            let [a, b, c] = triangle.vertices();
+            let n = 40;
+            let sampled = (0..=n).any(|s| {
+                (0..=n - s).any(|t| {
+                    let (s, t) = (s as f32 / n as f32, t as f32 / n as f32);
+                    let expanded = Aabb::from_min_max(aabb.min * 0.99, aabb.max * 0.99);
+                    expanded.contains_point(a + (b - a) * s + (c - a) * t)
+                })
+            });
+            if sampled {
+                assert!(triangle.overlaps(&aabb), "{triangle:?}");
+            }
+        }
+    }
+}
@@ -3,13 +3,32 @@ use std::{
     ops::{Add, Div, Mul, Sub},
 };
 
+use serde::{Deserialize, Serialize};
+
 const RATIO: f64 = PI / 180.0;
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Deserialize, Serialize)]
 pub struct Radian(f64);
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Deserialize, Serialize)]
 pub struct Degree(f64);
 
+impl Radian {
+    pub fn new(value: f64) -> Self {
+        Self(value)
+    }
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+impl Degree {
+    pub fn new(value: f64) -> Self {
+        Self(value)
+    }
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
 // convert
 
 impl Into<Degree> for Radian {
@@ -1,5 +1,7 @@
 use std::ops::Range;
 
+use rand::RngCore;
+
 use crate::{
     aabb::Aabb,
     linear_algebra::{vector::dot, Transform, Vector3},
@@ -8,6 +10,66 @@ use crate::{
 pub trait Hitable {
     fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord>;
     fn bounding_box(&self) -> Aabb;
+    /// the spans of the whole ray line (negative t included) that are inside the object,
+    /// sorted along the ray.
+    ///
+    /// the default walks the hits one after another, pairing front faces with the following
+    /// back faces, so it only makes sense for closed surfaces. open surfaces have no inside.
+    /// it steps a bit past each hit, spans thinner than the step are lost: the primitives
+    /// finding all their hits at once override it.
+    fn intervals(&self, ray: Ray) -> Vec<Interval> {
+        Interval::step(self, ray)
+    }
+    /// whether directions toward the object can be drawn by `random_direction`
+    fn can_sample(&self) -> bool {
+        false
+    }
+    /// probability density, over solid angles, of `random_direction` giving `direction` from
+    /// `origin`. zero for the objects that can't be sampled
+    fn pdf_value(&self, _origin: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+    /// a direction from `origin` toward the object, distributed as `pdf_value`
+    ///
+    /// # Panics
+    /// for the objects that can't be sampled, see `can_sample`
+    fn random_direction(&self, _origin: Vector3, _rng: &mut dyn RngCore) -> Vector3 {
+        panic!("the object can't be sampled")
+    }
+}
+
+/// a span of a ray inside an object
+#[derive(Clone, Copy, Debug)]
+pub struct Interval {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+impl Interval {
+    /// the spans found by walking the hits of `object` one after another, stepping a bit past
+    /// each one
+    pub fn step<T: Hitable + ?Sized>(object: &T, ray: Ray) -> Vec<Interval> {
+        let mut start = f32::NEG_INFINITY;
+        Self::pair(std::iter::from_fn(|| {
+            let rec = object.hit(ray, start..f32::INFINITY)?;
+            // step over the hit without skipping a close one
+            start = rec.t + 1e-4 * rec.t.abs().max(1.0);
+            Some(rec)
+        }))
+    }
+    /// the spans from each front face to the back face following it, `hits` being sorted
+    /// along the ray. a front face following another one replaces it
+    pub fn pair(hits: impl IntoIterator<Item = HitRecord>) -> Vec<Interval> {
+        let mut intervals = vec![];
+        let mut enter: Option<HitRecord> = None;
+        for rec in hits {
+            if rec.front_face {
+                enter = Some(rec);
+            } else if let Some(enter) = enter.take() {
+                intervals.push(Interval { enter, exit: rec });
+            }
+        }
+        intervals
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -16,6 +78,8 @@ pub struct HitRecord {
     pub normal: Vector3,
     pub t: f32,
     pub front_face: bool,
+    /// surface coordinates of the hit point,
+    /// for triangles the barycentric coordinates of the 2nd and 3rd vertices
     pub u: f32,
     pub v: f32,
 }
@@ -34,10 +98,20 @@ impl HitRecord {
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    /// when the ray is cast, for moving objects
+    pub time: f32,
 }
 impl Ray {
     pub fn new(origin: Vector3, direction: Vector3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
     }
     pub fn at(&self, distance: f32) -> Vector3 {
         self.origin + distance * self.direction
@@ -68,7 +142,7 @@ impl Ray {
                 o.y,
                 o.x * sin_theta + o.z * cos_theta,
             );
-            let rotated_ray = Ray::new(origin, direction);
+            let rotated_ray = Ray::new(origin, direction).with_time(self.time);
             let mut record = target.hit(rotated_ray, range);
             if let Some(ref mut rec) = record {
                 let p = rec.point;
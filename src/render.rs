@@ -0,0 +1,634 @@
+//! a monte carlo path tracer rendering scenes to images of linear colors
+use std::{ops::Range, sync::Arc, thread};
+
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    camera::Projection,
+    color::{rgba, Color},
+    geometry::Bvh,
+    image::Image,
+    linear_algebra::Vector3,
+    material::Material,
+    ray::{HitRecord, Hitable, Ray},
+};
+
+/// a shape and what it's made of, the shape is shared with the lights of the scene
+pub struct Object {
+    pub shape: Arc<dyn Hitable + Send + Sync>,
+    pub material: Material,
+}
+impl Object {
+    pub fn new(shape: impl Hitable + Send + Sync + 'static, material: Material) -> Self {
+        Self {
+            shape: Arc::new(shape),
+            material,
+        }
+    }
+}
+impl Hitable for Object {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        self.shape.hit(ray, range)
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.shape.bounding_box()
+    }
+    fn can_sample(&self) -> bool {
+        self.shape.can_sample()
+    }
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        self.shape.pdf_value(origin, direction)
+    }
+    fn random_direction(&self, origin: Vector3, rng: &mut dyn RngCore) -> Vector3 {
+        self.shape.random_direction(origin, rng)
+    }
+}
+
+/// the shapes giving off light, sampled to send rays toward them
+///
+/// only spheres, quads, circles and triangles, instanced or not, can be sampled. the other
+/// lights, like meshes loaded from OBJ files, are left out: they still light the scene but
+/// only through the rays scattered by the materials that happen to hit them, so they are
+/// noisier when small.
+#[derive(Clone, Default)]
+pub struct Lights {
+    shapes: Vec<Arc<dyn Hitable + Send + Sync>>,
+}
+impl Lights {
+    /// keeps the shapes that can be sampled
+    pub fn new(shapes: Vec<Arc<dyn Hitable + Send + Sync>>) -> Self {
+        Self {
+            shapes: shapes
+                .into_iter()
+                .filter(|shape| shape.can_sample())
+                .collect(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+    /// the mean of the densities of the shapes, each being sampled as often
+    pub fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        if self.shapes.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self
+            .shapes
+            .iter()
+            .map(|shape| shape.pdf_value(origin, direction))
+            .sum();
+        sum / self.shapes.len() as f32
+    }
+    /// a direction toward a random shape, panics without any
+    pub fn random_direction(&self, origin: Vector3, rng: &mut impl Rng) -> Vector3 {
+        let shape = &self.shapes[rng.random_range(0..self.shapes.len())];
+        shape.random_direction(origin, rng)
+    }
+}
+
+/// the objects to render, in a bvh, and the lights among them
+pub struct Scene {
+    objects: Bvh<Object>,
+    lights: Lights,
+}
+impl Scene {
+    /// the objects made of `Material::DiffuseLight` are the lights
+    pub fn new(objects: Vec<Object>) -> Self {
+        let lights = objects
+            .iter()
+            .filter(|object| matches!(object.material, Material::DiffuseLight { .. }))
+            .map(|object| object.shape.clone())
+            .collect();
+        Self {
+            objects: Bvh::new(objects),
+            lights: Lights::new(lights),
+        }
+    }
+    /// the closest object hit, rays start a bit off the surface to not hit it again
+    pub fn hit(&self, ray: Ray) -> Option<(&Object, HitRecord)> {
+        self.objects.hit_object(ray, 1e-3..f32::INFINITY)
+    }
+    pub fn lights(&self) -> &Lights {
+        &self.lights
+    }
+}
+
+/// the light coming from where rays hit nothing
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub enum Background {
+    Solid(Color),
+    /// a vertical gradient
+    Sky {
+        horizon: Color,
+        zenith: Color,
+    },
+}
+impl Background {
+    pub fn color(&self, direction: Vector3) -> Color {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Sky { horizon, zenith } => {
+                let t = direction.normalize().y.max(0.0);
+                horizon * (1.0 - t) + zenith * t
+            }
+        }
+    }
+}
+
+/// how two sampling strategies are weighted against each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Heuristic {
+    Balance,
+    /// with an exponent of 2, better when one strategy is much better than the other
+    Power,
+}
+impl Heuristic {
+    /// the weight of a sample drawn with the density `pdf`, the other strategy having the
+    /// density `other` for it
+    pub fn weight(self, pdf: f32, other: f32) -> f32 {
+        let (a, b) = match self {
+            Heuristic::Balance => (pdf, other),
+            Heuristic::Power => (pdf * pdf, other * other),
+        };
+        if a + b > 0.0 {
+            a / (a + b)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// how the light coming back along a ray is estimated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Integrator {
+    /// following the rays scattered by the materials only, noisy with small lights
+    #[default]
+    PathTracing,
+    /// sampling the lights at each non specular bounce as well, both strategies combined by
+    /// multiple importance sampling
+    ///
+    /// https://graphics.stanford.edu/courses/cs348b-03/papers/veach-chapter9.pdf
+    Mis(Heuristic),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    /// max number of surfaces a path goes through
+    pub max_depth: u32,
+    /// paths are randomly ended from this depth on, with a chance depending on how much light
+    /// they still carry
+    pub russian_roulette_depth: u32,
+    pub background: Background,
+    /// the same seed renders the same image
+    pub seed: u64,
+    pub integrator: Integrator,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 400,
+            height: 225,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            russian_roulette_depth: 5,
+            background: Background::Sky {
+                horizon: Color::WHITE,
+                zenith: rgba(0.5, 0.7, 1.0, 1.0),
+            },
+            seed: 0,
+            integrator: Integrator::PathTracing,
+        }
+    }
+}
+
+/// renders on all the available threads, each pixel is the mean of its samples
+pub fn render(
+    scene: &Scene,
+    camera: &(impl Projection + Sync),
+    settings: &Settings,
+) -> Image<Color> {
+    let (width, height) = (settings.width, settings.height);
+    let mut pixels = vec![Color::BLACK; width * height];
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = height.div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        for (chunk, rows) in pixels
+            .chunks_mut((width * rows_per_thread).max(1))
+            .enumerate()
+        {
+            scope.spawn(move || {
+                for (i, row) in rows.chunks_mut(width).enumerate() {
+                    let y = chunk * rows_per_thread + i;
+                    // one generator per row, the image doesn't depend on the threads
+                    let mut rng = StdRng::seed_from_u64(
+                        settings.seed ^ (y as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15),
+                    );
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let sum: Color = (0..settings.samples_per_pixel)
+                            .map(|_| {
+                                let ray = camera.pixel_ray(
+                                    x as u32,
+                                    y as u32,
+                                    width as u32,
+                                    height as u32,
+                                    &mut rng,
+                                );
+                                trace(scene, ray, settings, &mut rng)
+                            })
+                            .sum();
+                        let mean = sum / settings.samples_per_pixel.max(1) as f32;
+                        *pixel = rgba(mean.r, mean.g, mean.b, 1.0);
+                    }
+                }
+            });
+        }
+    });
+    Image::from_pixels(width, height, pixels).unwrap()
+}
+
+/// the light coming back along the ray, estimated with a single path
+pub fn trace(scene: &Scene, ray: Ray, settings: &Settings, rng: &mut impl Rng) -> Color {
+    match settings.integrator {
+        Integrator::PathTracing => trace_path(scene, ray, settings, rng),
+        Integrator::Mis(heuristic) => trace_mis(scene, ray, settings, heuristic, rng),
+    }
+}
+
+fn trace_path(scene: &Scene, mut ray: Ray, settings: &Settings, rng: &mut impl Rng) -> Color {
+    let mut radiance = rgba(0.0, 0.0, 0.0, 1.0);
+    let mut throughput = Color::WHITE;
+    for depth in 0..settings.max_depth {
+        let Some((object, rec)) = scene.hit(ray) else {
+            radiance += throughput * settings.background.color(ray.direction);
+            break;
+        };
+        radiance += throughput * object.material.emitted(&rec);
+        let Some(scatter) = object.material.scatter(&ray, &rec, rng) else {
+            break;
+        };
+        throughput = throughput * scatter.attenuation;
+        if depth >= settings.russian_roulette_depth && !survives(&mut throughput, rng) {
+            break;
+        }
+        ray = scatter.ray;
+    }
+    radiance
+}
+
+/// like `trace_path`, the light reaching each non specular surface is also estimated by a ray
+/// toward a random light. a light found by both is weighted by `heuristic`
+fn trace_mis(
+    scene: &Scene,
+    mut ray: Ray,
+    settings: &Settings,
+    heuristic: Heuristic,
+    rng: &mut impl Rng,
+) -> Color {
+    let lights = scene.lights();
+    let mut radiance = rgba(0.0, 0.0, 0.0, 1.0);
+    let mut throughput = Color::WHITE;
+    // where the ray was scattered from and the density of its direction, None if the lights
+    // weren't sampled there
+    let mut scattered: Option<(Vector3, f32)> = None;
+    for depth in 0..settings.max_depth {
+        let Some((object, rec)) = scene.hit(ray) else {
+            radiance += throughput * settings.background.color(ray.direction);
+            break;
+        };
+        let weight = scattered.map_or(1.0, |(origin, pdf)| {
+            heuristic.weight(pdf, lights.pdf_value(origin, ray.direction))
+        });
+        radiance += throughput * object.material.emitted(&rec) * weight;
+        let Some(scatter) = object.material.scatter(&ray, &rec, rng) else {
+            break;
+        };
+        scattered = None;
+        // the light found by the last bounce wouldn't be followed by its scattered ray
+        if !object.material.is_specular() && !lights.is_empty() && depth + 1 < settings.max_depth {
+            let direction = lights.random_direction(rec.point, rng);
+            let light_pdf = lights.pdf_value(rec.point, direction);
+            let pdf = object.material.scattering_pdf(&ray, &rec, direction);
+            let shadow = Ray::new(rec.point, direction).with_time(ray.time);
+            if light_pdf > 0.0 && pdf > 0.0 {
+                if let Some((light, light_rec)) = scene.hit(shadow) {
+                    // the attenuation being for directions sampled by the material
+                    let weight = heuristic.weight(light_pdf, pdf) * pdf / light_pdf;
+                    radiance += throughput
+                        * scatter.attenuation
+                        * light.material.emitted(&light_rec)
+                        * weight;
+                }
+            }
+            let pdf = object
+                .material
+                .scattering_pdf(&ray, &rec, scatter.ray.direction);
+            scattered = Some((rec.point, pdf));
+        }
+        throughput = throughput * scatter.attenuation;
+        if depth >= settings.russian_roulette_depth && !survives(&mut throughput, rng) {
+            break;
+        }
+        ray = scatter.ray;
+    }
+    radiance
+}
+
+/// russian roulette, paths are ended with a chance depending on how much light they still
+/// carry. survivors carry the light of the ended paths, keeping the estimate unbiased
+fn survives(throughput: &mut Color, rng: &mut impl Rng) -> bool {
+    let survival = throughput.r.max(throughput.g).max(throughput.b).min(1.0);
+    if rng.random::<f32>() >= survival {
+        return false;
+    }
+    *throughput /= survival;
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        camera::{Perspective, View},
+        geometry::{Box, Quad, Sphere},
+        instance::Instance,
+        linear_algebra::{vector::vec3, Matrix4},
+        medium::{ConstantMedium, PhaseFunction},
+        radian::Degree,
+    };
+
+    fn camera() -> Perspective {
+        let view = View::new(vec3(0.0, 0.0, 5.0), Vector3::ZERO, Vector3::UNIT_Y);
+        Perspective::new(view, Degree::new(30.0), 1.0)
+    }
+    fn furnace(albedo: f32) -> Scene {
+        let grey = rgba(albedo, albedo, albedo, 1.0);
+        Scene::new(vec![Object::new(
+            Sphere::new(Vector3::ZERO, 1.0),
+            Material::Lambertian {
+                albedo: grey.into(),
+            },
+        )])
+    }
+    fn settings() -> Settings {
+        Settings {
+            width: 16,
+            height: 16,
+            samples_per_pixel: 4,
+            background: Background::Solid(Color::WHITE),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn white_furnace() {
+        // light bounces once off a convex object and leaves, so the sphere is exactly its albedo
+        let settings = Settings {
+            russian_roulette_depth: u32::MAX,
+            ..settings()
+        };
+        let image = render(&furnace(0.5), &camera(), &settings);
+        assert_eq!(image.get_pixel(8, 8).g, 0.5);
+        assert_eq!(image.get_pixel(0, 0).g, 1.0);
+        assert_eq!(image.get_pixel(8, 8).a, 1.0);
+        // without any bounce left after the hit, no light reaches it
+        let settings = Settings {
+            max_depth: 1,
+            ..settings
+        };
+        assert_eq!(
+            render(&furnace(0.5), &camera(), &settings)
+                .get_pixel(8, 8)
+                .g,
+            0.0
+        );
+    }
+    #[test]
+    fn russian_roulette() {
+        // paths are ended at random but the mean stays the same
+        let settings = Settings {
+            russian_roulette_depth: 0,
+            samples_per_pixel: 64,
+            ..settings()
+        };
+        let image = render(&furnace(0.5), &camera(), &settings);
+        let center: f32 = (6..10)
+            .flat_map(|y| (6..10).map(move |x| (x, y)))
+            .map(|(x, y)| image.get_pixel(x, y).g)
+            .sum::<f32>()
+            / 16.0;
+        assert!((center - 0.5).abs() < 0.05, "{center}");
+        // the same seed gives the same image
+        let again = render(&furnace(0.5), &camera(), &settings);
+        assert!(image
+            .pixels()
+            .iter()
+            .zip(again.pixels())
+            .all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+    }
+    #[test]
+    fn reproducible_fog() {
+        let fog = ConstantMedium::new(
+            Sphere::new(Vector3::ZERO, 1.0),
+            1.0,
+            PhaseFunction::Isotropic,
+        );
+        let scene = Scene::new(vec![Object::new(
+            fog,
+            Material::Isotropic {
+                albedo: rgba(0.8, 0.8, 0.8, 1.0).into(),
+                phase: PhaseFunction::Isotropic,
+            },
+        )]);
+        let image = render(&scene, &camera(), &settings());
+        let again = render(&scene, &camera(), &settings());
+        assert!(image
+            .pixels()
+            .iter()
+            .zip(again.pixels())
+            .all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+    }
+    #[test]
+    fn lights() {
+        let light = rgba(4.0, 2.0, 1.0, 1.0);
+        let scene = Scene::new(vec![
+            Object::new(
+                Quad::new(
+                    vec3(-1.0, -1.0, 0.0),
+                    vec3(2.0, 0.0, 0.0),
+                    vec3(0.0, 2.0, 0.0),
+                ),
+                Material::DiffuseLight { emit: light.into() },
+            ),
+            Object::new(
+                Sphere::new(vec3(0.0, 0.0, -100.0), 1.0),
+                Material::Lambertian {
+                    albedo: Color::WHITE.into(),
+                },
+            ),
+        ]);
+        let settings = Settings {
+            background: Background::Solid(rgba(0.0, 0.0, 0.0, 1.0)),
+            ..settings()
+        };
+        let image = render(&scene, &camera(), &settings);
+        let center = image.get_pixel(8, 8);
+        assert_eq!((center.r, center.g, center.b), (4.0, 2.0, 1.0));
+        assert_eq!(image.get_pixel(0, 0).r, 0.0);
+    }
+    #[test]
+    fn heuristics() {
+        assert_eq!(Heuristic::Balance.weight(3.0, 1.0), 0.75);
+        assert_eq!(Heuristic::Power.weight(3.0, 1.0), 0.9);
+        assert_eq!(Heuristic::Power.weight(0.0, 0.0), 0.0);
+        for heuristic in [Heuristic::Balance, Heuristic::Power] {
+            let sum = heuristic.weight(0.3, 2.0) + heuristic.weight(2.0, 0.3);
+            assert!((sum - 1.0).abs() < 1e-6);
+        }
+    }
+    #[test]
+    fn mis_furnace() {
+        // without lights everything comes from the scattered rays, as when path tracing
+        let settings = Settings {
+            russian_roulette_depth: u32::MAX,
+            integrator: Integrator::Mis(Heuristic::Power),
+            ..settings()
+        };
+        let image = render(&furnace(0.5), &camera(), &settings);
+        assert_eq!(image.get_pixel(8, 8).g, 0.5);
+        assert_eq!(image.get_pixel(0, 0).g, 1.0);
+    }
+    #[test]
+    fn small_light() {
+        // a floor lit by a small square light above it, in the dark
+        let (albedo, emit, side) = (0.5, 50.0, 0.2);
+        let scene = Scene::new(vec![
+            Object::new(
+                Quad::new(
+                    vec3(-5.0, 0.0, -5.0),
+                    vec3(0.0, 0.0, 10.0),
+                    vec3(10.0, 0.0, 0.0),
+                ),
+                Material::Lambertian {
+                    albedo: rgba(albedo, albedo, albedo, 1.0).into(),
+                },
+            ),
+            Object::new(
+                Quad::new(
+                    vec3(-side / 2.0, 1.0, -side / 2.0),
+                    vec3(side, 0.0, 0.0),
+                    vec3(0.0, 0.0, side),
+                ),
+                Material::DiffuseLight {
+                    emit: rgba(emit, emit, emit, 1.0).into(),
+                },
+            ),
+        ]);
+        assert_eq!(scene.lights().len(), 1);
+        // the light reflected at p, summed over the light's area
+        let p = vec3(0.5, 0.0, 0.0);
+        let n = 100;
+        let cell = side / n as f32;
+        let mut expected = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let x = -side / 2.0 + (i as f32 + 0.5) * cell;
+                let z = -side / 2.0 + (j as f32 + 0.5) * cell;
+                let d = vec3(x, 1.0, z) - p;
+                let cos = 1.0 / d.length();
+                expected += cos * cos / d.length_squared() * cell * cell;
+            }
+        }
+        expected *= albedo / std::f32::consts::PI * emit;
+        let ray = Ray::new(vec3(0.5, 0.5, 1.5), p - vec3(0.5, 0.5, 1.5));
+        let estimate = |integrator| {
+            let settings = Settings {
+                background: Background::Solid(rgba(0.0, 0.0, 0.0, 1.0)),
+                integrator,
+                ..settings()
+            };
+            let mut rng = StdRng::seed_from_u64(0);
+            let samples: Vec<f32> = (0..20000)
+                .map(|_| trace(&scene, ray, &settings, &mut rng).g)
+                .collect();
+            let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+            let variance =
+                samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+            (mean, variance)
+        };
+        let (path_mean, path_variance) = estimate(Integrator::PathTracing);
+        assert!(
+            (path_mean / expected - 1.0).abs() < 0.3,
+            "{path_mean} {expected}"
+        );
+        for heuristic in [Heuristic::Balance, Heuristic::Power] {
+            let (mean, variance) = estimate(Integrator::Mis(heuristic));
+            assert!((mean / expected - 1.0).abs() < 0.02, "{mean} {expected}");
+            assert!(
+                variance * 100.0 < path_variance,
+                "{variance} {path_variance}"
+            );
+        }
+    }
+    #[test]
+    fn lights_that_cant_be_sampled() {
+        let emit = |e: f32| Material::DiffuseLight {
+            emit: rgba(e, e, e, 1.0).into(),
+        };
+        let floor = || {
+            Object::new(
+                Quad::new(
+                    vec3(-5.0, 0.0, -5.0),
+                    vec3(0.0, 0.0, 10.0),
+                    vec3(10.0, 0.0, 0.0),
+                ),
+                Material::Lambertian {
+                    albedo: Color::WHITE.into(),
+                },
+            )
+        };
+        let panel = || Box::new(vec3(-0.5, 1.0, -0.5), vec3(0.5, 1.2, 0.5));
+        let sphere: Arc<dyn Hitable + Send + Sync> = Arc::new(Sphere::new(Vector3::ZERO, 1.0));
+        let moved = Instance::new(sphere, Matrix4::translation(vec3(0.0, 10.0, 0.0))).unwrap();
+        let scene = Scene::new(vec![
+            floor(),
+            Object::new(panel(), emit(4.0)),
+            Object::new(moved, emit(1.0)),
+        ]);
+        // the box is left out, the instanced sphere kept
+        assert_eq!(scene.lights().len(), 1);
+        assert!(scene.lights().pdf_value(Vector3::ZERO, Vector3::UNIT_Y) > 0.0);
+        // lit by the scattered rays only, even without any other light
+        let scene = Scene::new(vec![floor(), Object::new(panel(), emit(4.0))]);
+        assert!(scene.lights().is_empty());
+        let settings = Settings {
+            background: Background::Solid(rgba(0.0, 0.0, 0.0, 1.0)),
+            integrator: Integrator::Mis(Heuristic::Power),
+            ..settings()
+        };
+        let ray = Ray::new(vec3(0.0, 0.5, 1.5), vec3(0.0, -0.5, -1.5));
+        let mut rng = StdRng::seed_from_u64(0);
+        let lit = (0..100).any(|_| trace(&scene, ray, &settings, &mut rng).g > 0.0);
+        assert!(lit);
+    }
+    #[test]
+    fn sky() {
+        let sky = Background::Sky {
+            horizon: Color::WHITE,
+            zenith: rgba(0.0, 0.0, 1.0, 1.0),
+        };
+        assert_eq!(sky.color(Vector3::UNIT_Y).r, 0.0);
+        assert_eq!(sky.color(vec3(1.0, 0.0, 0.0)).r, 1.0);
+        assert_eq!(sky.color(vec3(1.0, -1.0, 0.0)).r, 1.0);
+        assert_eq!(sky.color(vec3(0.0, 1.0, 1.0)).r, 1.0 - 0.5_f32.sqrt());
+    }
+}
@@ -0,0 +1,85 @@
+//! random directions and points for monte carlo rendering
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::linear_algebra::{vector::vec3, Vector3};
+
+/// a uniformly distributed direction
+pub fn unit_sphere(rng: &mut impl Rng) -> Vector3 {
+    let z = 1.0 - 2.0 * rng.random::<f32>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.random::<f32>();
+    vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// a uniformly distributed point in the disk of radius 1 on the xy plane
+pub fn unit_disk(rng: &mut impl Rng) -> Vector3 {
+    let r = rng.random::<f32>().sqrt();
+    let phi = 2.0 * PI * rng.random::<f32>();
+    vec3(r * phi.cos(), r * phi.sin(), 0.0)
+}
+
+/// a direction around +z in the hemisphere z >= 0, with a density of cos θ / π
+pub fn cosine_hemisphere(rng: &mut impl Rng) -> Vector3 {
+    let r2: f32 = rng.random();
+    let phi = 2.0 * PI * rng.random::<f32>();
+    let r = r2.sqrt();
+    vec3(r * phi.cos(), r * phi.sin(), (1.0 - r2).max(0.0).sqrt())
+}
+
+/// a uniformly distributed direction in the cone around +z whose half angle has the cosine
+/// `cos_theta_max`, the density being 1 / (2π (1 - cos_theta_max))
+pub fn cone(cos_theta_max: f32, rng: &mut impl Rng) -> Vector3 {
+    let z = 1.0 + rng.random::<f32>() * (cos_theta_max - 1.0);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.random::<f32>();
+    vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn uniform_directions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let n = 10000;
+        let mut sum = Vector3::ZERO;
+        for _ in 0..n {
+            let d = unit_sphere(&mut rng);
+            assert!((d.length() - 1.0).abs() < 1e-5);
+            sum = sum + d;
+        }
+        assert!((sum / n as f32).length() < 0.03);
+    }
+    #[test]
+    fn cosine_weighted() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let n = 10000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let d = cosine_hemisphere(&mut rng);
+            assert!((d.length() - 1.0).abs() < 1e-5);
+            assert!(d.z >= 0.0);
+            sum += d.z;
+        }
+        // the mean cosine is 2/3 for a density of cos θ / π
+        assert!((sum / n as f32 - 2.0 / 3.0).abs() < 0.01);
+    }
+    #[test]
+    fn cones() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (n, cos_max) = (10000, 0.8);
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let d = cone(cos_max, &mut rng);
+            assert!((d.length() - 1.0).abs() < 1e-5);
+            assert!(d.z >= cos_max - 1e-6);
+            sum += d.z;
+        }
+        // uniform in z over [cos_max, 1]
+        assert!((sum / n as f32 - 0.9).abs() < 0.005);
+    }
+}
@@ -0,0 +1,812 @@
+//! scenes described in JSON files, built into what `render::render` takes
+//!
+//! a scene file has a format `version`, a `camera` and optionally render `settings`, named
+//! `textures` and `materials`, `objects` and `lights`. images and OBJ meshes are referred to
+//! by their path relative to the scene file.
+//! ```json
+//! {
+//!     "version": "1.0.0",
+//!     "camera": { "Perspective": {
+//!         "view": { "look_from": { "x": 0, "y": 1, "z": 5 }, "look_at": { "x": 0, "y": 0, "z": 0 },
+//!             "up": { "x": 0, "y": 1, "z": 0 } },
+//!         "fov": 40, "aspect_ratio": 1.5
+//!     } },
+//!     "textures": { "wood": { "Image": { "path": "wood.png" } } },
+//!     "materials": { "table": { "Lambertian": { "albedo": "wood" } } },
+//!     "objects": [
+//!         { "shape": { "Obj": "table.obj" }, "material": "table",
+//!             "transform": { "rotation": { "x": 0, "y": 90, "z": 0 } } }
+//!     ],
+//!     "lights": [
+//!         { "shape": { "Sphere": { "center": { "x": 0, "y": 4, "z": 0 }, "radius": 1 } },
+//!             "emit": { "Solid": { "r": 4, "g": 4, "b": 4, "a": 1 } } }
+//!     ]
+//! }
+//! ```
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, StringDeserializer},
+        DeserializeSeed, IgnoredAny, IntoDeserializer, MapAccess, Unexpected, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use serde_path_to_error::Segment;
+
+use crate::{
+    camera::Camera,
+    encoding::png,
+    geometry::Geometry,
+    image::{bmp, pfm, ppm, tga, Filter, Image},
+    instance::Instance,
+    linear_algebra::{vector::vec3, Matrix4, Vector3},
+    material::Material,
+    model_3d::obj::Parser,
+    ray::Hitable,
+    render::{Object, Scene, Settings},
+    texture::{AnyTexture, ImageTexture, Wrap},
+    SemVer,
+};
+
+/// the version of the format written, files of compatible versions can be read
+pub const VERSION: SemVer = SemVer {
+    major: 1,
+    minor: 0,
+    patch: 0,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// the scene file can't be read
+    Io { path: PathBuf, error: io::Error },
+    /// not JSON, or a scene holding something that can't be written like an image texture
+    Json(serde_json::Error),
+    /// written for a version of the format not compatible with `VERSION`
+    Version(SemVer),
+    /// something wrong at `path` in the file, like `$.objects[2].material`
+    Invalid { path: String, message: String },
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "can't read {}: {error}", path.display()),
+            Error::Json(error) => write!(f, "{error}"),
+            Error::Version(version) => {
+                write!(f, "version {version} isn't compatible with {VERSION}")
+            }
+            Error::Invalid { path, message } => write!(f, "{path}: {message}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+fn invalid(path: &str, message: impl Into<String>) -> Error {
+    Error::Invalid {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+/// a texture named in `textures`
+#[derive(Clone, Debug, Serialize)]
+pub enum TextureDescription {
+    /// `{ "Image": { "path": ... } }`
+    Image(ImageFile),
+    /// any other texture, like `{ "Noise": { ... } }`
+    #[serde(untagged)]
+    Texture(AnyTexture),
+}
+impl<'de> Deserialize<'de> for TextureDescription {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(Extended {
+            tag: "Image",
+            extra: TextureDescription::Image,
+            other: TextureDescription::Texture,
+        })
+    }
+}
+
+/// a PNG, PPM, PFM, BMP or TGA file, its colors used as they are stored
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageFile {
+    /// relative to the scene file
+    pub path: PathBuf,
+    #[serde(default = "default_wrap")]
+    pub wrap: Wrap,
+    #[serde(default = "default_filter")]
+    pub filter: Filter,
+}
+fn default_wrap() -> Wrap {
+    Wrap::Repeat
+}
+fn default_filter() -> Filter {
+    Filter::Bilinear
+}
+
+/// a texture of a material, a name in `textures` or the texture itself
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum TextureRef {
+    Named(String),
+    Inline(AnyTexture),
+}
+impl<'de> Deserialize<'de> for TextureRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RefVisitor;
+        impl<'de> Visitor<'de> for RefVisitor {
+            type Value = TextureRef;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a texture name or a texture")
+            }
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<TextureRef, E> {
+                Ok(TextureRef::Named(name.to_string()))
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TextureRef, A::Error> {
+                let texture = AnyTexture::deserialize(MapAccessDeserializer::new(&mut map))?;
+                only_key(map, TextureRef::Inline(texture))
+            }
+        }
+        deserializer.deserialize_any(RefVisitor)
+    }
+}
+impl From<AnyTexture> for TextureRef {
+    fn from(texture: AnyTexture) -> Self {
+        TextureRef::Inline(texture)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub enum Shape {
+    /// `{ "Obj": "mesh.obj" }`, a file relative to the scene file, loaded once for all the
+    /// objects using it
+    Obj(PathBuf),
+    /// any geometry, like `{ "Sphere": { ... } }`
+    #[serde(untagged)]
+    Geometry(Geometry),
+}
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(Extended {
+            tag: "Obj",
+            extra: Shape::Obj,
+            other: Shape::Geometry,
+        })
+    }
+}
+
+/// scaled, then rotated around x, y and z in this order, then translated
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Placement {
+    pub translation: Vector3,
+    /// in degrees
+    pub rotation: Vector3,
+    pub scale: Vector3,
+}
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::ZERO,
+            rotation: Vector3::ZERO,
+            scale: vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+impl Placement {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+    pub fn matrix(&self) -> Matrix4 {
+        let r = self.rotation;
+        Matrix4::translation(self.translation)
+            * Matrix4::rotation_z(r.z.to_radians())
+            * Matrix4::rotation_y(r.y.to_radians())
+            * Matrix4::rotation_x(r.x.to_radians())
+            * Matrix4::scale(self.scale)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectDescription {
+    pub shape: Shape,
+    /// a name in `materials`
+    pub material: String,
+    #[serde(default, skip_serializing_if = "Placement::is_identity")]
+    pub transform: Placement,
+}
+
+/// a shape giving off light, only sampled directly by `render::Lights` if it can be
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDescription {
+    pub shape: Shape,
+    pub emit: TextureRef,
+    #[serde(default, skip_serializing_if = "Placement::is_identity")]
+    pub transform: Placement,
+}
+
+/// what a scene file holds, references to other files and between its parts not resolved yet
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub version: SemVer,
+    pub camera: Camera,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub textures: BTreeMap<String, TextureDescription>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, Material<TextureRef>>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+}
+
+/// a scene ready to render
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub settings: Settings,
+}
+
+/// reads a scene file and the files it refers to
+pub fn load(path: impl AsRef<Path>) -> Result<LoadedScene, Error> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    SceneDescription::parse(&json)?.build(path.parent().unwrap_or(Path::new("")))
+}
+
+impl SceneDescription {
+    /// an empty scene with the default settings
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            version: VERSION,
+            camera,
+            settings: Settings::default(),
+            textures: BTreeMap::new(),
+            materials: BTreeMap::new(),
+            objects: Vec::new(),
+            lights: Vec::new(),
+        }
+    }
+    /// parses the content of a scene file, checking its version first
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(json).map_err(Error::Json)?;
+        // the rest of a file of another version may not be readable
+        if let Some(version) = value.get("version") {
+            let version: SemVer = from_value(version, "$.version")?;
+            if !VERSION.is_compatible_with(&version) {
+                return Err(Error::Version(version));
+            }
+        }
+        from_value(&value, "$")
+    }
+    /// the content of a scene file, parsed back to the same scene
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(Error::Json)
+    }
+    /// loads the files referred to from `directory` and resolves the names
+    pub fn build(&self, directory: impl AsRef<Path>) -> Result<LoadedScene, Error> {
+        let directory = directory.as_ref();
+        let mut textures = BTreeMap::new();
+        for (name, texture) in &self.textures {
+            let texture = match texture {
+                TextureDescription::Image(file) => {
+                    let path = format!("$.textures.{name}");
+                    AnyTexture::Image(Arc::new(load_image(directory, file, &path)?))
+                }
+                TextureDescription::Texture(texture) => texture.clone(),
+            };
+            textures.insert(name.as_str(), texture);
+        }
+        let resolve = |texture: &TextureRef, path: &str| match texture {
+            TextureRef::Named(name) => textures
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| invalid(path, format!("no texture named `{name}`"))),
+            TextureRef::Inline(texture) => Ok(texture.clone()),
+        };
+        let mut materials = BTreeMap::new();
+        for (name, material) in &self.materials {
+            let path = format!("$.materials.{name}");
+            let material = material.try_map(|texture| resolve(texture, &path))?;
+            materials.insert(name.as_str(), material);
+        }
+        let mut meshes = HashMap::new();
+        let mut objects = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            let path = format!("$.objects[{i}]");
+            let material = materials
+                .get(object.material.as_str())
+                .cloned()
+                .ok_or_else(|| {
+                    invalid(
+                        &format!("{path}.material"),
+                        format!("no material named `{}`", object.material),
+                    )
+                })?;
+            objects.push(Object {
+                shape: build_shape(
+                    &object.shape,
+                    &object.transform,
+                    directory,
+                    &mut meshes,
+                    &path,
+                )?,
+                material,
+            });
+        }
+        for (i, light) in self.lights.iter().enumerate() {
+            let path = format!("$.lights[{i}]");
+            objects.push(Object {
+                shape: build_shape(
+                    &light.shape,
+                    &light.transform,
+                    directory,
+                    &mut meshes,
+                    &path,
+                )?,
+                material: Material::DiffuseLight {
+                    emit: resolve(&light.emit, &format!("{path}.emit"))?,
+                },
+            });
+        }
+        Ok(LoadedScene {
+            scene: Scene::new(objects),
+            camera: self.camera,
+            settings: self.settings,
+        })
+    }
+}
+
+type Shared = Arc<dyn Hitable + Send + Sync>;
+
+/// `path` being where the object is in the file
+fn build_shape(
+    shape: &Shape,
+    transform: &Placement,
+    directory: &Path,
+    meshes: &mut HashMap<PathBuf, Shared>,
+    path: &str,
+) -> Result<Shared, Error> {
+    let shape: Shared = match shape {
+        Shape::Geometry(geometry) => Arc::new(geometry.clone()),
+        Shape::Obj(file) => match meshes.get(file) {
+            Some(mesh) => mesh.clone(),
+            None => {
+                let mesh = Parser::load(directory.join(file)).map_err(|error| {
+                    let message = format!("can't load {}: {error:?}", file.display());
+                    invalid(&format!("{path}.shape"), message)
+                })?;
+                let mesh: Shared = Arc::new(mesh.bvh());
+                meshes.insert(file.clone(), mesh.clone());
+                mesh
+            }
+        },
+    };
+    if transform.is_identity() {
+        return Ok(shape);
+    }
+    let instance = Instance::new(shape, transform.matrix())
+        .ok_or_else(|| invalid(&format!("{path}.transform"), "can't be inverted"))?;
+    Ok(Arc::new(instance))
+}
+
+fn load_image(directory: &Path, file: &ImageFile, path: &str) -> Result<ImageTexture, Error> {
+    let name = file.path.display();
+    let data = fs::read(directory.join(&file.path))
+        .map_err(|error| invalid(path, format!("can't read {name}: {error}")))?;
+    let extension = file.path.extension().and_then(|e| e.to_str());
+    let image: Result<Image<_>, String> = match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => png::decode(&data).map_err(|error| format!("{error:?}")),
+        Some("ppm") => ppm::decode(&data).map_err(|error| format!("{error:?}")),
+        Some("pfm") => pfm::decode(&data).map_err(|error| format!("{error:?}")),
+        Some("bmp") => bmp::decode(&data).map_err(|error| format!("{error:?}")),
+        Some("tga") => tga::decode(&data).map_err(|error| format!("{error:?}")),
+        _ => return Err(invalid(path, format!("{name} isn't a known image format"))),
+    };
+    let image = image.map_err(|error| invalid(path, format!("can't decode {name}: {error}")))?;
+    if image.width() == 0 || image.height() == 0 {
+        return Err(invalid(path, format!("{name} is empty")));
+    }
+    Ok(ImageTexture::new(image, file.wrap, file.filter))
+}
+
+/// an externally tagged enum `T` with one more variant named `tag`, holding an `E`, read
+/// from the map itself rather than a copy of it so errors keep their path
+struct Extended<E, T, R> {
+    tag: &'static str,
+    extra: fn(E) -> R,
+    other: fn(T) -> R,
+}
+impl<'de, E: Deserialize<'de>, T: Deserialize<'de>, R> Visitor<'de> for Extended<E, T, R> {
+    type Value = R;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an enum")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<R, A::Error> {
+        let tag: String = map.next_key()?.ok_or_else(single_key)?;
+        let value = if tag == self.tag {
+            (self.extra)(map.next_value()?)
+        } else {
+            let tagged = Tagged {
+                tag: Some(tag),
+                map: &mut map,
+            };
+            (self.other)(T::deserialize(MapAccessDeserializer::new(tagged))?)
+        };
+        only_key(map, value)
+    }
+}
+
+/// `map` whose first key, `tag`, has already been read
+struct Tagged<A> {
+    tag: Option<String>,
+    map: A,
+}
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Tagged<A> {
+    type Error = A::Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        match self.tag.take() {
+            Some(tag) => {
+                let tag: StringDeserializer<A::Error> = tag.into_deserializer();
+                seed.deserialize(tag).map(Some)
+            }
+            None => self.map.next_key_seed(seed),
+        }
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
+
+/// `value` if it was the only entry of `map`
+fn only_key<'de, A: MapAccess<'de>, T>(mut map: A, value: T) -> Result<T, A::Error> {
+    match map.next_key::<IgnoredAny>()? {
+        Some(_) => Err(single_key()),
+        None => Ok(value),
+    }
+}
+fn single_key<E: de::Error>() -> E {
+    E::invalid_value(Unexpected::Map, &"map with a single key")
+}
+
+/// `value` deserialized, an error telling the path of the innermost value or key that failed
+fn from_value<'a, T: Deserialize<'a>>(value: &'a Value, path: &str) -> Result<T, Error> {
+    serde_path_to_error::deserialize(value).map_err(|error| {
+        let mut path = path.to_string();
+        for segment in error.path() {
+            match segment {
+                Segment::Seq { index } => path += &format!("[{index}]"),
+                Segment::Map { key } => path += &format!(".{key}"),
+                Segment::Enum { variant } => path += &format!(".{variant}"),
+                Segment::Unknown => {}
+            }
+        }
+        invalid(&path, error.into_inner().to_string())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        camera::{Perspective, View},
+        color::Color,
+        geometry::Sphere,
+        radian::Degree,
+        ray::Ray,
+    };
+
+    const SCENE: &str = r#"{
+        "version": "1.0.3",
+        "camera": { "Perspective": {
+            "view": {
+                "look_from": { "x": 0.0, "y": 0.0, "z": 5.0 },
+                "look_at": { "x": 0.0, "y": 0.0, "z": 0.0 },
+                "up": { "x": 0.0, "y": 1.0, "z": 0.0 }
+            },
+            "fov": 40.0,
+            "aspect_ratio": 1.0
+        } },
+        "settings": { "width": 32, "height": 32, "samples_per_pixel": 2 },
+        "textures": {
+            "gray": { "Image": { "path": "png/gray1.png", "filter": "Nearest" } },
+            "marble": { "Noise": {
+                "color": { "r": 1.0, "g": 1.0, "b": 1.0, "a": 1.0 },
+                "scale": 4.0,
+                "style": { "Marble": { "depth": 7 } }
+            } }
+        },
+        "materials": {
+            "floor": { "Lambertian": { "albedo": "gray" } },
+            "stone": { "Metal": { "albedo": "marble", "fuzz": 0.2 } },
+            "glass": { "Dielectric": { "refraction_index": 1.5 } }
+        },
+        "objects": [
+            {
+                "shape": { "Sphere": { "center": { "x": 0.0, "y": 0.0, "z": 0.0 }, "radius": 1.0 } },
+                "material": "glass"
+            },
+            {
+                "shape": { "Obj": "obj/cube.obj" },
+                "material": "stone",
+                "transform": {
+                    "translation": { "x": 3.0, "y": 0.0, "z": 0.0 },
+                    "rotation": { "x": 0.0, "y": 45.0, "z": 0.0 },
+                    "scale": { "x": 2.0, "y": 2.0, "z": 2.0 }
+                }
+            },
+            {
+                "shape": { "Obj": "obj/cube.obj" },
+                "material": "floor",
+                "transform": { "translation": { "x": -3.0, "y": 0.0, "z": 0.0 } }
+            }
+        ],
+        "lights": [
+            {
+                "shape": { "Sphere": { "center": { "x": 0.0, "y": 10.0, "z": 0.0 }, "radius": 2.0 } },
+                "emit": { "Solid": { "r": 4.0, "g": 4.0, "b": 4.0, "a": 1.0 } }
+            }
+        ]
+    }"#;
+
+    /// the message of an invalid scene error, at `path`
+    fn invalid_at(json: &str, path: &str) -> String {
+        match SceneDescription::parse(json) {
+            Err(Error::Invalid { path: p, message }) if p == path => message,
+            other => panic!("{path}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_scene() {
+        let description = SceneDescription::parse(SCENE).unwrap();
+        assert_eq!(description.version, SemVer::new(1, 0, 3));
+        assert_eq!(description.settings.width, 32);
+        // the defaults for what isn't written
+        assert_eq!(description.settings.max_depth, 50);
+        assert!(description.objects[0].transform.is_identity());
+        let loaded = description.build("assets").unwrap();
+        assert!(matches!(loaded.camera, Camera::Perspective(_)));
+        let hit = |origin: Vector3| {
+            let ray = Ray::new(origin, vec3(0.0, 0.0, -1.0));
+            loaded.scene.hit(ray).map(|(object, rec)| (object, rec.t))
+        };
+        let (glass, t) = hit(vec3(0.0, 0.0, 5.0)).unwrap();
+        assert!(matches!(glass.material, Material::Dielectric { .. }));
+        assert!((t - 4.0).abs() < 1e-4);
+        // the cube scaled by 2 and turned by 45°, its corner toward +z
+        let (stone, t) = hit(vec3(3.1, 0.3, 5.0)).unwrap();
+        assert!(matches!(stone.material, Material::Metal { fuzz, .. } if fuzz == 0.2));
+        assert!((t - (5.1 - 2.0_f32.sqrt())).abs() < 1e-4, "{t}");
+        let (floor, t) = hit(vec3(-2.9, -0.2, 5.0)).unwrap();
+        assert!((t - 4.5).abs() < 1e-4);
+        let Material::Lambertian {
+            albedo: AnyTexture::Image(image),
+        } = &floor.material
+        else {
+            panic!("{:?}", floor.material);
+        };
+        assert_eq!(image.image.width(), 13);
+        assert_eq!(image.filter, Filter::Nearest);
+        let (light, _) = loaded
+            .scene
+            .hit(Ray::new(vec3(0.0, 5.0, 0.0), Vector3::UNIT_Y))
+            .unwrap();
+        assert!(matches!(light.material, Material::DiffuseLight { .. }));
+        assert_eq!(loaded.scene.lights().len(), 1);
+        assert!(hit(vec3(10.0, 0.0, 5.0)).is_none());
+    }
+    #[test]
+    fn round_trip() {
+        let description = SceneDescription::parse(SCENE).unwrap();
+        let json = description.to_json().unwrap();
+        let again = SceneDescription::parse(&json).unwrap();
+        assert_eq!(again.to_json().unwrap(), json);
+        assert!(
+            matches!(again.objects[1].shape, Shape::Obj(ref path) if path == Path::new("obj/cube.obj"))
+        );
+        assert_eq!(again.objects[1].transform.scale, vec3(2.0, 2.0, 2.0));
+        assert!(matches!(
+            again.textures["gray"],
+            TextureDescription::Image(ImageFile {
+                wrap: Wrap::Repeat,
+                ..
+            })
+        ));
+        // scenes made in code
+        let view = View::new(vec3(0.0, 0.0, 5.0), Vector3::ZERO, Vector3::UNIT_Y);
+        let camera = Camera::Perspective(Perspective::new(view, Degree::new(30.0), 1.0));
+        let mut description = SceneDescription::new(camera);
+        description.materials.insert(
+            "white".into(),
+            Material::Lambertian {
+                albedo: AnyTexture::from(Color::WHITE).into(),
+            },
+        );
+        description.objects.push(ObjectDescription {
+            shape: Shape::Geometry(Geometry::Sphere(Sphere::new(Vector3::ZERO, 1.0))),
+            material: "white".into(),
+            transform: Placement::default(),
+        });
+        let json = description.to_json().unwrap();
+        assert!(json.contains("\"version\": \"1.0.0\""));
+        assert!(!json.contains("transform"));
+        let again = SceneDescription::parse(&json).unwrap();
+        assert_eq!(again.objects.len(), 1);
+        // images aren't part of textures, only of the files they're loaded from
+        let image = ImageTexture::new(Image::new(1, 1, Color::WHITE), Wrap::Clamp, Filter::Nearest);
+        description.lights.push(LightDescription {
+            shape: Shape::Geometry(Geometry::Sphere(Sphere::new(Vector3::ZERO, 1.0))),
+            emit: AnyTexture::Image(Arc::new(image)).into(),
+            transform: Placement::default(),
+        });
+        assert!(matches!(description.to_json(), Err(Error::Json(_))));
+        // the parts read on their own too
+        let shape: Shape = serde_json::from_str(r#"{ "Obj": "a.obj" }"#).unwrap();
+        assert!(matches!(shape, Shape::Obj(ref path) if path == Path::new("a.obj")));
+        let json = r#"{ "Quad": { "q": { "x": 0, "y": 0, "z": 0 },
+            "u": { "x": 1, "y": 0, "z": 0 }, "v": { "x": 0, "y": 1, "z": 0 } } }"#;
+        let shape: Shape = serde_json::from_str(json).unwrap();
+        assert!(matches!(shape, Shape::Geometry(Geometry::Quad(_))));
+        let json = serde_json::to_string(&shape).unwrap();
+        assert!(serde_json::from_str::<Shape>(&json).is_ok(), "{json}");
+        assert!(serde_json::from_str::<Shape>(r#"{ "Obj": "a.obj", "Sphere": 1 }"#).is_err());
+        let texture: TextureRef = serde_json::from_str(r#""wood""#).unwrap();
+        assert!(matches!(texture, TextureRef::Named(ref name) if name == "wood"));
+        let json = r#"{ "Solid": { "r": 1, "g": 1, "b": 1, "a": 1 } }"#;
+        let texture: TextureRef = serde_json::from_str(json).unwrap();
+        assert!(matches!(texture, TextureRef::Inline(AnyTexture::Solid(_))));
+    }
+    #[test]
+    fn errors() {
+        let scene = |replace: &str, with: &str| {
+            assert!(SCENE.contains(replace), "{replace}");
+            SCENE.replacen(replace, with, 1)
+        };
+        assert!(matches!(
+            SceneDescription::parse(&scene("\"1.0.3\"", "\"2.0.0\"")),
+            Err(Error::Version(SemVer { major: 2, .. }))
+        ));
+        let message = invalid_at(&scene("\"1.0.3\"", "\"1.0\""), "$.version");
+        assert!(message.contains("invalid version"), "{message}");
+        let message = invalid_at(&scene("\"version\": \"1.0.3\",", ""), "$");
+        assert_eq!(message, "missing field `version`");
+        let message = invalid_at(&scene("\"Sphere\"", "\"Sphre\""), "$.objects[0].shape");
+        assert!(message.starts_with("unknown variant `Sphre`"), "{message}");
+        let message = invalid_at(
+            &scene("\"radius\": 1.0", "\"radius\": \"1\""),
+            "$.objects[0].shape.Sphere.radius",
+        );
+        assert!(message.contains("invalid type"), "{message}");
+        let message = invalid_at(&scene(", \"fuzz\": 0.2", ""), "$.materials.stone.Metal");
+        assert_eq!(message, "missing field `fuzz`");
+        let message = invalid_at(
+            &scene("\"material\": \"glass\"", "\"materal\": \"glass\""),
+            "$.objects[0].materal",
+        );
+        assert!(message.starts_with("unknown field"), "{message}");
+        invalid_at(&scene("\"lights\": [", "\"lights\": [1, "), "$.lights[0]");
+        invalid_at(&scene("\"width\": 32", "\"width\": -1"), "$.settings.width");
+        // misspelled keys anywhere in the file, down to the key
+        for (replace, with, path) in [
+            ("\"fuzz\"", "\"fuz\"", "$.materials.stone.Metal.fuz"),
+            ("\"filter\"", "\"filtre\"", "$.textures.gray.Image.filtre"),
+            ("\"fov\"", "\"fow\"", "$.camera.Perspective.fow"),
+            (
+                "\"up\": { \"x\"",
+                "\"up\": { \"w\"",
+                "$.camera.Perspective.view.up.w",
+            ),
+            ("\"samples_per_pixel\"", "\"samples\"", "$.settings.samples"),
+            (
+                "\"rotation\"",
+                "\"rotate\"",
+                "$.objects[1].transform.rotate",
+            ),
+            (
+                "\"depth\"",
+                "\"octaves\"",
+                "$.textures.marble.Noise.style.Marble.octaves",
+            ),
+            (
+                "\"radius\": 2.0",
+                "\"radius\": 2.0, \"r\": 2.0",
+                "$.lights[0].shape.Sphere.r",
+            ),
+            (
+                "\"g\": 4.0",
+                "\"green\": 4.0",
+                "$.lights[0].emit.Solid.green",
+            ),
+        ] {
+            let message = invalid_at(&scene(replace, with), path);
+            assert!(message.starts_with("unknown field"), "{message}");
+        }
+        // through a newtype and an option
+        let message = invalid_at(
+            &scene("\"fov\": 40.0", "\"fov\": \"40\""),
+            "$.camera.Perspective.fov",
+        );
+        assert!(message.contains("invalid type"), "{message}");
+        let plane: Value = serde_json::from_str(
+            r#"{ "Plane": { "point": { "x": 0, "y": 0, "z": 0 }, "normal": { "x": 0, "y": 1, "z": 0 },
+                "tangent": { "x": 1, "y": 0, "w": 0 } } }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            from_value::<Shape>(&plane, "$.shape"),
+            Err(Error::Invalid { ref path, .. }) if path == "$.shape.Plane.tangent.w"
+        ));
+        invalid_at(
+            &scene("\"Obj\": \"obj/cube.obj\"", "\"Obj\": 1"),
+            "$.objects[1].shape.Obj",
+        );
+        invalid_at(
+            &scene("\"style\": {", "\"style\": { \"Smooth\": null,"),
+            "$.textures.marble.Noise.style",
+        );
+        assert!(matches!(
+            SceneDescription::parse("{ \"version\": "),
+            Err(Error::Json(_))
+        ));
+        // references resolved when building
+        let build = |json: String| {
+            SceneDescription::parse(&json)
+                .unwrap()
+                .build("assets")
+                .err()
+                .unwrap()
+        };
+        let error = build(scene("\"material\": \"glass\"", "\"material\": \"gold\""));
+        assert_eq!(
+            error.to_string(),
+            "$.objects[0].material: no material named `gold`"
+        );
+        let error = build(scene("\"albedo\": \"marble\"", "\"albedo\": \"granite\""));
+        assert_eq!(
+            error.to_string(),
+            "$.materials.stone: no texture named `granite`"
+        );
+        let error = build(scene(
+            "\"Obj\": \"obj/cube.obj\"",
+            "\"Obj\": \"obj/sphere.obj\"",
+        ));
+        assert!(matches!(error, Error::Invalid { ref path, .. } if path == "$.objects[1].shape"));
+        let error = build(scene("png/gray1.png", "png/gray1.jpg"));
+        assert!(
+            error.to_string().starts_with("$.textures.gray: can't read"),
+            "{error}"
+        );
+        let error = build(scene("png/gray1.png", "img.jpg"));
+        assert_eq!(
+            error.to_string(),
+            "$.textures.gray: img.jpg isn't a known image format"
+        );
+        let error = build(scene("\"scale\": { \"x\": 2.0", "\"scale\": { \"x\": 0.0"));
+        assert_eq!(
+            error.to_string(),
+            "$.objects[1].transform: can't be inverted"
+        );
+        assert!(matches!(load("assets/missing.json"), Err(Error::Io { .. })));
+    }
+}
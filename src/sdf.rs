@@ -0,0 +1,558 @@
+//! signed distance fields, rendered by sphere tracing
+//!
+//! distance functions from https://iquilezles.org/articles/distfunctions/
+use std::ops::Range;
+
+use crate::{
+    aabb::Aabb,
+    linear_algebra::{
+        vector::{dot, vec3},
+        Matrix4, Vector3,
+    },
+    ray::{HitRecord, Hitable, Ray},
+};
+
+pub trait Sdf {
+    /// signed distance from `p` to the surface, negative inside.
+    ///
+    /// it may underestimate the distance but must never overestimate it.
+    fn distance(&self, p: Vector3) -> f32;
+    fn bounding_box(&self) -> Aabb;
+}
+
+impl<T: Sdf + ?Sized> Sdf for std::boxed::Box<T> {
+    fn distance(&self, p: Vector3) -> f32 {
+        self.as_ref().distance(p)
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.as_ref().bounding_box()
+    }
+}
+
+fn unbounded() -> Aabb {
+    Aabb::from_min_max(
+        vec3(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+    )
+}
+
+fn abs(v: Vector3) -> Vector3 {
+    vec3(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+// primitives
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sphere {
+    pub center: Vector3,
+    pub radius: f32,
+}
+impl Sdf for Sphere {
+    fn distance(&self, p: Vector3) -> f32 {
+        (p - self.center).length() - self.radius
+    }
+    fn bounding_box(&self) -> Aabb {
+        let r = vec3(self.radius, self.radius, self.radius);
+        Aabb::from_min_max(self.center - r, self.center + r)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Box {
+    pub center: Vector3,
+    /// half of the size on each axis
+    pub half_size: Vector3,
+}
+impl Sdf for Box {
+    fn distance(&self, p: Vector3) -> f32 {
+        let q = abs(p - self.center) - self.half_size;
+        q.max(Vector3::ZERO).length() + q.x.max(q.y).max(q.z).min(0.0)
+    }
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_min_max(self.center - self.half_size, self.center + self.half_size)
+    }
+}
+
+/// a box with its edges rounded by `radius`, within the same bounds as an unrounded box
+#[derive(Clone, Copy, Debug)]
+pub struct RoundBox {
+    pub center: Vector3,
+    pub half_size: Vector3,
+    pub radius: f32,
+}
+impl Sdf for RoundBox {
+    fn distance(&self, p: Vector3) -> f32 {
+        let r = vec3(self.radius, self.radius, self.radius);
+        let q = abs(p - self.center) - self.half_size + r;
+        q.max(Vector3::ZERO).length() + q.x.max(q.y).max(q.z).min(0.0) - self.radius
+    }
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_min_max(self.center - self.half_size, self.center + self.half_size)
+    }
+}
+
+/// a torus around the Y axis, use `Transformed` to orient it
+#[derive(Clone, Copy, Debug)]
+pub struct Torus {
+    pub center: Vector3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+}
+impl Sdf for Torus {
+    fn distance(&self, p: Vector3) -> f32 {
+        let p = p - self.center;
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        (ring * ring + p.y * p.y).sqrt() - self.minor_radius
+    }
+    fn bounding_box(&self) -> Aabb {
+        let (big_r, r) = (self.major_radius + self.minor_radius, self.minor_radius);
+        let half = vec3(big_r, r, big_r);
+        Aabb::from_min_max(self.center - half, self.center + half)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Capsule {
+    pub a: Vector3,
+    pub b: Vector3,
+    pub radius: f32,
+}
+impl Sdf for Capsule {
+    fn distance(&self, p: Vector3) -> f32 {
+        let (pa, ba) = (p - self.a, self.b - self.a);
+        let h = (dot(pa, ba) / dot(ba, ba)).clamp(0.0, 1.0);
+        (pa - ba * h).length() - self.radius
+    }
+    fn bounding_box(&self) -> Aabb {
+        let r = vec3(self.radius, self.radius, self.radius);
+        Aabb::from_min_max(self.a.min(self.b) - r, self.a.max(self.b) + r)
+    }
+}
+
+/// the half space below the plane
+#[derive(Clone, Copy, Debug)]
+pub struct Plane {
+    /// supposed to be normalized
+    pub normal: Vector3,
+    /// distance from the origin along the normal
+    pub offset: f32,
+}
+impl Sdf for Plane {
+    fn distance(&self, p: Vector3) -> f32 {
+        dot(p, self.normal) - self.offset
+    }
+    fn bounding_box(&self) -> Aabb {
+        unbounded()
+    }
+}
+
+// combinators
+
+/// union blending the surfaces over a distance of `k`, a `k` of 0 is a plain union
+#[derive(Clone, Copy, Debug)]
+pub struct SmoothUnion<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+impl<A: Sdf, B: Sdf> SmoothUnion<A, B> {
+    pub fn new(a: A, b: B, k: f32) -> Self {
+        Self { a, b, k }
+    }
+}
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, p: Vector3) -> f32 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= 0.0 {
+            return a.min(b);
+        }
+        // polynomial smooth min
+        let h = (self.k - (a - b).abs()).max(0.0) / self.k;
+        a.min(b) - h * h * self.k * 0.25
+    }
+    fn bounding_box(&self) -> Aabb {
+        let (a, b) = (self.a.bounding_box(), self.b.bounding_box());
+        let mut aabb = a.union(&b);
+        // the blend can bulge by up to k / 4
+        aabb.expand_by_scalar(self.k * 0.25);
+        aabb
+    }
+}
+
+/// `a` with `b` carved out of it
+#[derive(Clone, Copy, Debug)]
+pub struct Subtraction<A, B> {
+    pub a: A,
+    pub b: B,
+}
+impl<A: Sdf, B: Sdf> Subtraction<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+impl<A: Sdf, B: Sdf> Sdf for Subtraction<A, B> {
+    fn distance(&self, p: Vector3) -> f32 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.a.bounding_box()
+    }
+}
+
+/// infinite copies of `inner` every `period` units, a period of 0 disables the repetition
+/// on that axis. `inner` should fit within one period around the origin.
+#[derive(Clone, Copy, Debug)]
+pub struct Repetition<T> {
+    pub inner: T,
+    pub period: Vector3,
+}
+impl<T: Sdf> Repetition<T> {
+    pub fn new(inner: T, period: Vector3) -> Self {
+        Self { inner, period }
+    }
+}
+impl<T: Sdf> Sdf for Repetition<T> {
+    fn distance(&self, p: Vector3) -> f32 {
+        let wrap = |x: f32, period: f32| {
+            if period > 0.0 {
+                x - period * (x / period).round()
+            } else {
+                x
+            }
+        };
+        let q = vec3(
+            wrap(p.x, self.period.x),
+            wrap(p.y, self.period.y),
+            wrap(p.z, self.period.z),
+        );
+        self.inner.distance(q)
+    }
+    fn bounding_box(&self) -> Aabb {
+        let inner = self.inner.bounding_box();
+        let axis = |period: f32, min: f32, max: f32| {
+            if period > 0.0 {
+                (f32::NEG_INFINITY, f32::INFINITY)
+            } else {
+                (min, max)
+            }
+        };
+        let x = axis(self.period.x, inner.min.x, inner.max.x);
+        let y = axis(self.period.y, inner.min.y, inner.max.y);
+        let z = axis(self.period.z, inner.min.z, inner.max.z);
+        Aabb::from_min_max(vec3(x.0, y.0, z.0), vec3(x.1, y.1, z.1))
+    }
+}
+
+/// `inner` moved by an affine transform
+#[derive(Clone, Copy, Debug)]
+pub struct Transformed<T> {
+    pub inner: T,
+    transform: Matrix4,
+    inverse: Matrix4,
+    /// the smallest scale factor of the transform, distances shrink at most by it
+    scale: f32,
+}
+impl<T: Sdf> Transformed<T> {
+    /// None if the transform can't be inverted
+    pub fn new(inner: T, transform: Matrix4) -> Option<Self> {
+        let inverse = transform.inverse()?;
+        let scale = smallest_singular_value(&transform);
+        Some(Self {
+            inner,
+            transform,
+            inverse,
+            scale,
+        })
+    }
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+}
+impl<T: Sdf> Sdf for Transformed<T> {
+    fn distance(&self, p: Vector3) -> f32 {
+        self.inner.distance(self.inverse.transform_point(p)) * self.scale
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.inner.bounding_box().transform(&self.transform)
+    }
+}
+
+/// the smallest factor by which the linear part of `m` scales a vector, sheared or not.
+///
+/// square root of the smallest eigenvalue of MᵀM, by the closed form for symmetric 3x3 matrices
+/// https://en.wikipedia.org/wiki/Eigenvalue_algorithm#3%C3%973_matrices
+fn smallest_singular_value(m: &Matrix4) -> f32 {
+    let mut b = [[0.0f64; 3]; 3];
+    for (j, row) in b.iter_mut().enumerate() {
+        for (k, b) in row.iter_mut().enumerate() {
+            *b = (0..3)
+                .map(|i| m.get(i, j) as f64 * m.get(i, k) as f64)
+                .sum();
+        }
+    }
+    let q = (b[0][0] + b[1][1] + b[2][2]) / 3.0;
+    let off = b[0][1] * b[0][1] + b[0][2] * b[0][2] + b[1][2] * b[1][2];
+    let p = (((b[0][0] - q).powi(2) + (b[1][1] - q).powi(2) + (b[2][2] - q).powi(2) + 2.0 * off)
+        / 6.0)
+        .sqrt();
+    if p == 0.0 {
+        // a uniform scale
+        return q.sqrt() as f32;
+    }
+    for (i, row) in b.iter_mut().enumerate() {
+        row[i] -= q;
+    }
+    let det = b[0][0] * (b[1][1] * b[2][2] - b[1][2] * b[2][1])
+        - b[0][1] * (b[1][0] * b[2][2] - b[1][2] * b[2][0])
+        + b[0][2] * (b[1][0] * b[2][1] - b[1][1] * b[2][0]);
+    let r = (det / (2.0 * p * p * p)).clamp(-1.0, 1.0);
+    let smallest = q + 2.0 * p * (r.acos() / 3.0 + 2.0 * std::f64::consts::PI / 3.0).cos();
+    smallest.max(0.0).sqrt() as f32
+}
+
+/// renders an `Sdf` as a `Hitable` by sphere tracing
+#[derive(Clone, Copy, Debug)]
+pub struct SdfObject<T> {
+    pub sdf: T,
+    /// a hit is reported within this distance to the surface
+    pub epsilon: f32,
+    pub max_steps: usize,
+    /// rays give up after this distance, for unbounded fields
+    pub max_distance: f32,
+}
+impl<T: Sdf> SdfObject<T> {
+    pub fn new(sdf: T) -> Self {
+        Self {
+            sdf,
+            epsilon: 1e-4,
+            max_steps: 256,
+            max_distance: 1e3,
+        }
+    }
+    /// outward normal from the gradient, estimated with the tetrahedron technique
+    pub fn normal(&self, p: Vector3) -> Vector3 {
+        let h = self.epsilon;
+        [
+            vec3(1.0, -1.0, -1.0),
+            vec3(-1.0, -1.0, 1.0),
+            vec3(-1.0, 1.0, -1.0),
+            vec3(1.0, 1.0, 1.0),
+        ]
+        .into_iter()
+        .map(|k| k * self.sdf.distance(p + k * h))
+        .sum::<Vector3>()
+        .normalize_or_zero()
+    }
+}
+impl<T: Sdf> Hitable for SdfObject<T> {
+    fn hit(&self, ray: Ray, range: Range<f32>) -> Option<HitRecord> {
+        let length = ray.direction.length();
+        // only march where the surface can be
+        let mut bounds = self.sdf.bounding_box();
+        bounds.expand_by_scalar(self.epsilon);
+        let (start, end) = bounds.intersect_ray_interval(ray, range)?;
+        let end = end.min(self.max_distance / length);
+        let mut t = start;
+        for _ in 0..self.max_steps {
+            let point = ray.at(t);
+            // rays starting inside march to the surface the same way
+            let distance = self.sdf.distance(point).abs();
+            if distance < self.epsilon {
+                let mut record = HitRecord {
+                    point,
+                    normal: Vector3::ZERO,
+                    t,
+                    front_face: false,
+                    u: 0.0,
+                    v: 0.0,
+                };
+                record.set_face_normal(&ray, self.normal(point));
+                return Some(record);
+            }
+            t += distance / length;
+            if t >= end {
+                return None;
+            }
+        }
+        None
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.sdf.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn hit(sdf: impl Sdf, origin: Vector3, direction: Vector3) -> Option<HitRecord> {
+        SdfObject::new(sdf).hit(Ray::new(origin, direction), 0.0..f32::INFINITY)
+    }
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn distances() {
+        let p = vec3(3.0, 0.0, 0.0);
+        let sphere = Sphere {
+            center: Vector3::ZERO,
+            radius: 1.0,
+        };
+        assert_eq!(sphere.distance(p), 2.0);
+        assert_eq!(sphere.distance(Vector3::ZERO), -1.0);
+        let b = Box {
+            center: Vector3::ZERO,
+            half_size: vec3(1.0, 1.0, 1.0),
+        };
+        assert_eq!(b.distance(p), 2.0);
+        assert_eq!(b.distance(vec3(2.0, 2.0, 1.0)), 2.0_f32.sqrt());
+        assert_eq!(b.distance(Vector3::ZERO), -1.0);
+        let rounded = RoundBox {
+            center: Vector3::ZERO,
+            half_size: vec3(1.0, 1.0, 1.0),
+            radius: 0.5,
+        };
+        assert_eq!(rounded.distance(p), 2.0);
+        let corner = rounded.distance(vec3(2.0, 2.0, 2.0));
+        assert!((corner - (0.5 * 3.0_f32.sqrt() + 3.0_f32.sqrt() - 0.5)).abs() < 1e-5);
+        let torus = Torus {
+            center: Vector3::ZERO,
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        assert_eq!(torus.distance(p), 0.5);
+        assert_eq!(torus.distance(Vector3::ZERO), 1.5);
+        let capsule = Capsule {
+            a: Vector3::ZERO,
+            b: vec3(0.0, 2.0, 0.0),
+            radius: 0.5,
+        };
+        assert_eq!(capsule.distance(vec3(0.0, 4.0, 0.0)), 1.5);
+        assert_eq!(capsule.distance(vec3(1.0, 1.0, 0.0)), 0.5);
+        let plane = Plane {
+            normal: Vector3::UNIT_Y,
+            offset: 1.0,
+        };
+        assert_eq!(plane.distance(p), -1.0);
+    }
+    #[test]
+    fn combinators() {
+        let a = Sphere {
+            center: vec3(-1.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        let b = Sphere {
+            center: vec3(1.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        // the smooth union fills the gap where the spheres touch
+        let p = vec3(0.0, 0.3, 0.0);
+        assert!(SmoothUnion::new(a, b, 0.0).distance(p) > 0.0);
+        assert!(SmoothUnion::new(a, b, 0.5).distance(p) < 0.0);
+        let carved = Subtraction::new(a, b);
+        assert_eq!(carved.distance(vec3(0.5, 0.0, 0.0)), 0.5);
+        assert_eq!(carved.distance(vec3(-1.5, 0.0, 0.0)), -0.5);
+        let repeated = Repetition::new(
+            Sphere {
+                center: Vector3::ZERO,
+                radius: 1.0,
+            },
+            vec3(4.0, 0.0, 0.0),
+        );
+        assert_eq!(repeated.distance(vec3(40.0, 0.0, 0.0)), -1.0);
+        assert_eq!(repeated.distance(vec3(42.0, 0.0, 0.0)), 1.0);
+        assert_eq!(repeated.distance(vec3(40.0, 3.0, 0.0)), 2.0);
+        assert_eq!(repeated.bounding_box().min.y, -1.0);
+        assert_eq!(repeated.bounding_box().min.x, f32::NEG_INFINITY);
+    }
+    #[test]
+    fn transforms() {
+        let torus = Torus {
+            center: Vector3::ZERO,
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        // standing up, facing Z, moved and scaled twice as large
+        let m = Matrix4::translation(vec3(0.0, 0.0, 5.0))
+            * Matrix4::rotation_x(FRAC_PI_2)
+            * Matrix4::scale(vec3(2.0, 2.0, 2.0));
+        let transformed = Transformed::new(torus, m).unwrap();
+        assert!((transformed.distance(vec3(0.0, 0.0, 5.0)) - 3.0).abs() < 1e-5);
+        assert!((transformed.distance(vec3(0.0, 4.0, 5.0)) + 1.0).abs() < 1e-5);
+        let aabb = transformed.bounding_box();
+        assert_close(aabb.min, vec3(-5.0, -5.0, 4.0));
+        assert_close(aabb.max, vec3(5.0, 5.0, 6.0));
+        assert!(Transformed::new(torus, Matrix4::scale(Vector3::ZERO)).is_none());
+
+        // sheared, the columns have length 1 and sqrt(2) but some directions shrink more
+        let sphere = Sphere {
+            center: Vector3::ZERO,
+            radius: 1.0,
+        };
+        let shear = Matrix4::from_rows([
+            [1.0, 1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let sheared = Transformed::new(sphere, shear).unwrap();
+        let golden = (5.0_f32.sqrt() - 1.0) / 2.0;
+        assert!((sheared.distance(vec3(3.0, 0.0, 0.0)) - 2.0 * golden).abs() < 1e-5);
+        // never more than the distance to the points of the surface
+        for i in 0..64 {
+            let (sin, cos) = (i as f32 / 64.0 * std::f32::consts::TAU).sin_cos();
+            let surface = shear.transform_point(vec3(cos, sin, 0.0));
+            let p = vec3(0.0, 2.5, 0.0);
+            assert!(sheared.distance(p) <= p.distance_to(&surface) + 1e-5);
+        }
+        let scaled = Transformed::new(sphere, Matrix4::scale(vec3(2.0, 3.0, 0.5))).unwrap();
+        assert!((scaled.scale - 0.5).abs() < 1e-6);
+    }
+    #[test]
+    fn sphere_tracing() {
+        let sphere = Sphere {
+            center: Vector3::ZERO,
+            radius: 1.0,
+        };
+        let rec = hit(sphere, vec3(-5.0, 0.0, 0.0), Vector3::UNIT_X * 2.0).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-3);
+        assert_close(rec.point, vec3(-1.0, 0.0, 0.0));
+        assert_close(rec.normal, -Vector3::UNIT_X);
+        assert!(rec.front_face);
+        // from the inside
+        let rec = hit(sphere, Vector3::ZERO, Vector3::UNIT_Y).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-3);
+        assert!(!rec.front_face);
+        assert_close(rec.normal, -Vector3::UNIT_Y);
+        assert!(hit(sphere, vec3(-5.0, 2.0, 0.0), Vector3::UNIT_X).is_none());
+        // unbounded fields stop at the max distance
+        let repeated = Repetition::new(sphere, vec3(4.0, 0.0, 4.0));
+        let rec = hit(repeated, vec3(2.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-2);
+        assert!(hit(repeated, vec3(0.0, 2.0, 0.0), Vector3::UNIT_X).is_none());
+    }
+    #[test]
+    fn with_geometry() {
+        use crate::geometry;
+        let object = SdfObject::new(Sphere {
+            center: vec3(0.0, 0.0, 2.0),
+            radius: 1.0,
+        });
+        let sphere = geometry::Sphere::new(vec3(0.0, 0.0, -2.0), 1.0);
+        let scene: [&dyn Hitable; 2] = [&object, &sphere];
+        let ray = Ray::new(vec3(0.0, 0.0, 5.0), -Vector3::UNIT_Z);
+        let closest = scene
+            .iter()
+            .filter_map(|o| o.hit(ray, 0.0..f32::INFINITY))
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+            .unwrap();
+        assert!((closest.t - 2.0).abs() < 1e-3);
+        // both agree on the surface of the same sphere
+        let analytic = geometry::Sphere::new(vec3(0.0, 0.0, 2.0), 1.0);
+        let expected = analytic.hit(ray, 0.0..f32::INFINITY).unwrap();
+        assert_close(closest.point, expected.point);
+        assert_close(closest.normal, expected.normal);
+    }
+}
@@ -1,12 +1,15 @@
 use std::fmt::Display;
 
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
 /// Semantic Versioning
 ///
 /// features:
 /// 1. convert strings to SemVer
 /// 2. compare 2 versions' using >, <, or ==
 /// 3. into string
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// 4. serialized as a string like "1.2.3"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SemVer {
     // ordering is important since Ord trait relies on this
     pub major: u32,
@@ -84,6 +87,18 @@ impl Display for SemVer {
     }
 }
 
+impl Serialize for SemVer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for SemVer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        SemVer::parse(&s).map_err(|_| de::Error::custom(format!("invalid version `{s}`")))
+    }
+}
+
 impl Default for SemVer {
     fn default() -> Self {
         Self {
@@ -149,6 +164,15 @@ mod test {
         );
     }
 
+    #[test]
+    fn serde() {
+        let version: SemVer = serde_json::from_str("\"1.4.2\"").unwrap();
+        assert_eq!(version, SemVer::new(1, 4, 2));
+        assert_eq!(serde_json::to_string(&version).unwrap(), "\"1.4.2\"");
+        let error = serde_json::from_str::<SemVer>("\"1.x\"").unwrap_err();
+        assert!(error.to_string().starts_with("invalid version `1.x`"));
+    }
+
     #[test]
     fn compare() {
         assert!(SemVer::new(0, 1, 2) > SemVer::new(0, 0, 1));
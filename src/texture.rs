@@ -0,0 +1,460 @@
+//! colors varying over surfaces, looked up by the surface coordinates and the hit point
+//!
+//! https://raytracing.github.io/books/RayTracingTheNextWeek.html#solidtextures
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::Color,
+    image::{Filter, Image},
+    interpolate::lerp::lerp,
+    linear_algebra::{vector::dot, Vector3},
+    sampling,
+};
+
+pub trait Texture {
+    /// the color at the surface coordinates `u`, `v` of `point`, as in `HitRecord`
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color;
+}
+impl Texture for Color {
+    fn value(&self, _u: f32, _v: f32, _point: Vector3) -> Color {
+        *self
+    }
+}
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        (**self).value(u, v, point)
+    }
+}
+
+/// squares alternating between two textures in the surface coordinates
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Checker<T> {
+    pub even: T,
+    pub odd: T,
+    /// number of squares along u and along v
+    pub squares: f32,
+}
+impl<T: Texture> Texture for Checker<T> {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        let (i, j) = ((u * self.squares).floor(), (v * self.squares).floor());
+        if (i + j) as i64 & 1 == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+/// cubes alternating between two textures in space, the same on any shape
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolidChecker<T> {
+    pub even: T,
+    pub odd: T,
+    /// the side of the cubes
+    pub size: f32,
+}
+impl<T: Texture> Texture for SolidChecker<T> {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        let p = point / self.size;
+        if (p.x.floor() + p.y.floor() + p.z.floor()) as i64 & 1 == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+/// how surface coordinates outside [0, 1] map onto an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    /// the edge pixels are stretched
+    Clamp,
+}
+impl Wrap {
+    /// the pixel index of `i` in a row or column of `n` pixels
+    fn index(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let index = match self {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::MirroredRepeat => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+            Wrap::Clamp => i.clamp(0, n - 1),
+        };
+        index as usize
+    }
+}
+
+/// an image stretched over the surface coordinates, u going right and v going up
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+    pub image: Image<Color>,
+    pub wrap: Wrap,
+    pub filter: Filter,
+}
+impl ImageTexture {
+    /// panics on an empty image
+    pub fn new(image: Image<Color>, wrap: Wrap, filter: Filter) -> Self {
+        assert!(image.width() > 0 && image.height() > 0, "empty image");
+        Self {
+            image,
+            wrap,
+            filter,
+        }
+    }
+    fn pixel(&self, x: i64, y: i64) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        *self
+            .image
+            .get_pixel(self.wrap.index(x, width), self.wrap.index(y, height))
+    }
+}
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: Vector3) -> Color {
+        // in pixels, rows from the top
+        let x = u * self.image.width() as f32;
+        let y = (1.0 - v) * self.image.height() as f32;
+        match self.filter {
+            Filter::Nearest => self.pixel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // between the 4 closest pixel centers, wrapped like the pixels themselves
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = lerp(self.pixel(x0, y0), self.pixel(x0 + 1, y0), tx);
+                let bottom = lerp(self.pixel(x0, y0 + 1), self.pixel(x0 + 1, y0 + 1), tx);
+                lerp(top, bottom, ty)
+            }
+        }
+    }
+}
+
+const PERLIN_SIZE: usize = 256;
+
+/// gradient noise, smooth and random looking, between about -1 and 1
+///
+/// https://raytracing.github.io/books/RayTracingTheNextWeek.html#perlinnoise
+#[derive(Clone, Debug)]
+pub struct Perlin {
+    gradients: Vec<Vector3>,
+    permutations: [Vec<usize>; 3],
+}
+impl Perlin {
+    /// the same seed gives the same noise
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..PERLIN_SIZE)
+            .map(|_| sampling::unit_sphere(&mut rng))
+            .collect();
+        let permutations = [(); 3].map(|_| {
+            let mut permutation: Vec<usize> = (0..PERLIN_SIZE).collect();
+            permutation.shuffle(&mut rng);
+            permutation
+        });
+        Self {
+            gradients,
+            permutations,
+        }
+    }
+    pub fn noise(&self, point: Vector3) -> f32 {
+        let floor = [point.x.floor(), point.y.floor(), point.z.floor()];
+        let cell = floor.map(|f| f as i64);
+        let fraction = [point.x - floor[0], point.y - floor[1], point.z - floor[2]];
+        // hermite smoothed, the noise has no visible grid
+        let [u, v, w] = fraction.map(|t| t * t * (3.0 - 2.0 * t));
+        let mut sum = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut hash = 0;
+            for axis in 0..3 {
+                let i = (cell[axis] + offset[axis] as i64) as usize & (PERLIN_SIZE - 1);
+                hash ^= self.permutations[axis][i];
+            }
+            let weight = Vector3::new(
+                fraction[0] - offset[0] as f32,
+                fraction[1] - offset[1] as f32,
+                fraction[2] - offset[2] as f32,
+            );
+            let [wu, wv, ww] = [(u, offset[0]), (v, offset[1]), (w, offset[2])].map(|(t, o)| {
+                if o == 1 {
+                    t
+                } else {
+                    1.0 - t
+                }
+            });
+            sum += wu * wv * ww * dot(self.gradients[hash], weight);
+        }
+        sum
+    }
+    /// `depth` octaves of noise, each twice as fine and half as strong, in [0, about 1]
+    pub fn turbulence(&self, point: Vector3, depth: u32) -> f32 {
+        let mut sum = 0.0;
+        let (mut point, mut weight) = (point, 1.0);
+        for _ in 0..depth {
+            sum += weight * self.noise(point);
+            weight *= 0.5;
+            point = point * 2.0;
+        }
+        sum.abs()
+    }
+}
+
+/// how the noise is turned into a shade
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub enum NoiseStyle {
+    /// the noise itself
+    Smooth,
+    /// `depth` octaves of noise
+    Turbulence { depth: u32 },
+    /// stripes along z disturbed by turbulence
+    Marble { depth: u32 },
+}
+
+/// a color shaded by perlin noise
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "NoiseParams", into = "NoiseParams")]
+pub struct Noise {
+    pub color: Color,
+    /// the frequency of the noise, bigger for finer details
+    pub scale: f32,
+    pub style: NoiseStyle,
+    seed: u64,
+    perlin: Perlin,
+}
+impl Noise {
+    pub fn new(color: Color, scale: f32, style: NoiseStyle, seed: u64) -> Self {
+        Self {
+            color,
+            scale,
+            style,
+            seed,
+            perlin: Perlin::new(seed),
+        }
+    }
+}
+impl Texture for Noise {
+    fn value(&self, _u: f32, _v: f32, point: Vector3) -> Color {
+        let p = point * self.scale;
+        let shade = match self.style {
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.perlin.noise(p)),
+            NoiseStyle::Turbulence { depth } => self.perlin.turbulence(p, depth),
+            NoiseStyle::Marble { depth } => {
+                0.5 * (1.0 + (p.z + 10.0 * self.perlin.turbulence(p, depth)).sin())
+            }
+        };
+        let color = self.color * shade;
+        Color {
+            a: self.color.a,
+            ..color
+        }
+    }
+}
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseParams {
+    color: Color,
+    scale: f32,
+    style: NoiseStyle,
+    #[serde(default)]
+    seed: u64,
+}
+impl From<NoiseParams> for Noise {
+    fn from(params: NoiseParams) -> Self {
+        Self::new(params.color, params.scale, params.style, params.seed)
+    }
+}
+impl From<Noise> for NoiseParams {
+    fn from(noise: Noise) -> Self {
+        Self {
+            color: noise.color,
+            scale: noise.scale,
+            style: noise.style,
+            seed: noise.seed,
+        }
+    }
+}
+
+/// any texture, as found in materials
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum AnyTexture {
+    Solid(Color),
+    Checker(Checker<Box<AnyTexture>>),
+    SolidChecker(SolidChecker<Box<AnyTexture>>),
+    /// images are loaded apart, they aren't part of the serialized texture
+    #[serde(skip)]
+    Image(Arc<ImageTexture>),
+    Noise(Noise),
+}
+impl Texture for AnyTexture {
+    fn value(&self, u: f32, v: f32, point: Vector3) -> Color {
+        match self {
+            AnyTexture::Solid(color) => *color,
+            AnyTexture::Checker(checker) => checker.value(u, v, point),
+            AnyTexture::SolidChecker(checker) => checker.value(u, v, point),
+            AnyTexture::Image(image) => image.value(u, v, point),
+            AnyTexture::Noise(noise) => noise.value(u, v, point),
+        }
+    }
+}
+impl From<Color> for AnyTexture {
+    fn from(color: Color) -> Self {
+        AnyTexture::Solid(color)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::rgba, linear_algebra::vector::vec3};
+
+    fn red() -> Color {
+        rgba(1.0, 0.0, 0.0, 1.0)
+    }
+
+    #[test]
+    fn checkers() {
+        let checker = Checker {
+            even: red(),
+            odd: Color::WHITE,
+            squares: 4.0,
+        };
+        assert_eq!(checker.value(0.1, 0.1, Vector3::ZERO).g, 0.0);
+        assert_eq!(checker.value(0.3, 0.1, Vector3::ZERO).g, 1.0);
+        assert_eq!(checker.value(0.3, 0.3, Vector3::ZERO).g, 0.0);
+        // continued outside [0, 1]
+        assert_eq!(checker.value(-0.1, 0.1, Vector3::ZERO).g, 1.0);
+        let solid = SolidChecker {
+            even: red(),
+            odd: Color::WHITE,
+            size: 0.5,
+        };
+        assert_eq!(solid.value(0.0, 0.0, vec3(0.1, 0.1, 0.1)).g, 0.0);
+        assert_eq!(solid.value(0.0, 0.0, vec3(0.1, 0.6, 0.1)).g, 1.0);
+        assert_eq!(solid.value(0.0, 0.0, vec3(-0.1, 0.6, 0.1)).g, 0.0);
+        // nested
+        let nested = AnyTexture::Checker(Checker {
+            even: Box::new(AnyTexture::SolidChecker(SolidChecker {
+                even: Box::new(red().into()),
+                odd: Box::new(Color::BLACK.into()),
+                size: 1.0,
+            })),
+            odd: Box::new(Color::WHITE.into()),
+            squares: 1.0,
+        });
+        assert_eq!(nested.value(0.5, 0.5, vec3(0.5, 0.5, 0.5)).r, 1.0);
+        assert_eq!(nested.value(0.5, 0.5, vec3(1.5, 0.5, 0.5)).r, 0.0);
+        assert_eq!(nested.value(1.5, 0.5, vec3(1.5, 0.5, 0.5)).g, 1.0);
+    }
+    #[test]
+    fn wraps() {
+        assert_eq!(
+            (-1..7)
+                .map(|i| Wrap::Repeat.index(i, 3))
+                .collect::<Vec<_>>(),
+            [2, 0, 1, 2, 0, 1, 2, 0]
+        );
+        assert_eq!(
+            (-1..7)
+                .map(|i| Wrap::MirroredRepeat.index(i, 3))
+                .collect::<Vec<_>>(),
+            [0, 0, 1, 2, 2, 1, 0, 0]
+        );
+        assert_eq!(
+            (-1..7).map(|i| Wrap::Clamp.index(i, 3)).collect::<Vec<_>>(),
+            [0, 0, 1, 2, 2, 2, 2, 2]
+        );
+    }
+    #[test]
+    fn image() {
+        // black on the left, white on the right, red at the bottom right
+        let pixels = vec![Color::BLACK, Color::WHITE, Color::BLACK, red()];
+        let image = Image::from_pixels(2, 2, pixels).unwrap();
+        let nearest = ImageTexture::new(image.clone(), Wrap::Repeat, Filter::Nearest);
+        assert_eq!(nearest.value(0.25, 0.75, Vector3::ZERO).g, 0.0);
+        assert_eq!(nearest.value(0.75, 0.75, Vector3::ZERO).g, 1.0);
+        assert_eq!(nearest.value(0.75, 0.25, Vector3::ZERO).g, 0.0);
+        assert_eq!(nearest.value(0.75, 0.25, Vector3::ZERO).r, 1.0);
+        assert_eq!(nearest.value(1.25, 1.75, Vector3::ZERO).g, 0.0);
+        let bilinear = ImageTexture::new(image.clone(), Wrap::Repeat, Filter::Bilinear);
+        // exactly on the pixel centers, then between them
+        assert_eq!(bilinear.value(0.75, 0.75, Vector3::ZERO).g, 1.0);
+        assert_eq!(bilinear.value(0.5, 0.75, Vector3::ZERO).g, 0.5);
+        // across the edge, halfway to the pixel on the other side
+        assert_eq!(bilinear.value(0.0, 0.75, Vector3::ZERO).g, 0.5);
+        let clamped = ImageTexture::new(image, Wrap::Clamp, Filter::Bilinear);
+        assert_eq!(clamped.value(0.0, 0.75, Vector3::ZERO).g, 0.0);
+        assert_eq!(clamped.value(2.0, 0.75, Vector3::ZERO).g, 1.0);
+    }
+    #[test]
+    fn perlin() {
+        let perlin = Perlin::new(1);
+        // zero on the lattice, smooth and in [-1, 1] in between
+        assert_eq!(perlin.noise(vec3(3.0, -2.0, 7.0)), 0.0);
+        let mut previous = perlin.noise(vec3(0.5, 0.5, 0.5));
+        let mut varies = false;
+        for i in 1..1000 {
+            let n = perlin.noise(vec3(0.5 + i as f32 * 0.001, 0.5, 0.5));
+            assert!(n.abs() <= 1.0);
+            assert!((n - previous).abs() < 0.01);
+            varies |= n != previous;
+            previous = n;
+        }
+        assert!(varies);
+        let again = Perlin::new(1);
+        let other = Perlin::new(2);
+        let p = vec3(1.3, 2.7, -0.4);
+        assert_eq!(perlin.noise(p), again.noise(p));
+        assert_ne!(perlin.noise(p), other.noise(p));
+        assert!(perlin.turbulence(p, 7) >= 0.0);
+        assert_eq!(perlin.turbulence(p, 1), perlin.noise(p).abs());
+    }
+    #[test]
+    fn noise_and_serde() {
+        let json = r#"[
+            { "Solid": { "r": 0.5, "g": 0.5, "b": 0.5, "a": 1.0 } },
+            { "Noise": {
+                "color": { "r": 1.0, "g": 1.0, "b": 1.0, "a": 1.0 },
+                "scale": 4.0,
+                "style": { "Marble": { "depth": 7 } }
+            } },
+            { "Checker": {
+                "even": { "Solid": { "r": 0.0, "g": 0.0, "b": 0.0, "a": 1.0 } },
+                "odd": { "Solid": { "r": 1.0, "g": 1.0, "b": 1.0, "a": 1.0 } },
+                "squares": 10.0
+            } }
+        ]"#;
+        let textures: Vec<AnyTexture> = serde_json::from_str(json).unwrap();
+        assert_eq!(textures[0].value(0.0, 0.0, Vector3::ZERO).g, 0.5);
+        for i in 0..100 {
+            let c = textures[1].value(0.0, 0.0, vec3(i as f32 * 0.37, 0.2, i as f32 * 0.11));
+            assert!((0.0..=1.0).contains(&c.r) && c.r == c.b && c.a == 1.0);
+        }
+        assert_eq!(textures[2].value(0.15, 0.05, Vector3::ZERO).g, 1.0);
+        // the permutations aren't serialized, only the seed they come from
+        let again: AnyTexture =
+            serde_json::from_str(&serde_json::to_string(&textures[1]).unwrap()).unwrap();
+        let p = vec3(0.3, 0.2, 0.1);
+        assert_eq!(again.value(0.0, 0.0, p).r, textures[1].value(0.0, 0.0, p).r);
+        let image = Image::new(1, 1, Color::WHITE);
+        let image = AnyTexture::Image(Arc::new(ImageTexture::new(
+            image,
+            Wrap::Clamp,
+            Filter::Nearest,
+        )));
+        assert!(serde_json::to_string(&image).is_err());
+    }
+}